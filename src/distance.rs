@@ -0,0 +1,119 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// A trait for key types with a notion of (non-negative) distance between two values.
+/// This trait powers the gap-related convenience methods on `SortedMapExt` and `SortedSetExt`.
+pub trait KeyDistance {
+    /// The type used to measure the distance between two keys.
+    type Distance: Ord;
+
+    /// Returns the distance between this key and `other`. The distance must not depend on the
+    /// order of the two operands.
+    fn distance(&self, other: &Self) -> Self::Distance;
+}
+
+/// A trait for key types whose values can be enumerated one step at a time, such as the
+/// integers. This trait powers the density-related convenience methods on `SortedMapExt` and
+/// `SortedSetExt`.
+pub trait KeyStep: Ord + Sized {
+    /// Returns the key immediately following this one, or `None` if this is the greatest key.
+    fn successor(&self) -> Option<Self>;
+
+    /// Returns the key `steps` steps after this one, or `None` if there is no such key.
+    fn forward(&self, steps: usize) -> Option<Self>;
+
+    /// Returns the number of steps needed to get from this key to `other`, or `None` if `other`
+    /// is less than this key or the number of steps does not fit in a `usize`.
+    fn steps_between(&self, other: &Self) -> Option<usize>;
+}
+
+macro_rules! key_distance_impl {
+    ($($typ:ty => $dist:ty),*) => ($(
+        impl KeyDistance for $typ {
+            type Distance = $dist;
+
+            fn distance(&self, other: &$typ) -> $dist {
+                if *self < *other {
+                    (*other as $dist).wrapping_sub(*self as $dist)
+                } else {
+                    (*self as $dist).wrapping_sub(*other as $dist)
+                }
+            }
+        }
+
+        impl KeyStep for $typ {
+            fn successor(&self) -> Option<$typ> {
+                self.checked_add(1)
+            }
+
+            fn forward(&self, steps: usize) -> Option<$typ> {
+                if steps as u64 > !(0 as $dist) as u64 {
+                    return None;
+                }
+                let key = (*self as $dist).wrapping_add(steps as $dist) as $typ;
+                if key < *self { None } else { Some(key) }
+            }
+
+            fn steps_between(&self, other: &$typ) -> Option<usize> {
+                if *other < *self {
+                    return None;
+                }
+                let steps = (*other as $dist).wrapping_sub(*self as $dist);
+                if steps as u64 > usize::MAX as u64 {
+                    None
+                } else {
+                    Some(steps as usize)
+                }
+            }
+        }
+    )*)
+}
+
+key_distance_impl!(u8 => u8, u16 => u16, u32 => u32, u64 => u64, usize => usize,
+                   i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize);
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyDistance, KeyStep};
+
+    #[test]
+    fn test_distance_unsigned() {
+        assert_eq!(3u32.distance(&10), 7);
+        assert_eq!(10u32.distance(&3), 7);
+        assert_eq!(0u8.distance(&255), 255);
+    }
+
+    #[test]
+    fn test_distance_signed() {
+        assert_eq!((-3i32).distance(&4), 7u32);
+        assert_eq!(4i32.distance(&-3), 7u32);
+        assert_eq!((-128i8).distance(&127), 255u8);
+    }
+
+    #[test]
+    fn test_successor() {
+        assert_eq!(3u32.successor(), Some(4));
+        assert_eq!((-1i32).successor(), Some(0));
+        assert_eq!(255u8.successor(), None);
+    }
+
+    #[test]
+    fn test_forward() {
+        assert_eq!(3u32.forward(0), Some(3));
+        assert_eq!(3u32.forward(7), Some(10));
+        assert_eq!((-3i8).forward(130), Some(127));
+        assert_eq!((-3i8).forward(131), None);
+        assert_eq!((-128i8).forward(255), Some(127));
+        assert_eq!(250u8.forward(300), None);
+    }
+
+    #[test]
+    fn test_steps_between() {
+        assert_eq!(3u32.steps_between(&3), Some(0));
+        assert_eq!((-2i64).steps_between(&5), Some(7));
+        assert_eq!(5u8.steps_between(&3), None);
+    }
+}
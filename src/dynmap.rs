@@ -0,0 +1,394 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::ops::Bound;
+
+use crate::DebugKey;
+use crate::sortedmap::SortedMapNav;
+
+/// An object-safe counterpart of `SortedMapNav`, for holding sorted maps of different types
+/// behind a `Box<dyn DynSortedMap<K, V>>` and choosing between them at runtime. It is implemented
+/// for every `SortedMapNav` implementer, and so for every `SortedMapExt` implementer.
+///
+/// Each method is its `SortedMapNav` counterpart with a `dyn_` prefix, so that a concrete map can
+/// have both traits in scope without its method calls becoming ambiguous. The range methods
+/// return boxed iterators in place of `SortedMapNav`'s associated iterator types, and the
+/// navigation methods take a `&K` rather than any borrowed form of the key type.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use sorted_collections::DynSortedMap;
+///
+/// fn main() {
+///     let map: BTreeMap<u32, u32> =
+///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+///     assert_eq!(map.dyn_ceiling(&3), Some(&3u32));
+///     assert_eq!(map.dyn_range_iter(&2, &4).map(|(&k, _)| k).collect::<Vec<u32>>(),
+///         vec![2u32, 3]);
+/// }
+/// ```
+pub trait DynSortedMap<K, V>
+    where K: Ord + DebugKey
+{
+    /// Returns an immutable reference to the first (least) key currently in this map.
+    /// Returns `None` if this map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3)].into_iter().collect();
+    ///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     assert_eq!(map.dyn_first(), Some(&1u32));
+    /// }
+    /// ```
+    fn dyn_first(&self) -> Option<&K>;
+
+    /// Returns an immutable reference to the last (greatest) key currently in this map.
+    /// Returns `None` if this map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3)].into_iter().collect();
+    ///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     assert_eq!(map.dyn_last(), Some(&3u32));
+    /// }
+    /// ```
+    fn dyn_last(&self) -> Option<&K>;
+
+    /// Returns an immutable reference to the least key in this map greater than or equal to
+    /// `key`. Returns `None` if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (3, 3), (5, 5)].into_iter().collect();
+    ///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     assert_eq!(map.dyn_ceiling(&2), Some(&3u32));
+    ///     assert_eq!(map.dyn_ceiling(&6), None);
+    /// }
+    /// ```
+    fn dyn_ceiling(&self, key: &K) -> Option<&K>;
+
+    /// Returns an immutable reference to the greatest key in this map less than or equal to
+    /// `key`. Returns `None` if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (3, 3), (5, 5)].into_iter().collect();
+    ///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     assert_eq!(map.dyn_floor(&4), Some(&3u32));
+    ///     assert_eq!(map.dyn_floor(&0), None);
+    /// }
+    /// ```
+    fn dyn_floor(&self, key: &K) -> Option<&K>;
+
+    /// Returns an immutable reference to the least key in this map strictly greater than `key`.
+    /// Returns `None` if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (3, 3), (5, 5)].into_iter().collect();
+    ///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     assert_eq!(map.dyn_higher(&3), Some(&5u32));
+    ///     assert_eq!(map.dyn_higher(&5), None);
+    /// }
+    /// ```
+    fn dyn_higher(&self, key: &K) -> Option<&K>;
+
+    /// Returns an immutable reference to the greatest key in this map strictly less than `key`.
+    /// Returns `None` if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (3, 3), (5, 5)].into_iter().collect();
+    ///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     assert_eq!(map.dyn_lower(&3), Some(&1u32));
+    ///     assert_eq!(map.dyn_lower(&1), None);
+    /// }
+    /// ```
+    fn dyn_lower(&self, key: &K) -> Option<&K>;
+
+    /// Returns a boxed iterator over pairs of immutable key-value references into this map,
+    /// with the pairs being iterated being those whose keys are in the range [from_key, to_key).
+    /// The iterator is empty if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3)].into_iter().collect();
+    ///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     assert_eq!(map.dyn_range_iter(&1, &3).map(|(&k, &v)| (k, v))
+    ///                   .collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2)]);
+    /// }
+    /// ```
+    fn dyn_range_iter(&self, from_key: &K, to_key: &K)
+                      -> Box<dyn DoubleEndedIterator<Item=(&K, &V)> + '_>;
+
+    /// Returns a boxed iterator over pairs of immutable-key/mutable-value references into this
+    /// map, with the pairs being iterated being those whose keys are in the range
+    /// [from_key, to_key). The iterator is empty if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3)].into_iter().collect();
+    ///     let mut map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     for (_, v) in map.dyn_range_iter_mut(&2, &4) {
+    ///         *v += 1;
+    ///     }
+    ///     assert_eq!(map.dyn_range_iter(&1, &4).map(|(_, &v)| v).collect::<Vec<u32>>(),
+    ///         vec![1u32, 3, 4]);
+    /// }
+    /// ```
+    fn dyn_range_iter_mut(&mut self, from_key: &K, to_key: &K)
+                          -> Box<dyn DoubleEndedIterator<Item=(&K, &mut V)> + '_>;
+
+    /// Returns a boxed iterator over pairs of immutable key-value references into this map, with
+    /// the pairs being iterated being those whose keys lie between the bounds `min` and `max`,
+    /// either of which may be inclusive, exclusive or unbounded. The iterator is empty if the
+    /// bounds describe no keys, as when `min` lies above `max`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::ops::Bound::{Excluded, Unbounded};
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3)].into_iter().collect();
+    ///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     assert_eq!(map.dyn_range_iter_bounded(Excluded(&1), Unbounded).map(|(&k, _)| k)
+    ///                   .collect::<Vec<u32>>(), vec![2u32, 3]);
+    /// }
+    /// ```
+    fn dyn_range_iter_bounded(&self, min: Bound<&K>, max: Bound<&K>)
+                              -> Box<dyn DoubleEndedIterator<Item=(&K, &V)> + '_>;
+
+    /// Returns a boxed iterator over pairs of immutable-key/mutable-value references into this
+    /// map, with the pairs being iterated being those whose keys lie between the bounds `min`
+    /// and `max`, either of which may be inclusive, exclusive or unbounded. The iterator is empty
+    /// if the bounds describe no keys, as when `min` lies above `max`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::ops::Bound::{Included, Unbounded};
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3)].into_iter().collect();
+    ///     let mut map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     for (_, v) in map.dyn_range_iter_mut_bounded(Unbounded, Included(&2)) {
+    ///         *v += 1;
+    ///     }
+    ///     assert_eq!(map.dyn_range_iter(&1, &4).map(|(_, &v)| v).collect::<Vec<u32>>(),
+    ///         vec![2u32, 3, 3]);
+    /// }
+    /// ```
+    fn dyn_range_iter_mut_bounded(&mut self, min: Bound<&K>, max: Bound<&K>)
+                                  -> Box<dyn DoubleEndedIterator<Item=(&K, &mut V)> + '_>;
+
+    /// Returns a boxed iterator over pairs of immutable key-value references into this map,
+    /// with the pairs being iterated being those whose keys are in the range [from_key, to_key].
+    /// The iterator is empty if `from_key` is greater than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3)].into_iter().collect();
+    ///     let map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     assert_eq!(map.dyn_range_iter_inclusive(&2, &3).map(|(&k, _)| k).collect::<Vec<u32>>(),
+    ///         vec![2u32, 3]);
+    /// }
+    /// ```
+    fn dyn_range_iter_inclusive(&self, from_key: &K, to_key: &K)
+                                -> Box<dyn DoubleEndedIterator<Item=(&K, &V)> + '_>;
+
+    /// Returns a boxed iterator over pairs of immutable-key/mutable-value references into this
+    /// map, with the pairs being iterated being those whose keys are in the range
+    /// [from_key, to_key]. The iterator is empty if `from_key` is greater than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::DynSortedMap;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3)].into_iter().collect();
+    ///     let mut map: Box<dyn DynSortedMap<u32, u32>> = Box::new(map);
+    ///     for (_, v) in map.dyn_range_iter_mut_inclusive(&2, &3) {
+    ///         *v += 1;
+    ///     }
+    ///     assert_eq!(map.dyn_range_iter(&1, &4).map(|(_, &v)| v).collect::<Vec<u32>>(),
+    ///         vec![1u32, 3, 4]);
+    /// }
+    /// ```
+    fn dyn_range_iter_mut_inclusive(&mut self, from_key: &K, to_key: &K)
+                                    -> Box<dyn DoubleEndedIterator<Item=(&K, &mut V)> + '_>;
+}
+
+impl<K, V, M> DynSortedMap<K, V> for M
+    where K: Ord + DebugKey, M: SortedMapNav<K, V>
+{
+    fn dyn_first(&self) -> Option<&K> {
+        SortedMapNav::first(self)
+    }
+
+    fn dyn_last(&self) -> Option<&K> {
+        SortedMapNav::last(self)
+    }
+
+    fn dyn_ceiling(&self, key: &K) -> Option<&K> {
+        SortedMapNav::ceiling(self, key)
+    }
+
+    fn dyn_floor(&self, key: &K) -> Option<&K> {
+        SortedMapNav::floor(self, key)
+    }
+
+    fn dyn_higher(&self, key: &K) -> Option<&K> {
+        SortedMapNav::higher(self, key)
+    }
+
+    fn dyn_lower(&self, key: &K) -> Option<&K> {
+        SortedMapNav::lower(self, key)
+    }
+
+    fn dyn_range_iter(&self, from_key: &K, to_key: &K)
+                      -> Box<dyn DoubleEndedIterator<Item=(&K, &V)> + '_> {
+        Box::new(SortedMapNav::range_iter(self, from_key, to_key))
+    }
+
+    fn dyn_range_iter_mut(&mut self, from_key: &K, to_key: &K)
+                          -> Box<dyn DoubleEndedIterator<Item=(&K, &mut V)> + '_> {
+        Box::new(SortedMapNav::range_iter_mut(self, from_key, to_key))
+    }
+
+    fn dyn_range_iter_bounded(&self, min: Bound<&K>, max: Bound<&K>)
+                              -> Box<dyn DoubleEndedIterator<Item=(&K, &V)> + '_> {
+        Box::new(SortedMapNav::range_iter_bounded(self, min, max))
+    }
+
+    fn dyn_range_iter_mut_bounded(&mut self, min: Bound<&K>, max: Bound<&K>)
+                                  -> Box<dyn DoubleEndedIterator<Item=(&K, &mut V)> + '_> {
+        Box::new(SortedMapNav::range_iter_mut_bounded(self, min, max))
+    }
+
+    fn dyn_range_iter_inclusive(&self, from_key: &K, to_key: &K)
+                                -> Box<dyn DoubleEndedIterator<Item=(&K, &V)> + '_> {
+        Box::new(SortedMapNav::range_iter_inclusive(self, from_key, to_key))
+    }
+
+    fn dyn_range_iter_mut_inclusive(&mut self, from_key: &K, to_key: &K)
+                                    -> Box<dyn DoubleEndedIterator<Item=(&K, &mut V)> + '_> {
+        Box::new(SortedMapNav::range_iter_mut_inclusive(self, from_key, to_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    use crate::sortedmap::tests::VecMap;
+    use crate::sortedmap::SortedMapNav;
+    use super::DynSortedMap;
+
+    fn backends() -> Vec<Box<dyn DynSortedMap<u32, u32>>> {
+        let pairs = vec![(1u32, 10u32), (3, 30), (5, 50), (7, 70)];
+        vec![Box::new(pairs.iter().cloned().collect::<BTreeMap<u32, u32>>()),
+             Box::new(VecMap(pairs))]
+    }
+
+    #[test]
+    fn test_navigation_through_trait_object() {
+        for map in backends() {
+            assert_eq!(map.dyn_first(), Some(&1u32));
+            assert_eq!(map.dyn_last(), Some(&7u32));
+            assert_eq!(map.dyn_ceiling(&3), Some(&3u32));
+            assert_eq!(map.dyn_ceiling(&4), Some(&5u32));
+            assert_eq!(map.dyn_ceiling(&8), None);
+            assert_eq!(map.dyn_floor(&4), Some(&3u32));
+            assert_eq!(map.dyn_higher(&3), Some(&5u32));
+            assert_eq!(map.dyn_lower(&1), None);
+        }
+    }
+
+    #[test]
+    fn test_range_iter_through_trait_object() {
+        for mut map in backends() {
+            assert_eq!(map.dyn_range_iter(&2, &7).map(|(&k, &v)| (k, v))
+                          .collect::<Vec<(u32, u32)>>(), vec![(3u32, 30u32), (5, 50)]);
+            assert_eq!(map.dyn_range_iter_inclusive(&2, &7).rev().map(|(&k, _)| k)
+                          .collect::<Vec<u32>>(), vec![7u32, 5, 3]);
+            assert_eq!(map.dyn_range_iter_bounded(Excluded(&3), Unbounded).map(|(&k, _)| k)
+                          .collect::<Vec<u32>>(), vec![5u32, 7]);
+            assert_eq!(map.dyn_range_iter(&5, &2).count(), 0);
+            assert_eq!(map.dyn_range_iter_bounded(Included(&5), Included(&2)).count(), 0);
+            assert_eq!(map.dyn_range_iter_bounded(Excluded(&3), Excluded(&3)).count(), 0);
+            assert_eq!(map.dyn_range_iter_mut_bounded(Included(&5), Excluded(&2)).count(), 0);
+            for (_, v) in map.dyn_range_iter_mut(&3, &7) {
+                *v += 1;
+            }
+            assert_eq!(map.dyn_range_iter_bounded(Unbounded, Unbounded).map(|(_, &v)| v)
+                          .collect::<Vec<u32>>(), vec![10u32, 31, 51, 70]);
+        }
+    }
+
+    #[test]
+    fn test_concrete_map_with_both_traits_in_scope() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 10u32), (3, 30), (5, 50)].into_iter().collect();
+        assert_eq!(map.first(), map.dyn_first());
+        assert_eq!(map.ceiling(&2), map.dyn_ceiling(&2));
+        assert_eq!(map.range_iter(&1, &5).count(), map.dyn_range_iter(&1, &5).count());
+    }
+}
@@ -7,12 +7,165 @@
 //! sorted-collections-rs is a library providing useful extension traits and convenience
 //! methods for ordered collections in Rust.
 
-#![feature(collections, core)]
+#![cfg_attr(all(test, feature = "unstable"), feature(test))]
+#[cfg(all(test, feature = "unstable"))] extern crate test;
 
-#![cfg_attr(test, feature(test))] #[cfg(test)] extern crate test;
+use std::error::Error;
+use std::fmt;
 
-pub use sortedmap::SortedMapExt;
+pub use distance::{KeyDistance, KeyStep};
+pub use dynmap::DynSortedMap;
+pub use sortedmap::{NavigableMapCore, SortedMapExt, SortedMapNav, SortedMapRemove};
 pub use sortedset::SortedSetExt;
+pub use watermark::Watermarked;
 
+// Implements `Iterator`, `DoubleEndedIterator` and `FusedIterator` for a struct wrapping a fused
+// double-ended iterator in its `iter` field, by delegating to that iterator and, if given,
+// passing each item through `$proj`. Every wrapper iterator in the crate is declared through here
+// so that none of them can miss an impl; the hand-written iterators implement `FusedIterator`
+// beside their `Iterator` impls.
+macro_rules! wrapper_iterator {
+    (impl<$($gen:tt),*> $ty:ty => $item:ty) => {
+        impl<$($gen),*> Iterator for $ty {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<$item> { self.iter.next() }
+            fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
+        }
+        impl<$($gen),*> DoubleEndedIterator for $ty {
+            fn next_back(&mut self) -> Option<$item> { self.iter.next_back() }
+        }
+        impl<$($gen),*> ::std::iter::FusedIterator for $ty {}
+    };
+    (impl<$($gen:tt),*> $ty:ty => $item:ty, $proj:expr) => {
+        impl<$($gen),*> Iterator for $ty {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<$item> { self.iter.next().map($proj) }
+            fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
+        }
+        impl<$($gen),*> DoubleEndedIterator for $ty {
+            fn next_back(&mut self) -> Option<$item> { self.iter.next_back().map($proj) }
+        }
+        impl<$($gen),*> ::std::iter::FusedIterator for $ty {}
+    };
+}
+
+pub mod distance;
+pub mod dynmap;
 pub mod sortedmap;
 pub mod sortedset;
+pub mod tracked;
+pub mod watermark;
+
+/// The error returned when two collections that must not have interleaving keys do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OverlapError<K> {
+    /// A key of one collection lying within the key span of the other.
+    pub key: K
+}
+
+impl<K: fmt::Debug> fmt::Display for OverlapError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "key {:?} lies within the key span of both collections", self.key)
+    }
+}
+impl<K: fmt::Debug> Error for OverlapError<K> {}
+
+/// A bound on the keys of the sorted collections, implemented by every type. Under the
+/// `debug-validate` feature it requires `Debug` instead, so that validation failures can name the
+/// keys involved.
+#[cfg(not(feature = "debug-validate"))]
+pub trait DebugKey {}
+#[cfg(not(feature = "debug-validate"))]
+impl<T: ?Sized> DebugKey for T {}
+
+/// A bound on the keys of the sorted collections, implemented by every type. Under the
+/// `debug-validate` feature it requires `Debug` instead, so that validation failures can name the
+/// keys involved.
+#[cfg(feature = "debug-validate")]
+pub trait DebugKey: fmt::Debug {}
+#[cfg(feature = "debug-validate")]
+impl<T: ?Sized + fmt::Debug> DebugKey for T {}
+
+// Panics unless `searched`, the answer navigation method `method` found for `probe` by searching
+// the tree, is the very same element as `scanned`, the answer found by a linear scan. The two can
+// only disagree if the element type's `Ord` implementation is inconsistent.
+#[cfg(feature = "debug-validate")]
+fn validate_navigation<Q: ?Sized + fmt::Debug, T: fmt::Debug>(method: &str, probe: &Q,
+                                                              searched: Option<&T>,
+                                                              scanned: Option<&T>) {
+    let agree = match (searched, scanned) {
+        (Some(a), Some(b)) => ::std::ptr::eq(a, b),
+        (None, None) => true,
+        _ => false,
+    };
+    if !agree {
+        panic!("inconsistent Ord implementation: {}({:?}) found {:?} by searching the tree but {:?} \
+                by a linear scan", method, probe, searched, scanned);
+    }
+}
+
+// Panics unless `edges` describes at least one bucket with strictly ascending boundaries.
+fn check_histogram_edges<T: Ord>(edges: &[T]) {
+    assert!(edges.len() >= 2, "histogram requires at least two bucket edges");
+    for i in 1..edges.len() {
+        assert!(edges[i - 1] < edges[i], "histogram bucket edges must be strictly ascending");
+    }
+}
+
+// Returns the earliest pair of adjacent keys in `keys` with the largest distance between them.
+fn max_adjacent_gap<'a, T, I>(mut keys: I) -> Option<(&'a T, &'a T)>
+    where T: KeyDistance + 'a,
+          I: Iterator<Item = &'a T>
+{
+    let mut prev = keys.next()?;
+    let mut best: Option<(&T, &T, T::Distance)> = None;
+    for key in keys {
+        let dist = prev.distance(key);
+        let better = match best {
+            Some((_, _, ref best_dist)) => dist > *best_dist,
+            None => true,
+        };
+        if better {
+            best = Some((prev, key, dist));
+        }
+        prev = key;
+    }
+    best.map(|(lo, hi, _)| (lo, hi))
+}
+
+// Returns whether looking up `probes` keys one at a time in a sorted collection of `len` elements
+// is expected to be cheaper than a merged walk over both.
+fn probing_is_cheaper(probes: usize, len: usize) -> bool {
+    let mut depth = 1;
+    let mut n = len;
+    while n > 1 {
+        n /= 2;
+        depth += 1;
+    }
+    probes.saturating_mul(depth) < len
+}
+
+// Returns whether `keys`, an ascending sequence of keys lying in [from, to), contains every key
+// of that range.
+fn covers_range<'a, T, I>(keys: I, from: &T, to: &T) -> bool
+    where T: KeyStep + 'a,
+          I: Iterator<Item = &'a T>
+{
+    let mut prev: Option<&T> = None;
+    for key in keys {
+        let adjacent = match prev {
+            Some(prev) => prev.successor().as_ref() == Some(key),
+            None => key == from,
+        };
+        if !adjacent {
+            return false;
+        }
+        prev = Some(key);
+    }
+    match prev {
+        Some(last) => last.successor().as_ref() == Some(to),
+        None => from >= to,
+    }
+}
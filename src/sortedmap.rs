@@ -4,106 +4,227 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::collections::Bound::{Included, Excluded};
+use std::borrow::Borrow;
+use std::cmp::{Ordering, self};
+use std::collections::{BTreeSet, BinaryHeap};
 use std::collections::btree_map::{BTreeMap, self};
+use std::error::Error;
+use std::fmt;
+use std::iter;
+use std::mem;
+use std::ops::Bound::{Included, Excluded, Unbounded};
+use std::ops::{Bound, RangeBounds, Sub};
+use std::slice;
+use std::vec;
 
-/// An extension trait for a `Map` whose keys have a defined total ordering.
-/// This trait provides convenience methods which take advantage of the map's ordering.
-pub trait SortedMapExt<K, V>
-    where K: Clone + Ord,
-          V: Clone 
+use crate::{DebugKey, OverlapError};
+use crate::distance::{KeyDistance, KeyStep};
+
+/// The core of a sorted map: seeking to a key from a bound, iterating between two bounds and
+/// removing a key. `SortedMapNav` and `SortedMapRemove` are derived from it by blanket impls, so
+/// a new map type needs to implement only this trait to get both of them.
+///
+/// `remove_range` has a default that removes the range one key at a time, which a map that can
+/// split off a range more cheaply should override.
+pub trait NavigableMapCore<K, V>
+    where K: Ord + DebugKey
 {
-    /// An iterator over immutable references to the key-value pairs in this map whose keys fall
-    /// within a given range.
-    type RangeIter;
+    /// An iterator over immutable references to the key-value pairs in this map whose keys lie
+    /// between two bounds.
+    type Iter<'a>: DoubleEndedIterator<Item=(&'a K, &'a V)>
+        where Self: 'a, K: 'a, V: 'a;
 
-    /// An iterator over mutable references to the key-value pairs in this map whose keys fall
-    /// within a given range.
-    type RangeIterMut;
+    /// An iterator over mutable references to the key-value pairs in this map whose keys lie
+    /// between two bounds.
+    type IterMut<'a>: DoubleEndedIterator<Item=(&'a K, &'a mut V)>
+        where Self: 'a, K: 'a, V: 'a;
 
-    /// A by-value iterator yielding key-value pairs whose keys fall within a given range and
-    /// which have just been removed from this map.
-    type RangeRemoveIter;
+    /// Returns an immutable reference to the least key in this map satisfying the lower bound
+    /// `bound`, which is the least key of all if `bound` is `Unbounded`. Returns `None` if there
+    /// is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::ops::Bound::{Included, Excluded, Unbounded};
+    /// use sorted_collections::NavigableMapCore;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (3, 3), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.seek_ge(Included(&3)), Some(&3u32));
+    ///     assert_eq!(map.seek_ge(Excluded(&3)), Some(&5u32));
+    ///     assert_eq!(map.seek_ge::<u32>(Unbounded), Some(&1u32));
+    ///     assert_eq!(map.seek_ge(Excluded(&5)), None);
+    /// }
+    /// ```
+    fn seek_ge<Q>(&self, bound: Bound<&Q>) -> Option<&K>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
-    /// Returns an immutable reference to the first (least) key currently in this map.
-    /// Returns `None` if this map is empty.
+    /// Returns an immutable reference to the greatest key in this map satisfying the upper bound
+    /// `bound`, which is the greatest key of all if `bound` is `Unbounded`. Returns `None` if
+    /// there is no such key.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
+    /// use std::collections::BTreeMap;
+    /// use std::ops::Bound::{Included, Excluded, Unbounded};
+    /// use sorted_collections::NavigableMapCore;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (3, 3), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.seek_le(Included(&3)), Some(&3u32));
+    ///     assert_eq!(map.seek_le(Excluded(&3)), Some(&1u32));
+    ///     assert_eq!(map.seek_le::<u32>(Unbounded), Some(&5u32));
+    ///     assert_eq!(map.seek_le(Excluded(&1)), None);
+    /// }
+    /// ```
+    fn seek_le<Q>(&self, bound: Bound<&Q>) -> Option<&K>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Removes `key` from this map, returning it along with its value. Returns `None` if `key` is
+    /// not in this map.
+    ///
+    /// # Examples
     ///
+    /// ```
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::NavigableMapCore;
     ///
     /// fn main() {
-    ///     let map: BTreeMap<u32, u32> =
-    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.first().unwrap(), &1u32);
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (3, 3), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.remove_key(&3), Some((3u32, 3u32)));
+    ///     assert_eq!(map.remove_key(&3), None);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (5, 5)]);
     /// }
     /// ```
-    fn first(&self) -> Option<&K>;
+    fn remove_key(&mut self, key: &K) -> Option<(K, V)>;
 
-    /// Removes and returns the first (least) key currently in this map and its associated value.
-    /// Returns `None` if this map is empty.
+    /// Returns an iterator over pairs of immutable key-value references into this map, with the
+    /// pairs being iterated being those whose keys lie between the bounds `min` and `max`, either
+    /// of which may be inclusive, exclusive or unbounded. An implementation may panic if `min`
+    /// lies above `max`, as `BTreeMap::range` does.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
+    /// use std::collections::BTreeMap;
+    /// use std::ops::Bound::{Excluded, Unbounded};
+    /// use sorted_collections::NavigableMapCore;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (3, 3), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.iter_from_bound(Excluded(&1), Unbounded).map(|(&k, _)| k)
+    ///                   .collect::<Vec<u32>>(), vec![3u32, 5]);
+    /// }
+    /// ```
+    fn iter_from_bound(&self, min: Bound<&K>, max: Bound<&K>) -> Self::Iter<'_>;
+
+    /// Returns an iterator over pairs of immutable-key/mutable-value references into this map,
+    /// with the pairs being iterated being those whose keys lie between the bounds `min` and
+    /// `max`, either of which may be inclusive, exclusive or unbounded. An implementation may
+    /// panic if `min` lies above `max`, as `BTreeMap::range_mut` does.
+    ///
+    /// # Examples
     ///
+    /// ```
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use std::ops::Bound::{Included, Unbounded};
+    /// use sorted_collections::NavigableMapCore;
     ///
     /// fn main() {
     ///     let mut map: BTreeMap<u32, u32> =
-    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.first_remove().unwrap(), (1u32, 1u32));
+    ///         vec![(1u32, 1u32), (3, 3), (5, 5)].into_iter().collect();
+    ///     for (_, v) in map.iter_mut_from_bound(Unbounded, Included(&3)) {
+    ///         *v += 1;
+    ///     }
     ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-    ///         vec![(2u32, 2u32), (3, 3), (4, 4), (5, 5)]);
+    ///         vec![(1u32, 2u32), (3, 4), (5, 5)]);
     /// }
     /// ```
-    fn first_remove(&mut self) -> Option<(K, V)>;
+    fn iter_mut_from_bound(&mut self, min: Bound<&K>, max: Bound<&K>) -> Self::IterMut<'_>;
 
-    /// Returns an immutable reference to the last (greatest) key currently in this map.
-    /// Returns `None` if this map is empty.
+    /// Removes the key-value pairs in this map whose keys lie between the bounds `min` and `max`,
+    /// returning them as a new map. An implementation may panic if `min` lies above `max`.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
+    /// use std::collections::BTreeMap;
+    /// use std::ops::Bound::{Included, Excluded};
+    /// use sorted_collections::NavigableMapCore;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (3, 3), (5, 5), (7, 7)].into_iter().collect();
+    ///     assert_eq!(map.remove_range(Excluded(&1), Included(&5)).into_iter()
+    ///                   .collect::<Vec<(u32, u32)>>(), vec![(3u32, 3u32), (5, 5)]);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (7, 7)]);
+    /// }
+    /// ```
+    fn remove_range(&mut self, min: Bound<&K>, max: Bound<&K>) -> BTreeMap<K, V> where K: Clone {
+        let keys: Vec<K> = self.iter_from_bound(min, max).map(|(k, _)| k.clone()).collect();
+        keys.iter().filter_map(|k| self.remove_key(k)).collect()
+    }
+}
+
+/// A navigation trait for a `Map` whose keys have a defined total ordering, providing lookups
+/// of the extreme and neighbouring keys and iteration over key ranges. It needs nothing of the
+/// key type beyond its ordering.
+///
+/// The navigation methods (`ceiling`, `floor`, `higher`, `lower`, and their variants on
+/// `SortedMapRemove` and `SortedMapExt`) accept any borrowed form of the key type, as
+/// `BTreeMap::get` does.
+///
+/// It is implemented for every `NavigableMapCore` implementer.
+pub trait SortedMapNav<K, V>
+    where K: Ord + DebugKey
+{
+    /// An iterator over immutable references to the key-value pairs in this map whose keys fall
+    /// within a given range.
+    type RangeIter<'a>: DoubleEndedIterator<Item=(&'a K, &'a V)>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// An iterator over mutable references to the key-value pairs in this map whose keys fall
+    /// within a given range.
+    type RangeIterMut<'a>: DoubleEndedIterator<Item=(&'a K, &'a mut V)>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// Returns an immutable reference to the first (least) key currently in this map.
+    /// Returns `None` if this map is empty.
+    ///
+    /// # Examples
     ///
+    /// ```
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.last().unwrap(), &5u32);
+    ///     assert_eq!(map.first().unwrap(), &1u32);
     /// }
     /// ```
-    fn last(&self) -> Option<&K>;
+    fn first(&self) -> Option<&K>;
 
-    /// Removes and returns the last (greatest) key currently in this map and its associated value.
+    /// Returns an immutable reference to the last (greatest) key currently in this map.
     /// Returns `None` if this map is empty.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
-    ///     let mut map: BTreeMap<u32, u32> =
+    ///     let map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.last_remove().unwrap(), (5u32, 5u32));
-    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4)]);
+    ///     assert_eq!(map.last().unwrap(), &5u32);
     /// }
     /// ```
-    fn last_remove(&mut self) -> Option<(K, V)>;
+    fn last(&self) -> Option<&K>;
 
     /// Returns an immutable reference to the least key in this map greater than or equal to `key`.
     /// Returns `None` if there is no such key.
@@ -111,10 +232,8 @@ pub trait SortedMapExt<K, V>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let map: BTreeMap<u32, u32> =
@@ -122,496 +241,7787 @@ pub trait SortedMapExt<K, V>
     ///     assert_eq!(map.ceiling(&3).unwrap(), &3u32);
     /// }
     /// ```
-    fn ceiling(&self, key: &K) -> Option<&K>;
+    fn ceiling<Q>(&self, key: &Q) -> Option<&K> where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
-    /// Removes and returns the least key in this map greater than or equal to `key` and its
-    /// associated value.
-    /// Returns `None` if there is no such element.
+    /// Returns an immutable reference to the greatest key in this map less than or equal to `key`.
+    /// Returns `None` if there is no such key.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
-    ///     let mut map: BTreeMap<u32, u32> =
+    ///     let map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.ceiling_remove(&3).unwrap(), (3u32, 3u32));
-    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-    ///         vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)]);
+    ///     assert_eq!(map.floor(&3).unwrap(), &3u32);
     /// }
     /// ```
-    fn ceiling_remove(&mut self, key: &K) -> Option<(K, V)>;
+    fn floor<Q>(&self, key: &Q) -> Option<&K> where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
-    /// Returns an immutable reference to the greatest key in this map less than or equal to `key`.
+    /// Returns an immutable reference to the least key in this map strictly greater than `key`.
     /// Returns `None` if there is no such key.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapNav;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.higher(&3).unwrap(), &4u32);
+    /// }
+    /// ```
+    fn higher<Q>(&self, key: &Q) -> Option<&K> where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+
+    /// Returns an immutable reference to the greatest key in this map strictly less than `key`.
+    /// Returns `None` if there is no such key.
+    ///
+    /// # Examples
     ///
+    /// ```
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.floor(&3).unwrap(), &3u32);
+    ///     assert_eq!(map.lower(&3).unwrap(), &2u32);
     /// }
     /// ```
-    fn floor(&self, key: &K) -> Option<&K>;
+    fn lower<Q>(&self, key: &Q) -> Option<&K> where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
-    /// Removes and returns the greatest key in this map less than or equal to `key` and its
-    /// associated value.
-    /// Returns `None` if there is no such element.
+    /// Returns an iterator over pairs of immutable key-value references into this map,
+    /// with the pairs being iterated being those whose keys are in the range [from_key, to_key).
+    /// The iterator is empty if `from_key` is not less than `to_key`.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapNav;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.range_iter(&2, &4).map(|(&k, &v)| (k, v)).collect::<Vec<(u32, u32)>>(),
+    ///         vec![(2u32, 2u32), (3, 3)]);
+    /// }
+    /// ```
+    fn range_iter(&self, from_key: &K, to_key: &K) -> Self::RangeIter<'_>;
+
+    /// Returns an iterator over pairs of immutable-key/mutable-value references into this map,
+    /// with the pairs being iterated being those whose keys are in the range [from_key, to_key).
+    /// The iterator is empty if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
     ///
+    /// ```
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let mut map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.floor_remove(&3).unwrap(), (3u32, 3u32));
+    ///     for (_, v) in map.range_iter_mut(&2, &4) {
+    ///         *v += 1;
+    ///     }
     ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-    ///         vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)]);
+    ///         vec![(1u32, 1u32), (2, 3), (3, 4), (4, 4), (5, 5)]);
     /// }
     /// ```
-    fn floor_remove(&mut self, key: &K) -> Option<(K, V)>;
+    fn range_iter_mut(&mut self, from_key: &K, to_key: &K) -> Self::RangeIterMut<'_>;
 
-    /// Returns an immutable reference to the least key in this map strictly greater than `key`.
-    /// Returns `None` if there is no such key.
+    /// Returns an iterator over pairs of immutable key-value references into this map, with the
+    /// pairs being iterated being those whose keys lie between the bounds `min` and `max`, either
+    /// of which may be inclusive, exclusive or unbounded. The iterator is empty if the bounds
+    /// describe no keys, as when `min` lies above `max`.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use std::ops::Bound::{Included, Unbounded};
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.higher(&3).unwrap(), &4u32);
+    ///     assert_eq!(map.range_iter_bounded(Included(&4), Unbounded).map(|(&k, _)| k)
+    ///                   .collect::<Vec<u32>>(), vec![4u32, 5]);
+    ///     assert_eq!(map.range_iter_bounded(Unbounded, Included(&2)).map(|(&k, _)| k)
+    ///                   .collect::<Vec<u32>>(), vec![1u32, 2]);
     /// }
     /// ```
-    fn higher(&self, key: &K) -> Option<&K>;
+    fn range_iter_bounded(&self, min: Bound<&K>, max: Bound<&K>) -> Self::RangeIter<'_>;
 
-    /// Removes and returns the least key in this map strictly greater than `key` and its
-    /// associated value.
-    /// Returns `None` if there is no such element.
+    /// Returns an iterator over pairs of immutable-key/mutable-value references into this map,
+    /// with the pairs being iterated being those whose keys lie between the bounds `min` and
+    /// `max`, either of which may be inclusive, exclusive or unbounded. The iterator is empty if
+    /// the bounds describe no keys, as when `min` lies above `max`.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use std::ops::Bound::{Excluded, Unbounded};
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let mut map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.higher_remove(&3).unwrap(), (4u32, 4u32));
+    ///     for (_, v) in map.range_iter_mut_bounded(Excluded(&3), Unbounded) {
+    ///         *v += 1;
+    ///     }
     ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (5, 5)]);
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 5), (5, 6)]);
     /// }
     /// ```
-    fn higher_remove(&mut self, key: &K) -> Option<(K, V)>;
+    fn range_iter_mut_bounded(&mut self, min: Bound<&K>, max: Bound<&K>) -> Self::RangeIterMut<'_>;
 
-
-    /// Returns an immutable reference to the greatest key in this map strictly less than `key`.
-    /// Returns `None` if there is no such key.
+    /// Returns an iterator over pairs of immutable key-value references into this map, with the
+    /// pairs being iterated being those whose keys lie in `range`, which may be any of the range
+    /// forms accepted by `BTreeMap::range` (`5..`, `..=9`, `..`, ...). Unlike `BTreeMap::range`,
+    /// an inverted range such as `4..2` gives an empty iterator rather than a panic.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.lower(&3).unwrap(), &2u32);
+    ///     assert_eq!(map.range_iter_by(4..).map(|(&k, _)| k).collect::<Vec<u32>>(),
+    ///         vec![4u32, 5]);
+    ///     assert_eq!(map.range_iter_by(..=2).map(|(&k, _)| k).collect::<Vec<u32>>(),
+    ///         vec![1u32, 2]);
     /// }
     /// ```
-    fn lower(&self, key: &K) -> Option<&K>;
+    fn range_iter_by<R>(&self, range: R) -> Self::RangeIter<'_> where R: RangeBounds<K>;
 
-    /// Removes and returns the greatest key in this map strictly less than `key` and its
-    /// associated value.
-    /// Returns `None` if there is no such element.
+    /// Returns an iterator over pairs of immutable-key/mutable-value references into this map,
+    /// with the pairs being iterated being those whose keys lie in `range`, which may be any of
+    /// the range forms accepted by `BTreeMap::range` (`5..`, `..=9`, `..`, ...). Unlike
+    /// `BTreeMap::range`, an inverted range such as `4..2` gives an empty iterator rather than a
+    /// panic.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let mut map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.lower_remove(&3).unwrap(), (2u32, 2u32));
+    ///     for (_, v) in map.range_iter_mut_by(2..=3) {
+    ///         *v += 1;
+    ///     }
     ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-    ///         vec![(1u32, 1u32), (3, 3), (4, 4), (5, 5)]);
+    ///         vec![(1u32, 1u32), (2, 3), (3, 4), (4, 4), (5, 5)]);
     /// }
     /// ```
-    fn lower_remove(&mut self, key: &K) -> Option<(K, V)>;
+    fn range_iter_mut_by<R>(&mut self, range: R) -> Self::RangeIterMut<'_> where R: RangeBounds<K>;
 
     /// Returns an iterator over pairs of immutable key-value references into this map,
-    /// with the pairs being iterated being those whose keys are in the range [from_key, to_key).
+    /// with the pairs being iterated being those whose keys are in the range [from_key, to_key].
+    /// The iterator is empty if `from_key` is greater than `to_key`.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.range_iter(&2, &4).map(|(&k, &v)| (k, v)).collect::<Vec<(u32, u32)>>(),
-    ///         vec![(2u32, 2u32), (3, 3)]);
+    ///     assert_eq!(map.range_iter_inclusive(&2, &4).map(|(&k, &v)| (k, v))
+    ///                   .collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (3, 3), (4, 4)]);
     /// }
     /// ```
-    fn range_iter(&self, from_key: &K, to_key: &K) -> Self::RangeIter;
+    fn range_iter_inclusive(&self, from_key: &K, to_key: &K) -> Self::RangeIter<'_>;
 
     /// Returns an iterator over pairs of immutable-key/mutable-value references into this map,
-    /// with the pairs being iterated being those whose keys are in the range [from_key, to_key).
+    /// with the pairs being iterated being those whose keys are in the range [from_key, to_key].
+    /// The iterator is empty if `from_key` is greater than `to_key`.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapNav;
     ///
     /// fn main() {
     ///     let mut map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     for (_, v) in map.range_iter_mut(&2, &4) {
+    ///     for (_, v) in map.range_iter_mut_inclusive(&2, &4) {
     ///         *v += 1;
     ///     }
     ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-    ///         vec![(1u32, 1u32), (2, 3), (3, 4), (4, 4), (5, 5)]);
+    ///         vec![(1u32, 1u32), (2, 3), (3, 4), (4, 5), (5, 5)]);
     /// }
     /// ```
-    fn range_iter_mut(&mut self, from_key: &K, to_key: &K) -> Self::RangeIterMut;
+    fn range_iter_mut_inclusive(&mut self, from_key: &K, to_key: &K) -> Self::RangeIterMut<'_>;
+}
 
-    /// Removes the key-value pairs of this map whose keys lie in the range [from_key, to_key),
-    /// and returns a by-value iterator over the removed pairs.
+/// A removal trait for a `Map` whose keys have a defined total ordering, providing removal of
+/// the extreme and neighbouring keys and of key ranges.
+///
+/// It is implemented for every `NavigableMapCore` implementer.
+pub trait SortedMapRemove<K, V>: SortedMapNav<K, V>
+    where K: Clone + Ord + DebugKey
+{
+    /// A by-value iterator yielding key-value pairs whose keys fall within a given range and
+    /// which have just been removed from this map.
+    type RangeRemoveIter: DoubleEndedIterator<Item=(K, V)>;
+
+    /// Removes and returns the first (least) key currently in this map and its associated value.
+    /// Returns `None` if this map is empty.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapRemove;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.first_remove().unwrap(), (1u32, 1u32));
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(2u32, 2u32), (3, 3), (4, 4), (5, 5)]);
+    /// }
+    /// ```
+    fn first_remove(&mut self) -> Option<(K, V)>;
+
+    /// Removes and returns the last (greatest) key currently in this map and its associated value.
+    /// Returns `None` if this map is empty.
     ///
+    /// # Examples
+    ///
+    /// ```
     /// use std::collections::BTreeMap;
-    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::SortedMapRemove;
     ///
     /// fn main() {
     ///     let mut map: BTreeMap<u32, u32> =
     ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-    ///     assert_eq!(map.range_remove_iter(&2, &4).collect::<Vec<(u32, u32)>>(),
-    ///         vec![(2u32, 2u32), (3, 3)]);
+    ///     assert_eq!(map.last_remove().unwrap(), (5u32, 5u32));
     ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-    ///         vec![(1u32, 1u32), (4, 4), (5, 5)]);
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4)]);
     /// }
     /// ```
-    fn range_remove_iter(&mut self, from_key: &K, to_key: &K) -> Self::RangeRemoveIter;
-}
+    fn last_remove(&mut self) -> Option<(K, V)>;
 
-// A generic reusable impl of SortedMapExt.
-macro_rules! sortedmap_impl {
-    ($typ:ty) => (
-        fn first(&self) -> Option<&K> {
-            self.keys().min()
-        }
+    /// Removes and returns the least key in this map greater than or equal to `key` and its
+    /// associated value.
+    /// Returns `None` if there is no such element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapRemove;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.ceiling_remove(&3).unwrap(), (3u32, 3u32));
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)]);
+    /// }
+    /// ```
+    fn ceiling_remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
-        fn first_remove(&mut self) -> Option<(K, V)> {
-            if let Some(key) = self.first().cloned() {
-                let val = self.remove(&key);
-                assert!(val.is_some());
-                Some((key, val.unwrap()))
+    /// Removes and returns the greatest key in this map less than or equal to `key` and its
+    /// associated value.
+    /// Returns `None` if there is no such element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapRemove;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.floor_remove(&3).unwrap(), (3u32, 3u32));
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)]);
+    /// }
+    /// ```
+    fn floor_remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Removes and returns the least key in this map strictly greater than `key` and its
+    /// associated value.
+    /// Returns `None` if there is no such element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapRemove;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.higher_remove(&3).unwrap(), (4u32, 4u32));
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (5, 5)]);
+    /// }
+    /// ```
+    fn higher_remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Removes and returns the greatest key in this map strictly less than `key` and its
+    /// associated value.
+    /// Returns `None` if there is no such element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapRemove;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.lower_remove(&3).unwrap(), (2u32, 2u32));
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (3, 3), (4, 4), (5, 5)]);
+    /// }
+    /// ```
+    fn lower_remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Removes the key-value pairs of this map whose keys lie in the range [from_key, to_key),
+    /// and returns a by-value iterator over the removed pairs. Nothing is removed if `from_key`
+    /// is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapRemove;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.range_remove_iter(&2, &4).collect::<Vec<(u32, u32)>>(),
+    ///         vec![(2u32, 2u32), (3, 3)]);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (4, 4), (5, 5)]);
+    /// }
+    /// ```
+    fn range_remove_iter(&mut self, from_key: &K, to_key: &K) -> Self::RangeRemoveIter;
+
+    /// Removes the key-value pairs of this map whose keys lie in the range [from_key, to_key],
+    /// and returns a by-value iterator over the removed pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapRemove;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.range_remove_iter_inclusive(&2, &4).collect::<Vec<(u32, u32)>>(),
+    ///         vec![(2u32, 2u32), (3, 3), (4, 4)]);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (5, 5)]);
+    /// }
+    /// ```
+    fn range_remove_iter_inclusive(&mut self, from_key: &K, to_key: &K) -> Self::RangeRemoveIter;
+}
+
+/// An extension trait for a `Map` whose keys have a defined total ordering.
+/// This trait provides convenience methods which take advantage of the map's ordering, on top
+/// of those of `SortedMapNav` and `SortedMapRemove`, which must be in scope as well for their
+/// methods to be called.
+pub trait SortedMapExt<K, V>: SortedMapRemove<K, V>
+    where K: Clone + Ord + DebugKey
+{
+    /// An iterator over immutable references to the key-value pairs in this map, ordered by
+    /// value rather than by key.
+    type ByValueIter<'a>: DoubleEndedIterator<Item=(&'a K, &'a V)>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// A by-value iterator over the keys of a consumed map that fall within a given range.
+    type IntoKeysRangeIter: DoubleEndedIterator<Item=K>;
+
+    /// A by-value iterator over the values of a consumed map whose keys fall within a given range.
+    type IntoValuesRangeIter: DoubleEndedIterator<Item=V>;
+
+    /// An iterator over immutable references to the key-value pairs in this map, in descending
+    /// key order.
+    type DescendingIter<'a>: DoubleEndedIterator<Item=(&'a K, &'a V)>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// An iterator over pairs of immutable key references and mutable value references into this
+    /// map, in descending key order.
+    type DescendingIterMut<'a>: DoubleEndedIterator<Item=(&'a K, &'a mut V)>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// An iterator over immutable references to the key-value pairs in this map whose keys fall
+    /// within a given range, in descending key order.
+    type RangeDescIter<'a>: DoubleEndedIterator<Item=(&'a K, &'a V)>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// An iterator over mutable references to the key-value pairs in this map whose keys fall
+    /// within a given range, in descending key order.
+    type RangeDescIterMut<'a>: DoubleEndedIterator<Item=(&'a K, &'a mut V)>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// An iterator over immutable references to the keys in this map that fall within a given
+    /// range.
+    type KeysRangeIter<'a>: DoubleEndedIterator<Item=&'a K>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// An iterator over immutable references to the values in this map whose keys fall within a
+    /// given range.
+    type ValuesRangeIter<'a>: DoubleEndedIterator<Item=&'a V>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// An iterator over mutable references to the values in this map whose keys fall within a
+    /// given range.
+    type ValuesRangeIterMut<'a>: DoubleEndedIterator<Item=&'a mut V>
+        where Self: 'a, K: 'a, V: 'a;
+
+    /// A by-value iterator over every key-value pair that was in this map before it was drained,
+    /// in ascending key order.
+    type DrainSortedIter: DoubleEndedIterator<Item=(K, V)>;
+
+    /// Counts the keys of this map falling into each of the buckets described by `edges`.
+    /// Bucket `i` is the range `[edges[i], edges[i + 1])`, so a key lying exactly on an interior
+    /// edge is counted in the bucket to its right, and keys outside `[edges[0], edges[n])` are
+    /// ignored. The counts are computed in a single ordered pass over the covered range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `edges` has fewer than two elements or is not strictly ascending.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.histogram(&[0, 2, 4, 5]), vec![1, 2, 1]);
+    /// }
+    /// ```
+    fn histogram(&self, edges: &[K]) -> Vec<usize>;
+
+    /// Like `histogram`, but sums `weight(value)` over the entries of each bucket instead of
+    /// counting them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `edges` has fewer than two elements or is not strictly ascending.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.histogram_weighted(&[0, 2, 4, 5], |&v| v as u64), vec![1u64, 5, 4]);
+    /// }
+    /// ```
+    fn histogram_weighted<F>(&self, edges: &[K], weight: F) -> Vec<u64>
+        where F: FnMut(&V) -> u64;
+
+    /// Returns the pair of adjacent keys in this map with the largest distance between them.
+    /// If several pairs share the largest distance, the least pair is returned.
+    /// Returns `None` if this map has fewer than two keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (6, 6), (7, 7), (9, 9)].into_iter().collect();
+    ///     assert_eq!(map.max_gap().unwrap(), (&2u32, &6u32));
+    /// }
+    /// ```
+    fn max_gap(&self) -> Option<(&K, &K)> where K: KeyDistance;
+
+    /// Returns the pair of adjacent keys in the range [from_key, to_key) of this map with the
+    /// largest distance between them. If several pairs share the largest distance, the least
+    /// pair is returned. Returns `None` if the range holds fewer than two keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (6, 6), (7, 7), (9, 9)].into_iter().collect();
+    ///     assert_eq!(map.max_gap_in_range(&6, &10).unwrap(), (&7u32, &9u32));
+    /// }
+    /// ```
+    fn max_gap_in_range(&self, from_key: &K, to_key: &K) -> Option<(&K, &K)> where K: KeyDistance;
+
+    /// Returns `true` if every key between the first and last keys of this map is present.
+    /// An empty map is considered contiguous.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert!(map.is_contiguous());
+    /// }
+    /// ```
+    fn is_contiguous(&self) -> bool where K: KeyStep;
+
+    /// Returns `true` if every key in the range [from_key, to_key) is present in this map.
+    /// An empty range is considered contiguous.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (5, 5), (6, 6)].into_iter().collect();
+    ///     assert!(map.is_contiguous_in_range(&1, &4));
+    ///     assert!(!map.is_contiguous_in_range(&1, &6));
+    /// }
+    /// ```
+    fn is_contiguous_in_range(&self, from_key: &K, to_key: &K) -> bool where K: KeyStep;
+
+    /// Returns the first key in this map for which `pred` returns `false`, or `None` if `pred`
+    /// holds for every entry. `pred` must be monotone over the map's ordering, i.e. it must
+    /// return `true` for some prefix of the entries and `false` for the rest; if it does not,
+    /// it is unspecified which of the keys at which it flips is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.partition_point_key(|&k, _| k * k < 10).unwrap(), &4u32);
+    /// }
+    /// ```
+    fn partition_point_key<F>(&self, pred: F) -> Option<&K> where F: FnMut(&K, &V) -> bool;
+
+    /// Returns an iterator over pairs of immutable key-value references into this map, ordered by
+    /// value. Pairs with equal values are yielded in ascending key order.
+    /// The pairs are collected and sorted up front, which takes O(n log n) time and O(n) memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 30u32), (2, 10), (3, 20)].into_iter().collect();
+    ///     assert_eq!(map.sorted_by_value_iter().map(|(&k, _)| k).collect::<Vec<u32>>(),
+    ///         vec![2u32, 3, 1]);
+    /// }
+    /// ```
+    fn sorted_by_value_iter(&self) -> Self::ByValueIter<'_> where V: Ord;
+
+    /// Returns an iterator over pairs of immutable key-value references into this map, ordered by
+    /// value according to `compare`. Pairs whose values compare equal are yielded in ascending
+    /// key order.
+    /// The pairs are collected and sorted up front, which takes O(n log n) time and O(n) memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 30u32), (2, 10), (3, 20)].into_iter().collect();
+    ///     assert_eq!(map.sorted_by_value_iter_by(|a, b| b.cmp(a)).map(|(&k, _)| k)
+    ///         .collect::<Vec<u32>>(), vec![1u32, 3, 2]);
+    /// }
+    /// ```
+    fn sorted_by_value_iter_by<F>(&self, compare: F) -> Self::ByValueIter<'_>
+        where F: FnMut(&V, &V) -> Ordering;
+
+    /// Returns the `n` pairs of immutable key-value references into this map with the greatest
+    /// values, in descending value order. Pairs with equal values are ranked in ascending key
+    /// order. Only `n` pairs are retained at a time, so this takes O(len log n) time and O(n)
+    /// memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 30u32), (2, 10), (3, 20)].into_iter().collect();
+    ///     assert_eq!(map.top_n_by_value(2), vec![(&1u32, &30u32), (&3, &20)]);
+    /// }
+    /// ```
+    fn top_n_by_value(&self, n: usize) -> Vec<(&K, &V)> where V: Ord;
+
+    /// Returns a map from each value in this map to the keys holding it. The keys for each value
+    /// are listed in ascending order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 20u32), (2, 10), (3, 20)].into_iter().collect();
+    ///     let inverted = map.invert();
+    ///     assert_eq!(inverted[&10], vec![2u32]);
+    ///     assert_eq!(inverted[&20], vec![1u32, 3]);
+    /// }
+    /// ```
+    fn invert(&self) -> BTreeMap<V, Vec<K>> where V: Clone + Ord;
+
+    /// Consumes this map and returns a map from each of its values to the keys holding it,
+    /// without cloning any keys or values. The keys for each value are listed in ascending order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 20u32), (2, 10), (3, 20)].into_iter().collect();
+    ///     let inverted = map.into_inverted();
+    ///     assert_eq!(inverted[&10], vec![2u32]);
+    ///     assert_eq!(inverted[&20], vec![1u32, 3]);
+    /// }
+    /// ```
+    fn into_inverted(self) -> BTreeMap<V, Vec<K>> where V: Ord;
+
+    /// Returns a map from each value in this map to the key holding it.
+    /// Returns an error naming a duplicated value if more than one key holds the same value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let inverted = map.invert_unique().unwrap();
+    ///     assert_eq!(inverted[&3], 3u32);
+    /// }
+    /// ```
+    fn invert_unique(&self) -> Result<BTreeMap<V, K>, NonUniqueValueError<V>>
+        where V: Clone + Ord;
+
+    /// Groups the key-value pairs of this map whose keys are in the range [from_key, to_key)
+    /// by the result of applying `proj` to their values. Within each group the pairs are listed
+    /// in ascending key order. An empty or inverted range produces an empty map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let groups = map.group_range_by_value(&1, &5, |&v| v % 2 == 0);
+    ///     assert_eq!(groups[&true], vec![(&2u32, &2u32), (&4, &4)]);
+    ///     assert_eq!(groups[&false], vec![(&1u32, &1u32), (&3, &3)]);
+    /// }
+    /// ```
+    fn group_range_by_value<G, F>(&self, from_key: &K, to_key: &K, proj: F)
+        -> BTreeMap<G, Vec<(&K, &V)>>
+        where G: Ord, F: FnMut(&V) -> G;
+
+    /// Like `group_range_by_value`, but the grouped key-value pairs are cloned out of this map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let groups = map.group_range_by_value_cloned(&1, &5, |&v| v % 2 == 0);
+    ///     assert_eq!(groups[&true], vec![(2u32, 2u32), (4, 4)]);
+    ///     assert_eq!(groups[&false], vec![(1u32, 1u32), (3, 3)]);
+    /// }
+    /// ```
+    fn group_range_by_value_cloned<G, F>(&self, from_key: &K, to_key: &K, proj: F)
+        -> BTreeMap<G, Vec<(K, V)>>
+        where V: Clone, G: Ord, F: FnMut(&V) -> G;
+
+    /// Inserts a key-value pair into this map only if `key` is not already present.
+    /// Returns an error holding the rejected pair and a reference to the existing value
+    /// otherwise, in which case this map is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert!(map.insert_unique(6, 6).is_ok());
+    ///     let err = map.insert_unique(3, 30).unwrap_err();
+    ///     assert_eq!((err.key, err.value, err.existing), (3u32, 30u32, &3u32));
+    /// }
+    /// ```
+    fn insert_unique(&mut self, key: K, value: V) -> Result<(), OccupiedError<'_, K, V>>;
+
+    /// Inserts a key-value pair into this map only if `key` is strictly greater than every key
+    /// currently in this map. Returns an error holding the rejected pair otherwise, in which case
+    /// this map is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert!(map.push_max(6, 6).is_ok());
+    ///     let err = map.push_max(6, 60).unwrap_err();
+    ///     assert_eq!((err.key, err.value), (6u32, 60u32));
+    /// }
+    /// ```
+    fn push_max(&mut self, key: K, value: V) -> Result<(), NotGreaterError<K, V>>;
+
+    /// Removes and returns the first (least) key currently in this map and its associated value,
+    /// but only if `pred` returns `true` for them. `pred` is called at most once.
+    /// Returns `None` if this map is empty or `pred` returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.first_remove_if(|&k, _| k > 1), None);
+    ///     assert_eq!(map.first_remove_if(|&k, _| k == 1).unwrap(), (1u32, 1u32));
+    /// }
+    /// ```
+    fn first_remove_if<F>(&mut self, pred: F) -> Option<(K, V)> where F: FnOnce(&K, &V) -> bool;
+
+    /// Removes and returns the last (greatest) key currently in this map and its associated
+    /// value, but only if `pred` returns `true` for them. `pred` is called at most once.
+    /// Returns `None` if this map is empty or `pred` returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.last_remove_if(|&k, _| k < 5), None);
+    ///     assert_eq!(map.last_remove_if(|&k, _| k == 5).unwrap(), (5u32, 5u32));
+    /// }
+    /// ```
+    fn last_remove_if<F>(&mut self, pred: F) -> Option<(K, V)> where F: FnOnce(&K, &V) -> bool;
+
+    /// Returns a set containing a clone of every key in this map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.keys_to_set().into_iter().collect::<Vec<u32>>(), vec![1u32, 2, 3, 4, 5]);
+    /// }
+    /// ```
+    fn keys_to_set(&self) -> BTreeSet<K>;
+
+    /// Retains only the key-value pairs of this map whose keys are in `keys`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     map.retain_keys(&vec![2u32, 4, 6].into_iter().collect());
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (4, 4)]);
+    /// }
+    /// ```
+    fn retain_keys(&mut self, keys: &BTreeSet<K>);
+
+    /// Removes the key-value pairs of this map whose keys are in `keys`, and returns the number
+    /// of pairs removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.remove_keys(&vec![2u32, 4, 6].into_iter().collect()), 2);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (3, 3), (5, 5)]);
+    /// }
+    /// ```
+    fn remove_keys(&mut self, keys: &BTreeSet<K>) -> usize;
+
+    /// Consumes this map and returns a by-value iterator over its keys in the range
+    /// [from_key, to_key). Nothing is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.into_keys_range(&2, &4).collect::<Vec<u32>>(), vec![2u32, 3]);
+    /// }
+    /// ```
+    fn into_keys_range(self, from_key: &K, to_key: &K) -> Self::IntoKeysRangeIter;
+
+    /// Consumes this map and returns a by-value iterator over the values whose keys lie in the
+    /// range [from_key, to_key). Nothing is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 10u32), (2, 20), (3, 30), (4, 40), (5, 50)].into_iter().collect();
+    ///     assert_eq!(map.into_values_range(&2, &4).collect::<Vec<u32>>(), vec![20u32, 30]);
+    /// }
+    /// ```
+    fn into_values_range(self, from_key: &K, to_key: &K) -> Self::IntoValuesRangeIter;
+
+    /// Returns a clone of the least key in this map greater than or equal to `key`, along with
+    /// an immutable reference to its associated value. The probe key is taken by value so that
+    /// callers holding an owned key need not keep it borrowed; it is dropped on return.
+    /// Returns `None` if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.ceiling_owned(3).unwrap(), (3u32, &3u32));
+    /// }
+    /// ```
+    fn ceiling_owned(&self, key: K) -> Option<(K, &V)>;
+
+    /// Removes and returns the least key in this map greater than or equal to `key` and its
+    /// associated value. If there is no such key, the probe key is handed back unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.remove_ceiling(3), Ok((3u32, 3u32)));
+    ///     assert_eq!(map.remove_ceiling(6), Err(6u32));
+    /// }
+    /// ```
+    fn remove_ceiling(&mut self, key: K) -> Result<(K, V), K>;
+
+    /// Removes and returns the greatest key in this map less than or equal to `key` and its
+    /// associated value. If there is no such key, the probe key is handed back unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.remove_floor(3), Ok((3u32, 3u32)));
+    ///     assert_eq!(map.remove_floor(0), Err(0u32));
+    /// }
+    /// ```
+    fn remove_floor(&mut self, key: K) -> Result<(K, V), K>;
+
+    /// Returns the positional extent of the range [from_key, to_key) within this map, as the
+    /// number of keys less than `from_key` and the number of keys less than `to_key`. The range
+    /// holds `end - start` keys. An empty or inverted range yields `start == end`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.range_position(&2, &4), (1, 3));
+    /// }
+    /// ```
+    fn range_position(&self, from_key: &K, to_key: &K) -> (usize, usize);
+
+    /// Moves all key-value pairs of `other` into this map, provided that every key of one map is
+    /// less than every key of the other. Otherwise, returns an error naming a key of one map that
+    /// lies within the key span of the other, and leaves both maps untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let mut other: BTreeMap<u32, u32> = vec![(6u32, 6u32), (7, 7)].into_iter().collect();
+    ///     assert!(map.append_assert_disjoint(&mut other).is_ok());
+    ///     assert_eq!(map.len(), 7);
+    ///     assert!(other.is_empty());
+    /// }
+    /// ```
+    fn append_assert_disjoint(&mut self, other: &mut BTreeMap<K, V>) -> Result<(), OverlapError<K>>;
+
+    /// Returns an iterator that yields, for each key `k` of this map in ascending order, `k`
+    /// together with a handle on the window of key-value pairs whose keys `j` satisfy `j < k`
+    /// and `j.distance(k) <= width`, i.e. the range [k - width, k). The window is maintained by
+    /// two cursors over the map, so the whole traversal moves each cursor at most once per entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let sums: Vec<u32> = map.rolling_range_iter(2)
+    ///         .map(|(_, window)| window.fold(0, |acc, _, &v| acc + v))
+    ///         .collect();
+    ///     assert_eq!(sums, vec![0u32, 1, 3, 5, 7]);
+    /// }
+    /// ```
+    fn rolling_range_iter(&self, width: K::Distance) -> RollingRangeIter<'_, K, V>
+        where K: KeyDistance;
+
+    /// Returns an iterator over at most the first `n` pairs of immutable key-value references
+    /// into this map whose keys are in the range [from_key, to_key), in ascending key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.first_n_in_range(&2, &5, 2).map(|(&k, &v)| (k, v)).collect::<Vec<(u32, u32)>>(),
+    ///         vec![(2u32, 2u32), (3, 3)]);
+    /// }
+    /// ```
+    fn first_n_in_range(&self, from_key: &K, to_key: &K, n: usize)
+                        -> iter::Take<Self::RangeIter<'_>>;
+
+    /// Returns an iterator over at most the last `n` pairs of immutable key-value references
+    /// into this map whose keys are in the range [from_key, to_key), in ascending key order.
+    /// The range is walked backwards and at most `n` pairs are buffered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.last_n_in_range(&2, &5, 2).map(|(&k, &v)| (k, v)).collect::<Vec<(u32, u32)>>(),
+    ///         vec![(3u32, 3u32), (4, 4)]);
+    /// }
+    /// ```
+    fn last_n_in_range(&self, from_key: &K, to_key: &K, n: usize) -> iter::Rev<vec::IntoIter<(&K, &V)>>;
+
+    /// Returns a page of at most `n` pairs of immutable key-value references into this map whose
+    /// keys are strictly greater than `cursor` (or the first `n` pairs if `cursor` is `None`), in
+    /// ascending key order. The page's `next_cursor` is the last key on the page if any pairs
+    /// remain after it, and `None` otherwise. Since the cursor is excluded by value rather than by
+    /// position, removing it from this map between calls neither skips nor repeats any pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let page = map.page_after(None, 3);
+    ///     assert_eq!(page.entries, vec![(&1u32, &1u32), (&2, &2), (&3, &3)]);
+    ///     let page = map.page_after(page.next_cursor.as_ref(), 3);
+    ///     assert_eq!(page.entries, vec![(&4u32, &4u32), (&5, &5)]);
+    ///     assert_eq!(page.next_cursor, None);
+    /// }
+    /// ```
+    fn page_after(&self, cursor: Option<&K>, n: usize) -> Page<'_, K, V>;
+
+    /// Returns a page of at most `n` pairs of immutable key-value references into this map whose
+    /// keys are strictly less than `cursor` (or the last `n` pairs if `cursor` is `None`), in
+    /// descending key order. The page's `next_cursor` is the last key on the page if any pairs
+    /// remain after it, and `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let page = map.page_before(None, 3);
+    ///     assert_eq!(page.entries, vec![(&5u32, &5u32), (&4, &4), (&3, &3)]);
+    ///     let page = map.page_before(page.next_cursor.as_ref(), 3);
+    ///     assert_eq!(page.entries, vec![(&2u32, &2u32), (&1, &1)]);
+    ///     assert_eq!(page.next_cursor, None);
+    /// }
+    /// ```
+    fn page_before(&self, cursor: Option<&K>, n: usize) -> Page<'_, K, V>;
+
+    /// Consumes this map and splits it in two: a map of the key-value pairs for which `pred`
+    /// returns `true` and a map of those for which it returns `false`. Keys and values are moved,
+    /// not cloned, and both maps are built in order in O(n).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let (odd, even) = map.binary_partition(|&k, _| k % 2 == 1);
+    ///     assert_eq!(odd.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (3, 3), (5, 5)]);
+    ///     assert_eq!(even.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (4, 4)]);
+    /// }
+    /// ```
+    fn binary_partition<F>(self, pred: F) -> (Self, Self)
+        where Self: Sized, F: FnMut(&K, &V) -> bool;
+
+    /// Returns the keys of this map split in two: a set of the keys of the key-value pairs for
+    /// which `pred` returns `true` and a set of those for which it returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let (odd, even) = map.partition_keys(|&k, _| k % 2 == 1);
+    ///     assert_eq!(odd.into_iter().collect::<Vec<u32>>(), vec![1u32, 3, 5]);
+    ///     assert_eq!(even.into_iter().collect::<Vec<u32>>(), vec![2u32, 4]);
+    /// }
+    /// ```
+    fn partition_keys<F>(&self, pred: F) -> (BTreeSet<K>, BTreeSet<K>)
+        where F: FnMut(&K, &V) -> bool;
+
+    /// Inserts a value computed by `default` for each key in `keys` not already present in this
+    /// map, and returns the number of keys inserted. `keys` may be given in any order and may
+    /// contain duplicates, but the lookups are done in a single ordered walk over this map when
+    /// there are many of them, and sorting is cheapest when the keys already come in ascending
+    /// order. `default` is called once per inserted key, in ascending key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.ensure_keys(vec![0u32, 2, 6], |&k| k * 10), 2);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///                vec![(0u32, 0u32), (1, 1), (2, 2), (3, 3), (4, 4), (5, 5), (6, 60)]);
+    /// }
+    /// ```
+    fn ensure_keys<I, F>(&mut self, keys: I, default: F) -> usize
+        where I: IntoIterator<Item = K>, F: FnMut(&K) -> V;
+
+    /// Inserts a value computed by `default` for each key of the form `from_key + n * step` less
+    /// than `to_key` not already present in this map, and returns the number of keys inserted.
+    /// `default` is called once per inserted key, in ascending key order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.ensure_keys_stride(&0, &10, 3, |_| 0), 3);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![0u32, 1, 2, 3, 4, 5, 6, 9]);
+    /// }
+    /// ```
+    fn ensure_keys_stride<F>(&mut self, from_key: &K, to_key: &K, step: usize, default: F) -> usize
+        where K: KeyStep, F: FnMut(&K) -> V;
+
+    /// Consumes this map and returns a map of its values keyed by the result of `f` applied to
+    /// their keys, where `f` must be strictly increasing. `f` is called once per key in ascending
+    /// order, the values are moved, not cloned, and the map is built in order in O(n).
+    ///
+    /// In debug builds, panics if `f` is not strictly increasing. In release builds the result is
+    /// still a valid map, in which a value whose transformed key is repeated replaces the earlier
+    /// one, but it takes O(n log n) to build.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let map = map.map_keys_monotonic(|k| k + 100);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![101u32, 102, 103, 104, 105]);
+    /// }
+    /// ```
+    fn map_keys_monotonic<K2, F>(self, f: F) -> BTreeMap<K2, V> where K2: Ord, F: FnMut(K) -> K2;
+
+    /// Consumes this map and returns a map of its values keyed by the result of `f` applied to
+    /// their keys. `f` is called once per key in ascending order, the values are moved, not
+    /// cloned, and the map is built in order in O(n). Returns an error naming the first
+    /// transformed key not strictly greater than the one before it if `f` is not strictly
+    /// increasing, in which case the rest of the map is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert!(map.clone().try_map_keys_monotonic(|k| k / 2).is_err());
+    ///     let map = map.try_map_keys_monotonic(|k| k * 2).unwrap();
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![2u32, 4, 6, 8, 10]);
+    /// }
+    /// ```
+    fn try_map_keys_monotonic<K2, F>(self, f: F) -> Result<BTreeMap<K2, V>, NotMonotonicError<K2>>
+        where K2: Ord, F: FnMut(K) -> K2;
+
+    /// Consumes this map and returns a map with the same keys, whose values are the result of `f`
+    /// applied to each key-value pair. `f` is called once per pair in ascending key order, the
+    /// keys and values are moved, not cloned, and the map is built in order in O(n).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let map = map.map_values(|&k, v| format!("{}:{}", k, v * 10));
+    ///     assert_eq!(map[&2], "2:20".to_string());
+    /// }
+    /// ```
+    fn map_values<W, F>(self, f: F) -> BTreeMap<K, W> where F: FnMut(&K, V) -> W;
+
+    /// Returns a map with the same keys as this map, whose values are the result of `f` applied to
+    /// each key-value pair. `f` is called once per pair in ascending key order and the map is
+    /// built in order in O(n).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let lengths = map.map_values_ref(|_, v| v.to_string().len());
+    ///     assert_eq!(lengths[&2], 1);
+    ///     assert_eq!(map[&2], 2);
+    /// }
+    /// ```
+    fn map_values_ref<W, F>(&self, f: F) -> BTreeMap<K, W> where F: FnMut(&K, &V) -> W;
+
+    /// Consumes this map and returns it with the values of the key-value pairs whose keys lie
+    /// in the range [from_key, to_key) replaced by the result of `f`, which is called once per
+    /// such pair in ascending key order. The other pairs are moved through untouched, and `f` is
+    /// never called if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let map = map.map_values_in_range(&2, &4, |_, v| v * 10);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///                vec![(1u32, 1u32), (2, 20), (3, 30), (4, 4), (5, 5)]);
+    /// }
+    /// ```
+    fn map_values_in_range<F>(self, from_key: &K, to_key: &K, f: F) -> Self
+        where Self: Sized, F: FnMut(&K, V) -> V;
+
+    /// Returns clones of at most `limit` key-value pairs in this map whose keys lie in the range
+    /// [from_key, to_key), in ascending key order, along with whether any more pairs lie in the
+    /// range beyond them. The range is not walked any further than the pair after the last one
+    /// returned. Returns an empty vector and `false` if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.collect_range_truncated(&2, &5, 2), (vec![(2u32, 2u32), (3, 3)], true));
+    ///     assert_eq!(map.collect_range_truncated(&2, &5, 3),
+    ///                (vec![(2u32, 2u32), (3, 3), (4, 4)], false));
+    /// }
+    /// ```
+    fn collect_range_truncated(&self, from_key: &K, to_key: &K, limit: usize)
+                               -> (Vec<(K, V)>, bool) where V: Clone;
+
+    /// Returns an iterator over immutable references to the key-value pairs in this map from the
+    /// first one onwards, in ascending key order, for as long as `proj` applied to their keys
+    /// stays equal to `proj` applied to the first key. The iterator is empty if this map is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<(u32, u32), u32> =
+    ///         vec![((1u32, 1u32), 1u32), ((1, 2), 2), ((2, 1), 3), ((3, 1), 4), ((3, 2), 5)]
+    ///         .into_iter().collect();
+    ///     let run: Vec<(&(u32, u32), &u32)> = map.first_run_by(|&(shard, _)| shard).collect();
+    ///     assert_eq!(run, vec![(&(1u32, 1u32), &1u32), (&(1, 2), &2)]);
+    /// }
+    /// ```
+    fn first_run_by<G, F>(&self, proj: F) -> RunIter<'_, K, V, G, F>
+        where G: PartialEq, F: FnMut(&K) -> G;
+
+    /// Returns an iterator over immutable references to the key-value pairs in this map from the
+    /// last one backwards, in descending key order, for as long as `proj` applied to their keys
+    /// stays equal to `proj` applied to the last key. The iterator is empty if this map is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<(u32, u32), u32> =
+    ///         vec![((1u32, 1u32), 1u32), ((1, 2), 2), ((2, 1), 3), ((3, 1), 4), ((3, 2), 5)]
+    ///         .into_iter().collect();
+    ///     let run: Vec<(&(u32, u32), &u32)> = map.last_run_by(|&(shard, _)| shard).collect();
+    ///     assert_eq!(run, vec![(&(3u32, 2u32), &5u32), (&(3, 1), &4)]);
+    /// }
+    /// ```
+    fn last_run_by<G, F>(&self, proj: F) -> RunIter<'_, K, V, G, F>
+        where G: PartialEq, F: FnMut(&K) -> G;
+
+    /// Removes the key-value pairs that `first_run_by` would yield for `proj` from this map, and
+    /// returns a by-value iterator over them in ascending key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<(u32, u32), u32> =
+    ///         vec![((1u32, 1u32), 1u32), ((1, 2), 2), ((2, 1), 3), ((3, 1), 4), ((3, 2), 5)]
+    ///         .into_iter().collect();
+    ///     let run: Vec<_> = map.first_run_remove_by(|&(shard, _)| shard).collect();
+    ///     assert_eq!(run, vec![((1u32, 1u32), 1u32), ((1, 2), 2)]);
+    ///     assert_eq!(map.len(), 3);
+    /// }
+    /// ```
+    fn first_run_remove_by<G, F>(&mut self, proj: F) -> Self::RangeRemoveIter
+        where G: PartialEq, F: FnMut(&K) -> G;
+
+    /// Returns, for each key in `probes`, whether it is present in this map. `probes` must be
+    /// sorted in ascending order, and may contain duplicates. The lookups are done in a single
+    /// ordered walk over this map unless there are few enough probes that looking each one up is
+    /// cheaper.
+    ///
+    /// In debug builds, panics if `probes` is not sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.contains_each_sorted(&[0, 2, 2, 6]), vec![false, true, true, false]);
+    /// }
+    /// ```
+    fn contains_each_sorted(&self, probes: &[K]) -> Vec<bool>;
+
+    /// Writes, for each key in `probes`, whether it is present in this map into the
+    /// corresponding element of `out`. This behaves like `contains_each_sorted`, except that it
+    /// does not allocate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` is not the same length as `probes`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let mut out = [false; 4];
+    ///     map.contains_each_sorted_into(&[0, 2, 2, 6], &mut out);
+    ///     assert_eq!(out, [false, true, true, false]);
+    /// }
+    /// ```
+    fn contains_each_sorted_into(&self, probes: &[K], out: &mut [bool]);
+
+    /// Returns an iterator over consecutive chunks of the key-value pairs in this map whose keys
+    /// lie in the range [from_key, to_key), in ascending key order. Each chunk is made as long as
+    /// it can be without the sum of `weight` over its pairs exceeding `max`, except that a pair
+    /// heavier than `max` on its own forms a chunk by itself. `weight` is called once per pair.
+    /// The iterator is empty if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let chunks: Vec<Vec<u32>> = map.chunk_range_by_weight(&1, &6, 5, |_, &v| v as u64)
+    ///         .map(|chunk| chunk.iter().map(|(&k, _)| k).collect())
+    ///         .collect();
+    ///     assert_eq!(chunks, vec![vec![1u32, 2], vec![3], vec![4], vec![5]]);
+    /// }
+    /// ```
+    fn chunk_range_by_weight<F>(&self, from_key: &K, to_key: &K, max: u64, weight: F)
+                                -> WeightChunks<'_, K, V, F>
+        where F: FnMut(&K, &V) -> u64;
+
+    /// Returns `k` key-value pairs sampled uniformly at random without replacement from those in
+    /// this map whose keys lie in the range [from_key, to_key), in ascending key order. Returns
+    /// every pair in the range if there are no more than `k` of them, and none if `from_key` is
+    /// not less than `to_key`. The sample is drawn by reservoir sampling in a single walk over
+    /// the range, so this takes O(range) time.
+    ///
+    /// This method is only available with the `rand` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// # #[cfg(not(feature = "rand"))] fn main() {}
+    /// # #[cfg(feature = "rand")]
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let mut rng = rand::thread_rng();
+    ///     let sample = map.sample_range(&2, &5, 2, &mut rng);
+    ///     assert_eq!(sample.len(), 2);
+    ///     assert!(sample.iter().all(|&(&k, _)| k >= 2 && k < 5));
+    ///     assert_eq!(map.sample_range(&2, &5, 10, &mut rng),
+    ///                vec![(&2u32, &2u32), (&3, &3), (&4, &4)]);
+    /// }
+    /// ```
+    #[cfg(feature = "rand")]
+    fn sample_range<R>(&self, from_key: &K, to_key: &K, k: usize, rng: &mut R) -> Vec<(&K, &V)>
+        where R: ::rand::Rng;
+
+    /// Removes every key-value pair whose key lies in the range [from_key, to_key) and whose value
+    /// equals that of the pair retained immediately before it, and returns the number of pairs
+    /// removed. The comparison is seeded with the pair just below `from_key`, if any, so that a run
+    /// of equal values straddling `from_key` is compacted down to its first pair outside the
+    /// range. Nothing is removed if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, &str> =
+    ///         vec![(1u32, "a"), (2, "a"), (3, "a"), (4, "b"), (5, "b")].into_iter().collect();
+    ///     assert_eq!(map.dedup_values_in_range(&2, &6), 3);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, &str)>>(), vec![(1u32, "a"), (4, "b")]);
+    /// }
+    /// ```
+    fn dedup_values_in_range(&mut self, from_key: &K, to_key: &K) -> usize where V: PartialEq;
+
+    /// Returns a handle which drains the key-value pairs in this map whose keys lie in the range
+    /// [from_key, to_key) in batches of at most `batch` pairs, in ascending key order, so that
+    /// other work can be done in between. Nothing is removed until the first batch is requested.
+    /// See `DrainBatches` for how the handle behaves if the map is modified between batches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let mut batches = map.drain_range_in_batches(&2, &6, 3);
+    ///     assert_eq!(batches.next_batch(), vec![(2u32, 2u32), (3, 3), (4, 4)]);
+    ///     assert_eq!(batches.next_batch(), vec![(5u32, 5u32)]);
+    ///     assert_eq!(batches.next_batch(), vec![]);
+    /// }
+    /// ```
+    fn drain_range_in_batches(&mut self, from_key: &K, to_key: &K, batch: usize)
+                              -> DrainBatches<'_, K, V>;
+
+    /// Returns immutable references to the `k` key-value pairs in this map whose keys are closest
+    /// to `probe` by the distance `dist(probe, key)`, in order of increasing distance, with ties
+    /// going to the lesser key. `dist` must not decrease moving away from `probe` in either
+    /// direction. Returns every pair if there are no more than `k` of them. The map is walked
+    /// outwards from `probe` in both directions at once, so this takes O(log n + k) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let dist = |&a: &u32, &b: &u32| if a < b { b - a } else { a - b };
+    ///     let nearest = map.k_nearest_entries_by(&3, 3, dist);
+    ///     assert_eq!(nearest, vec![(&3u32, &3u32), (&2, &2), (&4, &4)]);
+    /// }
+    /// ```
+    fn k_nearest_entries_by<D, F>(&self, probe: &K, k: usize, dist: F) -> Vec<(&K, &V)>
+        where D: Ord, F: Fn(&K, &K) -> D;
+
+    /// Returns `true` if `pred` returns `true` for any key-value pair in this map whose key lies
+    /// in the range [from_key, to_key), stopping at the first such pair. Returns `false` if
+    /// `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert!(map.range_any(&2, &5, |_, &v| v == 4));
+    ///     assert!(!map.range_any(&2, &5, |_, &v| v == 5));
+    /// }
+    /// ```
+    fn range_any<F>(&self, from_key: &K, to_key: &K, pred: F) -> bool
+        where F: FnMut(&K, &V) -> bool;
+
+    /// Returns `true` if `pred` returns `true` for every key-value pair in this map whose key lies
+    /// in the range [from_key, to_key), stopping at the first pair for which it does not. Returns
+    /// `true` if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert!(map.range_all(&2, &5, |_, &v| v < 5));
+    ///     assert!(!map.range_all(&2, &5, |_, &v| v < 4));
+    /// }
+    /// ```
+    fn range_all<F>(&self, from_key: &K, to_key: &K, pred: F) -> bool
+        where F: FnMut(&K, &V) -> bool;
+
+    /// Calls `f` on each key-value pair in this map whose key lies in the range
+    /// [from_key, to_key), in ascending key order, stopping at and returning the first error it
+    /// returns. Returns `Ok(())` without calling `f` if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let mut seen = Vec::new();
+    ///     let res = map.try_for_each_range(&2, &5, |&k, _| {
+    ///         if k == 4 { return Err(k); }
+    ///         seen.push(k);
+    ///         Ok(())
+    ///     });
+    ///     assert_eq!(res, Err(4));
+    ///     assert_eq!(seen, vec![2u32, 3]);
+    /// }
+    /// ```
+    fn try_for_each_range<E, F>(&self, from_key: &K, to_key: &K, f: F) -> Result<(), E>
+        where F: FnMut(&K, &V) -> Result<(), E>;
+
+    /// Calls `f` on each key-value pair in this map whose key lies in the range
+    /// [from_key, to_key), with a mutable reference to the value, in ascending key order,
+    /// stopping at and returning the first error it returns. Returns `Ok(())` without calling `f`
+    /// if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let res = map.try_for_each_range_mut(&2, &5, |&k, v| {
+    ///         if k == 4 { return Err(k); }
+    ///         *v *= 10;
+    ///         Ok(())
+    ///     });
+    ///     assert_eq!(res, Err(4));
+    ///     assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![1u32, 20, 30, 4, 5]);
+    /// }
+    /// ```
+    fn try_for_each_range_mut<E, F>(&mut self, from_key: &K, to_key: &K, f: F) -> Result<(), E>
+        where F: FnMut(&K, &mut V) -> Result<(), E>;
+
+    /// Returns immutable references to the first (least) key currently in this map and its
+    /// value, found in a single traversal. Returns `None` if this map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let (key, value) = map.first_entry().unwrap();
+    ///     assert_eq!((key, value), (&1u32, &1u32));
+    /// }
+    /// ```
+    fn first_entry(&self) -> Option<(&K, &V)>;
+
+    /// Returns immutable references to the last (greatest) key currently in this map and its
+    /// value, found in a single traversal. Returns `None` if this map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let (key, value) = map.last_entry().unwrap();
+    ///     assert_eq!((key, value), (&5u32, &5u32));
+    /// }
+    /// ```
+    fn last_entry(&self) -> Option<(&K, &V)>;
+
+    /// Returns immutable references to the least key in this map greater than or equal to `key`
+    /// and its value, found in a single traversal. Returns `None` if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     map.remove(&3);
+    ///     assert_eq!(map.ceiling_entry(&3), Some((&4u32, &4u32)));
+    /// }
+    /// ```
+    fn ceiling_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Returns immutable references to the greatest key in this map less than or equal to `key`
+    /// and its value, found in a single traversal. Returns `None` if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     map.remove(&3);
+    ///     assert_eq!(map.floor_entry(&3), Some((&2u32, &2u32)));
+    /// }
+    /// ```
+    fn floor_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Returns immutable references to the least key in this map strictly greater than `key` and
+    /// its value, found in a single traversal. Returns `None` if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.higher_entry(&3), Some((&4u32, &4u32)));
+    /// }
+    /// ```
+    fn higher_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Returns immutable references to the greatest key in this map strictly less than `key` and
+    /// its value, found in a single traversal. Returns `None` if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.lower_entry(&3), Some((&2u32, &2u32)));
+    /// }
+    /// ```
+    fn lower_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Returns an immutable reference to the least key in this map greater than or equal to `key`
+    /// and a mutable reference to its value, found in a single traversal. Returns `None` if there
+    /// is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     if let Some((_, value)) = map.ceiling_mut(&3) {
+    ///         *value *= 10;
+    ///     }
+    ///     assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 30, 4, 5]);
+    /// }
+    /// ```
+    fn ceiling_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Returns an immutable reference to the greatest key in this map less than or equal to `key`
+    /// and a mutable reference to its value, found in a single traversal. Returns `None` if there
+    /// is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     if let Some((_, value)) = map.floor_mut(&3) {
+    ///         *value *= 10;
+    ///     }
+    ///     assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 30, 4, 5]);
+    /// }
+    /// ```
+    fn floor_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Returns an immutable reference to the least key in this map strictly greater than `key` and
+    /// a mutable reference to its value, found in a single traversal. Returns `None` if there is no
+    /// such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     if let Some((_, value)) = map.higher_mut(&3) {
+    ///         *value *= 10;
+    ///     }
+    ///     assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 3, 40, 5]);
+    /// }
+    /// ```
+    fn higher_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Returns an immutable reference to the greatest key in this map strictly less than `key` and
+    /// a mutable reference to its value, found in a single traversal. Returns `None` if there is no
+    /// such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     if let Some((_, value)) = map.lower_mut(&3) {
+    ///         *value *= 10;
+    ///     }
+    ///     assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![1u32, 20, 3, 4, 5]);
+    /// }
+    /// ```
+    fn lower_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
+
+    /// Returns the entry with the greatest key strictly less than `key`, the entry at `key`, and
+    /// the entry with the least key strictly greater than `key`, in that order, each of which is
+    /// `None` if there is no such entry. This takes at most two traversals, rather than the three
+    /// of calling `lower_entry`, `get` and `higher_entry` separately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.neighbors(&3), (Some((&2, &2)), Some((&3, &3)), Some((&4, &4))));
+    ///     assert_eq!(map.neighbors(&0), (None, None, Some((&1, &1))));
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    fn neighbors(&self, key: &K) -> (Option<(&K, &V)>, Option<(&K, &V)>, Option<(&K, &V)>);
+
+    /// Returns an iterator over pairs of immutable key-value references into this map, starting
+    /// at the least key greater than or equal to `key` and running to the end of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.iter_from(&4).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![4u32, 5]);
+    /// }
+    /// ```
+    fn iter_from(&self, key: &K) -> Self::RangeIter<'_>;
+
+    /// Returns an iterator over pairs of immutable-key/mutable-value references into this map,
+    /// starting at the least key greater than or equal to `key` and running to the end of the
+    /// map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     for (_, v) in map.iter_from_mut(&4) {
+    ///         *v += 1;
+    ///     }
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 5), (5, 6)]);
+    /// }
+    /// ```
+    fn iter_from_mut(&mut self, key: &K) -> Self::RangeIterMut<'_>;
+
+    /// Returns an iterator over pairs of immutable key-value references into this map, running
+    /// from the start of the map up to but excluding `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.iter_until(&3).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![1u32, 2]);
+    /// }
+    /// ```
+    fn iter_until(&self, key: &K) -> Self::RangeIter<'_>;
+
+    /// Returns an iterator over pairs of immutable key-value references into this map, running
+    /// from the start of the map up to and including `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.iter_until_inclusive(&3).map(|(&k, _)| k).collect::<Vec<u32>>(),
+    ///         vec![1u32, 2, 3]);
+    /// }
+    /// ```
+    fn iter_until_inclusive(&self, key: &K) -> Self::RangeIter<'_>;
+
+    /// Returns an iterator over pairs of immutable-key/mutable-value references into this map,
+    /// running from the start of the map up to but excluding `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     for (_, v) in map.iter_until_mut(&3) {
+    ///         *v += 1;
+    ///     }
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 2u32), (2, 3), (3, 3), (4, 4), (5, 5)]);
+    /// }
+    /// ```
+    fn iter_until_mut(&mut self, key: &K) -> Self::RangeIterMut<'_>;
+
+    /// Returns an iterator over pairs of immutable key-value references into this map, from the
+    /// greatest key down to the least.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.descending_iter().map(|(&k, _)| k).collect::<Vec<u32>>(),
+    ///         vec![5u32, 4, 3, 2, 1]);
+    /// }
+    /// ```
+    fn descending_iter(&self) -> Self::DescendingIter<'_>;
+
+    /// Returns an iterator over pairs of immutable-key/mutable-value references into this map,
+    /// from the greatest key down to the least.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let mut next = 0;
+    ///     for (_, v) in map.descending_iter_mut() {
+    ///         *v = next;
+    ///         next += 1;
+    ///     }
+    ///     assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![4u32, 3, 2, 1, 0]);
+    /// }
+    /// ```
+    fn descending_iter_mut(&mut self) -> Self::DescendingIterMut<'_>;
+
+    /// Returns an iterator over pairs of immutable key-value references into this map, with the
+    /// pairs being iterated being those whose keys are in the range [from_key, to_key), from the
+    /// greatest key down to the least. The iterator is empty if `from_key` is not less than
+    /// `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.range_iter_desc(&2, &5).map(|(&k, _)| k).collect::<Vec<u32>>(),
+    ///         vec![4u32, 3, 2]);
+    /// }
+    /// ```
+    fn range_iter_desc(&self, from_key: &K, to_key: &K) -> Self::RangeDescIter<'_>;
+
+    /// Returns an iterator over pairs of immutable-key/mutable-value references into this map,
+    /// with the pairs being iterated being those whose keys are in the range [from_key, to_key),
+    /// from the greatest key down to the least. The iterator is empty if `from_key` is not less
+    /// than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let mut next = 10;
+    ///     for (_, v) in map.range_iter_desc_mut(&2, &5) {
+    ///         *v = next;
+    ///         next += 1;
+    ///     }
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (2, 12), (3, 11), (4, 10), (5, 5)]);
+    /// }
+    /// ```
+    fn range_iter_desc_mut(&mut self, from_key: &K, to_key: &K) -> Self::RangeDescIterMut<'_>;
+
+    /// Returns the number of key-value pairs in this map whose keys are in the range
+    /// [from_key, to_key), or 0 if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.count_range(&2, &4), 2);
+    /// }
+    /// ```
+    fn count_range(&self, from_key: &K, to_key: &K) -> usize;
+
+    /// Returns the number of key-value pairs in this map whose keys lie between the bounds `min`
+    /// and `max`, either of which may be inclusive, exclusive or unbounded. Returns `0` if
+    /// the bounds describe no keys, as when `min` lies above `max`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::ops::Bound::{Included, Unbounded};
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.count_range_bounded(Included(&2), Unbounded), 4);
+    /// }
+    /// ```
+    fn count_range_bounded(&self, min: Bound<&K>, max: Bound<&K>) -> usize;
+
+    /// Folds every key-value pair in this map whose key lies in the range [from_key, to_key)
+    /// into an accumulator, in ascending key order, and returns the final accumulator. Returns
+    /// `init` if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.fold_range(&2, &5, 0, |sum, _, &v| sum + v), 9);
+    /// }
+    /// ```
+    fn fold_range<B, F>(&self, from_key: &K, to_key: &K, init: B, f: F) -> B
+        where F: FnMut(B, &K, &V) -> B;
+
+    /// Folds every key-value pair in this map whose key lies in the range [from_key, to_key)
+    /// into an accumulator, in ascending key order, stopping at and returning the first error `f`
+    /// returns. Returns `Ok(init)` if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     // Find the key at which the running total first exceeds 5.
+    ///     let res = map.try_fold_range(&1, &6, 0, |sum, &k, &v| {
+    ///         if sum + v > 5 { Err(k) } else { Ok(sum + v) }
+    ///     });
+    ///     assert_eq!(res, Err(3));
+    /// }
+    /// ```
+    fn try_fold_range<B, E, F>(&self, from_key: &K, to_key: &K, init: B, f: F) -> Result<B, E>
+        where F: FnMut(B, &K, &V) -> Result<B, E>;
+
+    /// Retains only those key-value pairs in the range [from_key, to_key) for which `pred`
+    /// returns `true`, removing the rest. Pairs outside the range are neither visited nor
+    /// removed, and nothing is visited if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     map.retain_range(&2, &5, |&k, _| k % 2 == 1);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 3, 5]);
+    /// }
+    /// ```
+    fn retain_range<F>(&mut self, from_key: &K, to_key: &K, pred: F)
+        where F: FnMut(&K, &mut V) -> bool;
+
+    /// Removes the key-value pairs in the range [from_key, to_key) for which `pred` returns
+    /// `true`, and returns a by-value iterator over the removed pairs. Pairs for which it returns
+    /// `false` are left in place, and nothing is removed if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let removed = map.range_remove_if(&2, &5, |&k, _| k % 2 == 0);
+    ///     assert_eq!(removed.collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (4, 4)]);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 3, 5]);
+    /// }
+    /// ```
+    fn range_remove_if<F>(&mut self, from_key: &K, to_key: &K, pred: F) -> Self::RangeRemoveIter
+        where F: FnMut(&K, &V) -> bool;
+
+    /// Removes the key-value pairs whose keys lie in the range [from_key, to_key) and returns
+    /// them as a new map, splitting the tree rather than moving pairs one at a time. Returns an
+    /// empty map if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let range = map.split_off_range(&2, &4);
+    ///     assert_eq!(range.keys().cloned().collect::<Vec<u32>>(), vec![2u32, 3]);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 4, 5]);
+    /// }
+    /// ```
+    fn split_off_range(&mut self, from_key: &K, to_key: &K) -> Self where Self: Sized;
+
+    /// Removes the `n` key-value pairs with the least keys in this map, or every pair if there
+    /// are fewer than `n`, and returns a by-value iterator over them in ascending key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.pop_first_n(2).collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2)]);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![3u32, 4, 5]);
+    /// }
+    /// ```
+    fn pop_first_n(&mut self, n: usize) -> Self::RangeRemoveIter;
+
+    /// Removes the `n` key-value pairs with the greatest keys in this map, or every pair if there
+    /// are fewer than `n`, and returns a by-value iterator over them in ascending key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.pop_last_n(2).collect::<Vec<(u32, u32)>>(), vec![(4u32, 4u32), (5, 5)]);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 3]);
+    /// }
+    /// ```
+    fn pop_last_n(&mut self, n: usize) -> Self::RangeRemoveIter;
+
+    /// Removes key-value pairs from the front of this map, least key first, for as long as `pred`
+    /// holds for them, and returns a by-value iterator over the removed pairs in ascending key
+    /// order. Removal stops at the first pair for which `pred` returns `false`, which is kept
+    /// along with every pair after it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let removed = map.pop_while_front(|&k, _| k < 3);
+    ///     assert_eq!(removed.collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2)]);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![3u32, 4, 5]);
+    /// }
+    /// ```
+    fn pop_while_front<F>(&mut self, pred: F) -> Self::RangeRemoveIter
+        where F: FnMut(&K, &V) -> bool;
+
+    /// Removes key-value pairs from the back of this map, greatest key first, for as long as
+    /// `pred` holds for them, and returns a by-value iterator over the removed pairs in ascending
+    /// key order. Removal stops at the first pair for which `pred` returns `false`, which is kept
+    /// along with every pair before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let removed = map.pop_while_back(|_, &v| v > 3);
+    ///     assert_eq!(removed.collect::<Vec<(u32, u32)>>(), vec![(4u32, 4u32), (5, 5)]);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 3]);
+    /// }
+    /// ```
+    fn pop_while_back<F>(&mut self, pred: F) -> Self::RangeRemoveIter
+        where F: FnMut(&K, &V) -> bool;
+
+    /// Removes every key-value pair whose key is strictly less than `key`, keeping `key` itself,
+    /// and returns the number of pairs removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.truncate_before(&3), 2);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![3u32, 4, 5]);
+    /// }
+    /// ```
+    fn truncate_before(&mut self, key: &K) -> usize;
+
+    /// Removes every key-value pair whose key is strictly greater than `key`, keeping `key`
+    /// itself, and returns the number of pairs removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.truncate_after(&3), 2);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 3]);
+    /// }
+    /// ```
+    fn truncate_after(&mut self, key: &K) -> usize;
+
+    /// Returns the key-value pair with exactly `n` keys before it in this map, i.e. the pair at
+    /// zero-based rank `n` in key order. Returns `None` if this map has no more than `n` pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.nth_key(1), Some((&2u32, &2u32)));
+    ///     assert_eq!(map.nth_key(5), None);
+    /// }
+    /// ```
+    fn nth_key(&self, n: usize) -> Option<(&K, &V)>;
+
+    /// Removes and returns the key-value pair with exactly `n` keys before it in this map.
+    /// Returns `None`, leaving this map untouched, if it has no more than `n` pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.nth_key_remove(1), Some((2u32, 2u32)));
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 3, 4, 5]);
+    /// }
+    /// ```
+    fn nth_key_remove(&mut self, n: usize) -> Option<(K, V)>;
+
+    /// Returns the number of keys in this map strictly less than `key`, which is the rank `key`
+    /// has, or would have if it were inserted. `key` need not be present: a key less than every
+    /// key in this map has rank 0, and one greater than every key has rank `len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.rank(&3), 2);
+    ///     assert_eq!(map.rank(&9), 5);
+    /// }
+    /// ```
+    fn rank(&self, key: &K) -> usize;
+
+    /// Returns an iterator over immutable references to the keys in this map in the range
+    /// [from_key, to_key). The iterator is empty if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.keys_range(&2, &4).cloned().collect::<Vec<u32>>(), vec![2u32, 3]);
+    /// }
+    /// ```
+    fn keys_range(&self, from_key: &K, to_key: &K) -> Self::KeysRangeIter<'_>;
+
+    /// Returns an iterator over immutable references to the values in this map whose keys are in
+    /// the range [from_key, to_key). The iterator is empty if `from_key` is not less than
+    /// `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.values_range(&2, &4).cloned().collect::<Vec<u32>>(), vec![2u32, 3]);
+    /// }
+    /// ```
+    fn values_range(&self, from_key: &K, to_key: &K) -> Self::ValuesRangeIter<'_>;
+
+    /// Returns an iterator over mutable references to the values in this map whose keys are in
+    /// the range [from_key, to_key). The iterator is empty if `from_key` is not less than
+    /// `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     for v in map.values_range_mut(&2, &4) {
+    ///         *v *= 10;
+    ///     }
+    ///     assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![1u32, 20, 30, 4, 5]);
+    /// }
+    /// ```
+    fn values_range_mut(&mut self, from_key: &K, to_key: &K) -> Self::ValuesRangeIterMut<'_>;
+
+    /// Returns the key-value pair in this map whose key is closest to `key`, choosing between its
+    /// floor and its ceiling by `KeyDistance::distance`, with ties going to the lesser key.
+    /// Returns `None` if this map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     map.remove(&3);
+    ///     assert_eq!(map.nearest(&3), Some((&2u32, &2u32)));
+    ///     assert_eq!(map.nearest(&9), Some((&5u32, &5u32)));
+    /// }
+    /// ```
+    fn nearest(&self, key: &K) -> Option<(&K, &V)> where K: KeyDistance;
+
+    /// Returns immutable references to the `n` key-value pairs in this map whose keys are closest
+    /// to `key` by `KeyDistance::distance`, in order of increasing distance, with ties going to
+    /// the lesser key. Returns every pair if there are no more than `n` of them. This is
+    /// `k_nearest_entries_by` with the key type's own distance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.get_closest_n(&4, 3), vec![(&4u32, &4u32), (&3, &3), (&5, &5)]);
+    /// }
+    /// ```
+    fn get_closest_n(&self, key: &K, n: usize) -> Vec<(&K, &V)> where K: KeyDistance;
+
+    /// Moves the value associated with `old` so that it is associated with `new` instead, without
+    /// cloning it. Returns an error holding `new`, and leaves this map untouched, if `old` is not
+    /// present or if `new` is already present. Moving a present key onto itself does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::sortedmap::UpdateKeyError;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.update_key(&3, 9), Ok(()));
+    ///     assert_eq!(map.get(&9), Some(&3u32));
+    ///     assert_eq!(map.update_key(&3, 6), Err(UpdateKeyError::Missing(6)));
+    ///     assert_eq!(map.update_key(&1, 2), Err(UpdateKeyError::Occupied(2)));
+    /// }
+    /// ```
+    fn update_key(&mut self, old: &K, new: K) -> Result<(), UpdateKeyError<K>>;
+
+    /// Moves the value associated with `old` so that it is associated with `new` instead, without
+    /// cloning it. If `new` is already present, the two values are combined by calling
+    /// `merge(&new, existing, moved)`. Returns an error holding `new`, and leaves this map
+    /// untouched, if `old` is not present. Moving a present key onto itself does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.update_key_with(&3, 4, |_, existing, moved| existing + moved), Ok(()));
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (2, 2), (4, 7), (5, 5)]);
+    /// }
+    /// ```
+    fn update_key_with<F>(&mut self, old: &K, new: K, merge: F) -> Result<(), UpdateKeyError<K>>
+        where F: FnOnce(&K, V, V) -> V;
+
+    /// Removes the key-value pairs whose keys lie in the range [from_key, to_key), inserts
+    /// `entries` in their place, and returns a by-value iterator over the removed pairs. Entries
+    /// are not required to lie in the range: one outside it is inserted like any other, replacing
+    /// the value of an existing key. Nothing is removed if `from_key` is not less than `to_key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let removed = map.replace_range(&2, &4, vec![(2u32, 20u32), (3, 30)]);
+    ///     assert_eq!(removed.collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (3, 3)]);
+    ///     assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![1u32, 20, 30, 4, 5]);
+    /// }
+    /// ```
+    fn replace_range<I>(&mut self, from_key: &K, to_key: &K, entries: I) -> Self::RangeRemoveIter
+        where I: IntoIterator<Item=(K, V)>;
+
+    /// Inserts the key-value pairs of `iter`, which must be sorted by key, into this map. Where
+    /// a key occurs more than once, or is already present, the last value for it wins, as with
+    /// `extend`. The sorted pairs are built into a map in bulk and merged in one pass when they
+    /// all lie above this map's keys or are numerous enough to make that cheaper than inserting
+    /// them one at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `iter` is not sorted by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     map.extend_from_sorted(vec![(5u32, 50u32), (6, 6), (7, 7)]);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 50), (6, 6), (7, 7)]);
+    /// }
+    /// ```
+    fn extend_from_sorted<I>(&mut self, iter: I) where I: IntoIterator<Item=(K, V)>;
+
+    /// Returns the key-value pair in this map with the least value, with ties going to the
+    /// least key. Returns `None` if this map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     map.insert(6, 1);
+    ///     assert_eq!(map.min_by_value(), Some((&1u32, &1u32)));
+    /// }
+    /// ```
+    fn min_by_value(&self) -> Option<(&K, &V)> where V: Ord;
+
+    /// Returns the key-value pair in this map with the greatest value, with ties going to the
+    /// least key. Returns `None` if this map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.max_by_value(), Some((&5u32, &5u32)));
+    /// }
+    /// ```
+    fn max_by_value(&self) -> Option<(&K, &V)> where V: Ord;
+
+    /// Returns the key-value pair in this map with the least value as projected by `f`, with
+    /// ties going to the least key. Returns `None` if this map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.min_by_value_by_key(|&v| (v as i32 - 3).abs()), Some((&3u32, &3u32)));
+    /// }
+    /// ```
+    fn min_by_value_by_key<B, F>(&self, f: F) -> Option<(&K, &V)>
+        where B: Ord, F: FnMut(&V) -> B;
+
+    /// Returns the key-value pair in this map with the greatest value as projected by `f`, with
+    /// ties going to the least key. Returns `None` if this map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.max_by_value_by_key(|&v| (v as i32 - 3).abs()), Some((&1u32, &1u32)));
+    /// }
+    /// ```
+    fn max_by_value_by_key<B, F>(&self, f: F) -> Option<(&K, &V)>
+        where B: Ord, F: FnMut(&V) -> B;
+
+    /// Returns an iterator over the maximal runs of keys missing from this map between its least
+    /// and greatest keys, in ascending order. Each run is yielded as a pair (from, to) describing
+    /// the half-open range [from, to). A map whose keys are contiguous yields nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (5, 5), (7, 7)].into_iter().collect();
+    ///     assert_eq!(map.gaps().collect::<Vec<(u32, u32)>>(), vec![(3u32, 5u32), (6, 7)]);
+    /// }
+    /// ```
+    fn gaps(&self) -> Gaps<'_, K, V> where K: KeyStep;
+
+    /// Returns an iterator over each pair of adjacent key-value pairs in this map, in ascending
+    /// key order. A map with fewer than two pairs yields nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let deltas: Vec<u32> = map.pairs_iter().map(|((_, a), (_, b))| b - a).collect();
+    ///     assert_eq!(deltas, vec![1u32, 1, 1, 1]);
+    /// }
+    /// ```
+    fn pairs_iter(&self) -> PairsIter<'_, K, V>;
+
+    /// Returns an iterator over each pair of adjacent key-value pairs among those whose keys are
+    /// in the range [from_key, to_key), in ascending key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let pairs: Vec<(u32, u32)> = map.pairs_range_iter(&2, &5).rev()
+    ///                                     .map(|((&a, _), (&b, _))| (a, b)).collect();
+    ///     assert_eq!(pairs, vec![(3u32, 4u32), (2, 3)]);
+    /// }
+    /// ```
+    fn pairs_range_iter(&self, from_key: &K, to_key: &K) -> PairsIter<'_, K, V>;
+
+    /// Removes every key-value pair from this map, and returns a by-value iterator over them in
+    /// ascending key order. The map is left empty as soon as this is called, whether or not the
+    /// returned iterator is then exhausted or dropped early.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let drained: Vec<(u32, u32)> = map.drain_sorted().rev().take(2).collect();
+    ///     assert_eq!(drained, vec![(5u32, 5u32), (4, 4)]);
+    ///     assert!(map.is_empty());
+    /// }
+    /// ```
+    fn drain_sorted(&mut self) -> Self::DrainSortedIter;
+
+    /// Moves every key-value pair of `other` into this map. Where a key is in both maps,
+    /// `resolve` is called with the key, the value in this map and the value from `other`, and
+    /// its result is stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let other: BTreeMap<u32, u32> = vec![(5u32, 10u32), (6, 6)].into_iter().collect();
+    ///     map.merge_with(other, |_, mine, theirs| mine + theirs);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 15), (6, 6)]);
+    /// }
+    /// ```
+    fn merge_with<F>(&mut self, other: Self, resolve: F)
+        where Self: Sized, F: FnMut(&K, V, V) -> V;
+
+    /// Returns an iterator over the keys present in both this map and `other`, in ascending key
+    /// order, along with their values in each map. The iterator walks both maps in step, so it
+    /// never looks up a key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let other: BTreeMap<u32, &str> =
+    ///         vec![(2u32, "b"), (4, "d"), (6, "f")].into_iter().collect();
+    ///     assert_eq!(map.join_iter(&other).map(|(&k, &v, &w)| (k, v, w)).collect::<Vec<_>>(),
+    ///         vec![(2u32, 2u32, "b"), (4, 4, "d")]);
+    /// }
+    /// ```
+    fn join_iter<'b, V2>(&'b self, other: &'b BTreeMap<K, V2>) -> JoinIter<'b, K, V, V2>;
+
+    /// Returns an iterator over every key-value pair of this map, in ascending key order, along
+    /// with the value the key maps to in `other`, or `None` if it is not in `other`. Like
+    /// `join_iter`, this walks both maps in step rather than looking keys up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let other: BTreeMap<u32, &str> = vec![(2u32, "b"), (6, "f")].into_iter().collect();
+    ///     assert_eq!(map.left_join_iter(&other).map(|(&k, _, w)| (k, w.cloned()))
+    ///                   .collect::<Vec<_>>(),
+    ///         vec![(1u32, None), (2, Some("b")), (3, None), (4, None), (5, None)]);
+    /// }
+    /// ```
+    fn left_join_iter<'b, V2>(&'b self, other: &'b BTreeMap<K, V2>)
+                              -> LeftJoinIter<'b, K, V, V2>;
+
+    /// Removes every key-value pair of this map whose key is yielded by `keys`, which must yield
+    /// keys in ascending order, and returns the number of pairs removed. Keys not in this map
+    /// are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::{BTreeMap, BTreeSet};
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let tombstones: BTreeSet<u32> = vec![2u32, 4, 6].into_iter().collect();
+    ///     assert_eq!(map.remove_keys_of(&tombstones), 2);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 3, 5]);
+    /// }
+    /// ```
+    fn remove_keys_of<'b, I>(&mut self, keys: I) -> usize
+        where K: 'b, I: IntoIterator<Item=&'b K>;
+
+    /// Returns an iterator over the key-value pairs whose keys are in exactly one of this map
+    /// and `other`, in ascending key order, each tagged with the map it came from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    /// use sorted_collections::sortedmap::EitherKey;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let other: BTreeMap<u32, &str> =
+    ///         vec![(2u32, "b"), (4, "d"), (6, "f")].into_iter().collect();
+    ///     let keys: Vec<u32> = map.symmetric_difference_keys(&other).map(|either| match either {
+    ///         EitherKey::Left(&k, _) | EitherKey::Right(&k, _) => k
+    ///     }).collect();
+    ///     assert_eq!(keys, vec![1u32, 3, 5, 6]);
+    /// }
+    /// ```
+    fn symmetric_difference_keys<'b, V2>(&'b self, other: &'b BTreeMap<K, V2>)
+                                         -> SymmetricDifferenceKeys<'b, K, V, V2>;
+
+    /// Returns `true` if every key-value pair of this map is also in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let mut other = map.clone();
+    ///     other.insert(6, 6);
+    ///     assert!(map.is_submap(&other));
+    ///     assert!(!other.is_submap(&map));
+    /// }
+    /// ```
+    fn is_submap(&self, other: &Self) -> bool where V: PartialEq;
+
+    /// Returns `true` if every key of this map is also in `other`, and `eq` returns `true` for
+    /// the values each map holds for it. Stops at the first key for which either fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let other: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k + 10)).collect();
+    ///     assert!(map.is_submap_by(&other, |&a, &b| a + 10 == b));
+    ///     assert!(!map.is_submap_by(&other, |a, b| a == b));
+    /// }
+    /// ```
+    fn is_submap_by<F>(&self, other: &Self, eq: F) -> bool where F: FnMut(&V, &V) -> bool;
+
+    /// Returns an iterator over `keys`, which must be in ascending order, each paired with the
+    /// value it maps to in this map, or `None` if it is not in this map. The lookups are made in
+    /// one sweep through the map rather than one descent per key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     let probes = [0u32, 2, 3, 9];
+    ///     assert_eq!(map.get_many_sorted(&probes).map(|(&k, v)| (k, v.cloned()))
+    ///                   .collect::<Vec<(u32, Option<u32>)>>(),
+    ///         vec![(0u32, None), (2, Some(2u32)), (3, Some(3)), (9, None)]);
+    /// }
+    /// ```
+    fn get_many_sorted<'b>(&'b self, keys: &'b [K]) -> GetManySorted<'b, K, V>;
+
+    /// Removes the key-value pairs of this map whose keys are in `keys`, which must be in
+    /// ascending order, and returns the removed pairs in ascending key order. Keys not in this
+    /// map are skipped. The returned pairs hold the keys that were stored in this map, rather
+    /// than copies of the probes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.remove_many_sorted(&[0u32, 2, 4, 6]), vec![(2u32, 2u32), (4, 4)]);
+    ///     assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 3, 5]);
+    /// }
+    /// ```
+    fn remove_many_sorted(&mut self, keys: &[K]) -> Vec<(K, V)>;
+
+    /// Inserts a key-value pair if `key` is greater than every key in this map. Returns the
+    /// pair as an error instead, leaving this map untouched, if it is not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.insert_max(6, 6), Ok(()));
+    ///     assert_eq!(map.insert_max(6, 7), Err((6u32, 7u32)));
+    /// }
+    /// ```
+    fn insert_max(&mut self, key: K, value: V) -> Result<(), (K, V)>;
+
+    /// Inserts a key-value pair if `key` is less than every key in this map. Returns the pair as
+    /// an error instead, leaving this map untouched, if it is not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     assert_eq!(map.insert_min(0, 0), Ok(()));
+    ///     assert_eq!(map.insert_min(3, 3), Err((3u32, 3u32)));
+    /// }
+    /// ```
+    fn insert_min(&mut self, key: K, value: V) -> Result<(), (K, V)>;
+
+    /// Removes key-value pairs from the front of this map, in ascending key order, until the
+    /// sum of `measure` over them reaches `target`, and returns the removed pairs. If taking the
+    /// next pair whole would overshoot `target`, `split` is called with its key, its value and
+    /// the amount still wanted, and must return the part of the value to take and the part to
+    /// leave; the part left is put back into this map, and references to it are returned
+    /// alongside the removed pairs. This is also how a first pair that alone exceeds `target` is
+    /// handled. If the whole map does not reach `target`, every pair is removed and no pair is
+    /// left over.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use sorted_collections::SortedMapExt;
+    ///
+    /// fn main() {
+    ///     let mut map: BTreeMap<u32, u32> =
+    ///         vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+    ///     // Take 7 units from a book of (price, quantity) pairs.
+    ///     let (taken, left) = map.take_until_cumulative(7, |_, &qty| qty,
+    ///                                                   |_, qty, want| (want, qty - want));
+    ///     assert_eq!(taken, vec![(1u32, 1u32), (2, 2), (3, 3), (4, 1)]);
+    ///     assert_eq!(left, Some((&4u32, &3u32)));
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(4u32, 3u32), (5, 5)]);
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    fn take_until_cumulative<A, M, S>(&mut self, target: A, measure: M, split: S)
+                                      -> (Vec<(K, V)>, Option<(&K, &V)>)
+        where A: Copy + Ord + Default + Sub<Output=A>,
+              M: FnMut(&K, &V) -> A,
+              S: FnMut(&K, V, A) -> (V, V);
+
+}
+
+// A generic reusable impl of SortedMapExt.
+macro_rules! sortedmap_impl {
+    ($typ:ty) => (
+        fn histogram(&self, edges: &[K]) -> Vec<usize> {
+            crate::check_histogram_edges(edges);
+            let mut counts = vec![0; edges.len() - 1];
+            let mut bucket = 0;
+            for (key, _) in self.range_iter(&edges[0], &edges[edges.len() - 1]) {
+                while *key >= edges[bucket + 1] {
+                    bucket += 1;
+                }
+                counts[bucket] += 1;
+            }
+            counts
+        }
+
+        fn histogram_weighted<F>(&self, edges: &[K], mut weight: F) -> Vec<u64>
+            where F: FnMut(&V) -> u64
+        {
+            crate::check_histogram_edges(edges);
+            let mut sums = vec![0; edges.len() - 1];
+            let mut bucket = 0;
+            for (key, val) in self.range_iter(&edges[0], &edges[edges.len() - 1]) {
+                while *key >= edges[bucket + 1] {
+                    bucket += 1;
+                }
+                sums[bucket] += weight(val);
+            }
+            sums
+        }
+
+        fn max_gap(&self) -> Option<(&K, &K)> where K: KeyDistance {
+            crate::max_adjacent_gap(self.keys())
+        }
+
+        fn max_gap_in_range(&self, from_key: &K, to_key: &K) -> Option<(&K, &K)>
+            where K: KeyDistance
+        {
+            crate::max_adjacent_gap(self.range_iter(from_key, to_key).map(|(k, _)| k))
+        }
+
+        fn is_contiguous(&self) -> bool where K: KeyStep {
+            match (self.first(), self.last()) {
+                (Some(first), Some(last)) => first.steps_between(last) == Some(self.len() - 1),
+                _ => true,
+            }
+        }
+
+        fn is_contiguous_in_range(&self, from_key: &K, to_key: &K) -> bool where K: KeyStep {
+            crate::covers_range(self.range_iter(from_key, to_key).map(|(k, _)| k), from_key, to_key)
+        }
+
+        fn partition_point_key<F>(&self, mut pred: F) -> Option<&K>
+            where F: FnMut(&K, &V) -> bool
+        {
+            self.iter().find(|&(k, v)| !pred(k, v)).map(|(k, _)| k)
+        }
+
+        fn top_n_by_value(&self, n: usize) -> Vec<(&K, &V)> where V: Ord {
+            if n == 0 {
+                return Vec::new();
+            }
+            let mut heap = BinaryHeap::with_capacity(n);
+            for (key, val) in self.iter() {
+                let entry = ValueRank { key, val };
+                if heap.len() < n {
+                    heap.push(entry);
+                } else if entry < *heap.peek().unwrap() {
+                    heap.pop();
+                    heap.push(entry);
+                }
+            }
+            heap.into_sorted_vec().into_iter().map(|entry| (entry.key, entry.val)).collect()
+        }
+
+        fn invert(&self) -> BTreeMap<V, Vec<K>> where V: Clone + Ord {
+            let mut inverted = BTreeMap::new();
+            for (key, val) in self.iter() {
+                inverted.entry(val.clone()).or_insert_with(Vec::new).push(key.clone());
+            }
+            inverted
+        }
+
+        fn into_inverted(self) -> BTreeMap<V, Vec<K>> where V: Ord {
+            let mut inverted = BTreeMap::new();
+            for (key, val) in self.into_iter() {
+                inverted.entry(val).or_insert_with(Vec::new).push(key);
+            }
+            inverted
+        }
+
+        fn invert_unique(&self) -> Result<BTreeMap<V, K>, NonUniqueValueError<V>>
+            where V: Clone + Ord
+        {
+            let mut inverted = BTreeMap::new();
+            for (key, val) in self.iter() {
+                if inverted.insert(val.clone(), key.clone()).is_some() {
+                    return Err(NonUniqueValueError { value: val.clone() });
+                }
+            }
+            Ok(inverted)
+        }
+
+        fn group_range_by_value<G, F>(&self, from_key: &K, to_key: &K, mut proj: F)
+            -> BTreeMap<G, Vec<(&K, &V)>>
+            where G: Ord, F: FnMut(&V) -> G
+        {
+            let mut groups = BTreeMap::new();
+            if from_key < to_key {
+                for (key, val) in self.range_iter(from_key, to_key) {
+                    groups.entry(proj(val)).or_insert_with(Vec::new).push((key, val));
+                }
+            }
+            groups
+        }
+
+        fn group_range_by_value_cloned<G, F>(&self, from_key: &K, to_key: &K, mut proj: F)
+            -> BTreeMap<G, Vec<(K, V)>>
+            where V: Clone, G: Ord, F: FnMut(&V) -> G
+        {
+            let mut groups = BTreeMap::new();
+            if from_key < to_key {
+                for (key, val) in self.range_iter(from_key, to_key) {
+                    groups.entry(proj(val)).or_insert_with(Vec::new).push((key.clone(), val.clone()));
+                }
+            }
+            groups
+        }
+
+        fn insert_unique(&mut self, key: K, value: V) -> Result<(), OccupiedError<'_, K, V>> {
+            if self.contains_key(&key) {
+                let existing = self.get(&key).unwrap();
+                return Err(OccupiedError { key, value, existing });
+            }
+            self.insert(key, value);
+            Ok(())
+        }
+
+        fn push_max(&mut self, key: K, value: V) -> Result<(), NotGreaterError<K, V>> {
+            let greater = match self.last() {
+                Some(last) => key > *last,
+                None => true,
+            };
+            if greater {
+                self.insert(key, value);
+                Ok(())
+            } else {
+                Err(NotGreaterError { key, value })
+            }
+        }
+
+        fn first_n_in_range(&self, from_key: &K, to_key: &K, n: usize)
+                            -> iter::Take<Self::RangeIter<'_>> {
+            self.range_iter(from_key, to_key).take(n)
+        }
+
+        fn last_n_in_range(&self, from_key: &K, to_key: &K, n: usize)
+            -> iter::Rev<vec::IntoIter<(&K, &V)>>
+        {
+            let last_n: Vec<(&K, &V)> = self.range_iter(from_key, to_key).rev().take(n).collect();
+            last_n.into_iter().rev()
+        }
+
+        fn partition_keys<F>(&self, mut pred: F) -> (BTreeSet<K>, BTreeSet<K>)
+            where F: FnMut(&K, &V) -> bool
+        {
+            let (matching, rest): (Vec<(&K, &V)>, Vec<(&K, &V)>) =
+                self.iter().partition(|&(k, v)| pred(k, v));
+            (matching.into_iter().map(|(k, _)| k.clone()).collect(),
+             rest.into_iter().map(|(k, _)| k.clone()).collect())
+        }
+
+        fn ensure_keys_stride<F>(&mut self, from_key: &K, to_key: &K, step: usize, default: F)
+                                 -> usize
+            where K: KeyStep, F: FnMut(&K) -> V
+        {
+            assert!(step > 0, "ensure_keys_stride requires a nonzero step");
+            let mut keys = Vec::new();
+            let mut key = Some(from_key.clone());
+            while let Some(k) = key {
+                if k >= *to_key {
+                    break;
+                }
+                key = k.forward(step);
+                keys.push(k);
+            }
+            self.ensure_keys(keys, default)
+        }
+
+        fn map_values_ref<W, F>(&self, mut f: F) -> BTreeMap<K, W> where F: FnMut(&K, &V) -> W {
+            self.iter().map(|(key, val)| (key.clone(), f(key, val))).collect()
+        }
+
+        fn collect_range_truncated(&self, from_key: &K, to_key: &K, limit: usize)
+                                   -> (Vec<(K, V)>, bool) where V: Clone {
+            if from_key >= to_key {
+                return (Vec::new(), false);
+            }
+            let mut range = self.range_iter(from_key, to_key);
+            let entries = range.by_ref().take(limit).map(|(k, v)| (k.clone(), v.clone())).collect();
+            (entries, range.next().is_some())
+        }
+
+        fn contains_each_sorted(&self, probes: &[K]) -> Vec<bool> {
+            let mut out = vec![false; probes.len()];
+            self.contains_each_sorted_into(probes, &mut out);
+            out
+        }
+
+        fn contains_each_sorted_into(&self, probes: &[K], out: &mut [bool]) {
+            assert_eq!(probes.len(), out.len());
+            debug_assert!(probes.windows(2).all(|pair| pair[0] <= pair[1]),
+                          "contains_each_sorted requires sorted probes");
+            if crate::probing_is_cheaper(probes.len(), self.len()) {
+                for (probe, found) in probes.iter().zip(out.iter_mut()) {
+                    *found = self.contains_key(probe);
+                }
+            } else {
+                let mut keys = self.keys().peekable();
+                for (probe, found) in probes.iter().zip(out.iter_mut()) {
+                    while keys.peek().map_or(false, |&key| key < probe) {
+                        keys.next();
+                    }
+                    *found = keys.peek() == Some(&probe);
+                }
+            }
+        }
+
+        #[cfg(feature = "rand")]
+        fn sample_range<R>(&self, from_key: &K, to_key: &K, k: usize, rng: &mut R) -> Vec<(&K, &V)>
+            where R: ::rand::Rng
+        {
+            let mut sample = Vec::new();
+            if from_key >= to_key || k == 0 {
+                return sample;
+            }
+            for (seen, entry) in self.range_iter(from_key, to_key).enumerate() {
+                if seen < k {
+                    sample.push(entry);
+                } else {
+                    let slot = rng.gen_range(0..seen + 1);
+                    if slot < k {
+                        sample[slot] = entry;
+                    }
+                }
+            }
+            sample.sort_by(|a, b| a.0.cmp(b.0));
+            sample
+        }
+
+        fn range_any<F>(&self, from_key: &K, to_key: &K, mut pred: F) -> bool
+            where F: FnMut(&K, &V) -> bool
+        {
+            from_key < to_key && self.range_iter(from_key, to_key).any(|(k, v)| pred(k, v))
+        }
+
+        fn range_all<F>(&self, from_key: &K, to_key: &K, mut pred: F) -> bool
+            where F: FnMut(&K, &V) -> bool
+        {
+            from_key >= to_key || self.range_iter(from_key, to_key).all(|(k, v)| pred(k, v))
+        }
+
+        fn try_for_each_range<E, F>(&self, from_key: &K, to_key: &K, mut f: F) -> Result<(), E>
+            where F: FnMut(&K, &V) -> Result<(), E>
+        {
+            if from_key < to_key {
+                for (k, v) in self.range_iter(from_key, to_key) {
+                    f(k, v)?;
+                }
+            }
+            Ok(())
+        }
+
+        fn try_for_each_range_mut<E, F>(&mut self, from_key: &K, to_key: &K, mut f: F)
+                                        -> Result<(), E>
+            where F: FnMut(&K, &mut V) -> Result<(), E>
+        {
+            if from_key < to_key {
+                for (k, v) in self.range_iter_mut(from_key, to_key) {
+                    f(k, v)?;
+                }
+            }
+            Ok(())
+        }
+
+        fn count_range(&self, from_key: &K, to_key: &K) -> usize {
+            if from_key >= to_key {
+                return 0;
+            }
+            self.range_iter(from_key, to_key).count()
+        }
+
+        fn count_range_bounded(&self, min: Bound<&K>, max: Bound<&K>) -> usize {
+            self.range_iter_bounded(min, max).count()
+        }
+
+        fn fold_range<B, F>(&self, from_key: &K, to_key: &K, init: B, mut f: F) -> B
+            where F: FnMut(B, &K, &V) -> B
+        {
+            if from_key >= to_key {
+                return init;
+            }
+            self.range_iter(from_key, to_key).fold(init, |acc, (k, v)| f(acc, k, v))
+        }
+
+        fn try_fold_range<B, E, F>(&self, from_key: &K, to_key: &K, init: B, mut f: F)
+                                   -> Result<B, E>
+            where F: FnMut(B, &K, &V) -> Result<B, E>
+        {
+            let mut acc = init;
+            if from_key < to_key {
+                for (k, v) in self.range_iter(from_key, to_key) {
+                    acc = match f(acc, k, v) {
+                        Ok(acc) => acc,
+                        Err(err) => return Err(err)
+                    };
+                }
+            }
+            Ok(acc)
+        }
+
+        fn nth_key(&self, n: usize) -> Option<(&K, &V)> {
+            self.iter().nth(n)
+        }
+
+        fn nth_key_remove(&mut self, n: usize) -> Option<(K, V)> {
+            if let Some(key) = self.keys().nth(n).cloned() {
+                let val = self.remove(&key);
+                assert!(val.is_some());
+                Some((key, val.unwrap()))
+            } else {
+                None
+            }
+        }
+
+        fn rank(&self, key: &K) -> usize {
+            self.range_iter_bounded(Unbounded, Excluded(key)).count()
+        }
+
+        fn nearest(&self, key: &K) -> Option<(&K, &V)> where K: KeyDistance {
+            match (self.floor_entry(key), self.ceiling_entry(key)) {
+                (Some(lo), Some(hi)) => {
+                    if key.distance(lo.0) <= key.distance(hi.0) { Some(lo) } else { Some(hi) }
+                }
+                (lo, None) => lo,
+                (None, hi) => hi,
+            }
+        }
+
+        fn get_closest_n(&self, key: &K, n: usize) -> Vec<(&K, &V)> where K: KeyDistance {
+            self.k_nearest_entries_by(key, n, |a, b| a.distance(b))
+        }
+
+        fn update_key(&mut self, old: &K, new: K) -> Result<(), UpdateKeyError<K>> {
+            if !self.contains_key(old) {
+                return Err(UpdateKeyError::Missing(new));
+            }
+            if *old != new {
+                if self.contains_key(&new) {
+                    return Err(UpdateKeyError::Occupied(new));
+                }
+                let val = self.remove(old).unwrap();
+                self.insert(new, val);
+            }
+            Ok(())
+        }
+
+        fn update_key_with<F>(&mut self, old: &K, new: K, merge: F)
+                              -> Result<(), UpdateKeyError<K>>
+            where F: FnOnce(&K, V, V) -> V
+        {
+            if !self.contains_key(old) {
+                return Err(UpdateKeyError::Missing(new));
+            }
+            if *old != new {
+                let moved = self.remove(old).unwrap();
+                let val = match self.remove(&new) {
+                    Some(existing) => merge(&new, existing, moved),
+                    None => moved
+                };
+                self.insert(new, val);
+            }
+            Ok(())
+        }
+
+        fn min_by_value(&self) -> Option<(&K, &V)> where V: Ord {
+            self.iter().min_by_key(|&(_, v)| v)
+        }
+
+        fn max_by_value(&self) -> Option<(&K, &V)> where V: Ord {
+            // `max_by_key` returns the last of several maxima, so walk backwards to end on the
+            // least key.
+            self.iter().rev().max_by_key(|&(_, v)| v)
+        }
+
+        fn min_by_value_by_key<B, F>(&self, mut f: F) -> Option<(&K, &V)>
+            where B: Ord, F: FnMut(&V) -> B
+        {
+            self.iter().min_by_key(|&(_, v)| f(v))
+        }
+
+        fn max_by_value_by_key<B, F>(&self, mut f: F) -> Option<(&K, &V)>
+            where B: Ord, F: FnMut(&V) -> B
+        {
+            self.iter().rev().max_by_key(|&(_, v)| f(v))
+        }
+
+        fn is_submap(&self, other: &$typ) -> bool where V: PartialEq {
+            self.is_submap_by(other, |a, b| a == b)
+        }
+    );
+}
+
+// Returns the key at which to anchor an empty range if the bounds `min` and `max` describe no
+// keys at all, which `BTreeMap::range` would panic on: either `min` lies above `max`, or they
+// share a key and either excludes it.
+fn empty_bounds<'a, K: Ord>(min: Bound<&'a K>, max: Bound<&'a K>) -> Option<&'a K> {
+    match (min, max) {
+        (Included(lo), Included(hi)) if lo > hi => Some(lo),
+        (Included(lo), Excluded(hi)) | (Excluded(lo), Included(hi)) | (Excluded(lo), Excluded(hi))
+            if lo >= hi => Some(lo),
+        _ => None
+    }
+}
+
+// Every NavigableMapCore is a SortedMapNav, navigating by seeking from the probe key
+impl<K, V, M> SortedMapNav<K, V> for M
+    where K: Ord + DebugKey, M: NavigableMapCore<K, V>
+{
+    type RangeIter<'a> = M::Iter<'a> where Self: 'a, K: 'a, V: 'a;
+    type RangeIterMut<'a> = M::IterMut<'a> where Self: 'a, K: 'a, V: 'a;
+
+    fn first(&self) -> Option<&K> {
+        self.seek_ge::<K>(Unbounded)
+    }
+
+    fn last(&self) -> Option<&K> {
+        self.seek_le::<K>(Unbounded)
+    }
+
+    fn ceiling<Q>(&self, key: &Q) -> Option<&K> where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey {
+        let ceiling = self.seek_ge(Included(key));
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("ceiling", key, ceiling,
+                                   self.iter_from_bound(Unbounded, Unbounded).map(|(k, _)| k)
+                                       .filter(|&k| k.borrow() >= key).min());
+        ceiling
+    }
+
+    fn floor<Q>(&self, key: &Q) -> Option<&K> where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey {
+        let floor = self.seek_le(Included(key));
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("floor", key, floor,
+                                   self.iter_from_bound(Unbounded, Unbounded).map(|(k, _)| k)
+                                       .filter(|&k| k.borrow() <= key).max());
+        floor
+    }
+
+    fn higher<Q>(&self, key: &Q) -> Option<&K> where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey {
+        let higher = self.seek_ge(Excluded(key));
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("higher", key, higher,
+                                   self.iter_from_bound(Unbounded, Unbounded).map(|(k, _)| k)
+                                       .filter(|&k| k.borrow() > key).min());
+        higher
+    }
+
+    fn lower<Q>(&self, key: &Q) -> Option<&K> where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey {
+        let lower = self.seek_le(Excluded(key));
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("lower", key, lower,
+                                   self.iter_from_bound(Unbounded, Unbounded).map(|(k, _)| k)
+                                       .filter(|&k| k.borrow() < key).max());
+        lower
+    }
+
+    fn range_iter(&self, from_key: &K, to_key: &K) -> M::Iter<'_> {
+        // iter_from_bound may panic on inverted bounds, so clamp them to an empty range instead.
+        let to_key = if from_key > to_key { from_key } else { to_key };
+        self.iter_from_bound(Included(from_key), Excluded(to_key))
+    }
+
+    fn range_iter_mut(&mut self, from_key: &K, to_key: &K) -> M::IterMut<'_> {
+        let to_key = if from_key > to_key { from_key } else { to_key };
+        self.iter_mut_from_bound(Included(from_key), Excluded(to_key))
+    }
+
+    fn range_iter_bounded(&self, min: Bound<&K>, max: Bound<&K>) -> M::Iter<'_> {
+        if let Some(key) = empty_bounds(min, max) {
+            return self.iter_from_bound(Included(key), Excluded(key));
+        }
+        self.iter_from_bound(min, max)
+    }
+
+    fn range_iter_mut_bounded(&mut self, min: Bound<&K>, max: Bound<&K>) -> M::IterMut<'_> {
+        if let Some(key) = empty_bounds(min, max) {
+            return self.iter_mut_from_bound(Included(key), Excluded(key));
+        }
+        self.iter_mut_from_bound(min, max)
+    }
+
+    fn range_iter_by<R>(&self, range: R) -> M::Iter<'_> where R: RangeBounds<K> {
+        self.range_iter_bounded(range.start_bound(), range.end_bound())
+    }
+
+    fn range_iter_mut_by<R>(&mut self, range: R) -> M::IterMut<'_> where R: RangeBounds<K> {
+        self.range_iter_mut_bounded(range.start_bound(), range.end_bound())
+    }
+
+    fn range_iter_inclusive(&self, from_key: &K, to_key: &K) -> M::Iter<'_> {
+        if from_key > to_key {
+            return self.iter_from_bound(Included(from_key), Excluded(from_key));
+        }
+        self.iter_from_bound(Included(from_key), Included(to_key))
+    }
+
+    fn range_iter_mut_inclusive(&mut self, from_key: &K, to_key: &K) -> M::IterMut<'_> {
+        if from_key > to_key {
+            return self.iter_mut_from_bound(Included(from_key), Excluded(from_key));
+        }
+        self.iter_mut_from_bound(Included(from_key), Included(to_key))
+    }
+}
+
+// Every NavigableMapCore is a SortedMapRemove, removing the key its navigation finds
+impl<K, V, M> SortedMapRemove<K, V> for M
+    where K: Clone + Ord + DebugKey, M: NavigableMapCore<K, V>
+{
+    type RangeRemoveIter = BTreeMapRangeRemoveIter<K, V>;
+
+    fn first_remove(&mut self) -> Option<(K, V)> {
+        self.first().cloned().and_then(|key| self.remove_key(&key))
+    }
+
+    fn last_remove(&mut self) -> Option<(K, V)> {
+        self.last().cloned().and_then(|key| self.remove_key(&key))
+    }
+
+    fn ceiling_remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        self.ceiling(key).cloned().and_then(|ceiling| self.remove_key(&ceiling))
+    }
+
+    fn floor_remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        self.floor(key).cloned().and_then(|floor| self.remove_key(&floor))
+    }
+
+    fn higher_remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        self.higher(key).cloned().and_then(|higher| self.remove_key(&higher))
+    }
+
+    fn lower_remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        self.lower(key).cloned().and_then(|lower| self.remove_key(&lower))
+    }
+
+    fn range_remove_iter(&mut self, from_key: &K, to_key: &K) -> BTreeMapRangeRemoveIter<K, V> {
+        let removed = if from_key < to_key {
+            self.remove_range(Included(from_key), Excluded(to_key))
+        } else {
+            BTreeMap::new()
+        };
+        BTreeMapRangeRemoveIter { iter: removed.into_iter() }
+    }
+
+    fn range_remove_iter_inclusive(&mut self, from_key: &K, to_key: &K)
+                                   -> BTreeMapRangeRemoveIter<K, V> {
+        let removed = if from_key <= to_key {
+            self.remove_range(Included(from_key), Included(to_key))
+        } else {
+            BTreeMap::new()
+        };
+        BTreeMapRangeRemoveIter { iter: removed.into_iter() }
+    }
+}
+
+// An impl of NavigableMapCore for the standard library BTreeMap
+impl<K, V> NavigableMapCore<K, V> for BTreeMap<K, V>
+    where K: Ord + DebugKey
+{
+    type Iter<'a> = BTreeMapRangeIter<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    type IterMut<'a> = BTreeMapRangeIterMut<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+
+    fn seek_ge<Q>(&self, bound: Bound<&Q>) -> Option<&K>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        self.range((bound, Unbounded)).next().map(|(k, _)| k)
+    }
+
+    fn seek_le<Q>(&self, bound: Bound<&Q>) -> Option<&K>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        self.range((Unbounded, bound)).next_back().map(|(k, _)| k)
+    }
+
+    fn remove_key(&mut self, key: &K) -> Option<(K, V)> {
+        self.remove_entry(key)
+    }
+
+    fn iter_from_bound(&self, min: Bound<&K>, max: Bound<&K>) -> BTreeMapRangeIter<'_, K, V> {
+        BTreeMapRangeIter { iter: self.range((min, max)) }
+    }
+
+    fn iter_mut_from_bound(&mut self, min: Bound<&K>, max: Bound<&K>)
+                           -> BTreeMapRangeIterMut<'_, K, V> {
+        BTreeMapRangeIterMut { iter: self.range_mut((min, max)) }
+    }
+
+    fn remove_range(&mut self, min: Bound<&K>, max: Bound<&K>) -> BTreeMap<K, V> where K: Clone {
+        // Split the range off whole rather than removing it one key at a time.
+        let mut removed = match min {
+            Included(from_key) => self.split_off(from_key),
+            Excluded(from_key) => {
+                let mut removed = self.split_off(from_key);
+                if let Some((k, v)) = removed.remove_entry(from_key) {
+                    self.insert(k, v);
+                }
+                removed
+            }
+            Unbounded => mem::take(self)
+        };
+        let mut above = match max {
+            Included(to_key) => {
+                let mut above = removed.split_off(to_key);
+                if let Some((k, v)) = above.remove_entry(to_key) {
+                    removed.insert(k, v);
+                }
+                above
+            }
+            Excluded(to_key) => removed.split_off(to_key),
+            Unbounded => BTreeMap::new()
+        };
+        self.append(&mut above);
+        removed
+    }
+}
+
+// An impl of SortedMapExt for the standard library BTreeMap
+impl<K, V> SortedMapExt<K, V> for BTreeMap<K, V>
+    where K: Clone + Ord + DebugKey
+{
+    type ByValueIter<'a> = BTreeMapByValueIter<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    type IntoKeysRangeIter = BTreeMapIntoKeysRangeIter<K, V>;
+    type IntoValuesRangeIter = BTreeMapIntoValuesRangeIter<K, V>;
+    type DescendingIter<'a> = BTreeMapDescendingIter<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    type DescendingIterMut<'a> = BTreeMapDescendingIterMut<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    type RangeDescIter<'a> = BTreeMapRangeDescIter<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    type RangeDescIterMut<'a> = BTreeMapRangeDescIterMut<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    type KeysRangeIter<'a> = BTreeMapKeysRangeIter<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    type ValuesRangeIter<'a> = BTreeMapValuesRangeIter<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    type ValuesRangeIterMut<'a> = BTreeMapValuesRangeIterMut<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+    type DrainSortedIter = BTreeMapDrainSortedIter<K, V>;
+
+    sortedmap_impl!(BTreeMap<K, V>);
+
+    fn sorted_by_value_iter(&self) -> BTreeMapByValueIter<'_, K, V> where V: Ord {
+        self.sorted_by_value_iter_by(|a, b| a.cmp(b))
+    }
+
+    fn sorted_by_value_iter_by<F>(&self, mut compare: F) -> BTreeMapByValueIter<'_, K, V>
+        where F: FnMut(&V, &V) -> Ordering
+    {
+        // The sort is stable, so pairs with equal values keep their ascending key order.
+        let mut pairs: Vec<(&K, &V)> = self.iter().collect();
+        pairs.sort_by(|a, b| compare(a.1, b.1));
+        BTreeMapByValueIter { iter: pairs.into_iter() }
+    }
+
+    fn first_remove_if<F>(&mut self, pred: F) -> Option<(K, V)> where F: FnOnce(&K, &V) -> bool {
+        let matched = match self.iter().next() {
+            Some((key, val)) => pred(key, val),
+            None => false,
+        };
+        if matched { self.pop_first() } else { None }
+    }
+
+    fn last_remove_if<F>(&mut self, pred: F) -> Option<(K, V)> where F: FnOnce(&K, &V) -> bool {
+        let matched = match self.iter().next_back() {
+            Some((key, val)) => pred(key, val),
+            None => false,
+        };
+        if matched { self.pop_last() } else { None }
+    }
+
+    fn keys_to_set(&self) -> BTreeSet<K> {
+        // The keys arrive in order, so the set is bulk-built rather than inserted key by key.
+        self.keys().cloned().collect()
+    }
+
+    fn retain_keys(&mut self, keys: &BTreeSet<K>) {
+        if crate::probing_is_cheaper(keys.len(), self.len()) {
+            let mut kept = BTreeMap::new();
+            for key in keys.iter() {
+                if let Some((key, val)) = self.remove_entry(key) {
+                    kept.insert(key, val);
+                }
+            }
+            *self = kept;
+        } else {
+            let mut probes = keys.iter().peekable();
+            let old = std::mem::take(self);
+            *self = old.into_iter().filter(|(key, _)| {
+                while probes.peek().is_some_and(|&probe| probe < key) {
+                    probes.next();
+                }
+                probes.peek() == Some(&key)
+            }).collect();
+        }
+    }
+
+    fn remove_keys(&mut self, keys: &BTreeSet<K>) -> usize {
+        let old_len = self.len();
+        if crate::probing_is_cheaper(keys.len(), self.len()) {
+            for key in keys.iter() {
+                self.remove(key);
+            }
+        } else {
+            let mut probes = keys.iter().peekable();
+            let old = std::mem::take(self);
+            *self = old.into_iter().filter(|(key, _)| {
+                while probes.peek().is_some_and(|&probe| probe < key) {
+                    probes.next();
+                }
+                probes.peek() != Some(&key)
+            }).collect();
+        }
+        old_len - self.len()
+    }
+
+    fn into_keys_range(self, from_key: &K, to_key: &K) -> BTreeMapIntoKeysRangeIter<K, V> {
+        BTreeMapIntoKeysRangeIter { iter: into_range(self, from_key, to_key).into_iter() }
+    }
+
+    fn into_values_range(self, from_key: &K, to_key: &K) -> BTreeMapIntoValuesRangeIter<K, V> {
+        BTreeMapIntoValuesRangeIter { iter: into_range(self, from_key, to_key).into_iter() }
+    }
+
+    fn ceiling_owned(&self, key: K) -> Option<(K, &V)> {
+        let ceiling = self.range((Included(&key), Unbounded)).next();
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("ceiling_owned", &key, ceiling.map(|(k, _)| k),
+                              self.keys().filter(|&k| *k >= key).min());
+        ceiling.map(|(k, v)| (k.clone(), v))
+    }
+
+    fn remove_ceiling(&mut self, key: K) -> Result<(K, V), K> {
+        let ceiling = self.range((Included(&key), Unbounded)).next().map(|(k, _)| k);
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("remove_ceiling", &key, ceiling,
+                              self.keys().filter(|&k| *k >= key).min());
+        let ceiling = match ceiling {
+            Some(k) => k.clone(),
+            None => return Err(key),
+        };
+        let val = self.remove(&ceiling);
+        assert!(val.is_some());
+        Ok((ceiling, val.unwrap()))
+    }
+
+    fn remove_floor(&mut self, key: K) -> Result<(K, V), K> {
+        let floor = self.range((Unbounded, Included(&key))).next_back().map(|(k, _)| k);
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("remove_floor", &key, floor,
+                              self.keys().filter(|&k| *k <= key).max());
+        let floor = match floor {
+            Some(k) => k.clone(),
+            None => return Err(key),
+        };
+        let val = self.remove(&floor);
+        assert!(val.is_some());
+        Ok((floor, val.unwrap()))
+    }
+
+    fn range_position(&self, from_key: &K, to_key: &K) -> (usize, usize) {
+        let start = self.range((Unbounded, Excluded(from_key))).count();
+        if from_key < to_key {
+            (start, start + self.range((Included(from_key), Excluded(to_key))).count())
+        } else {
+            (start, start)
+        }
+    }
+
+    fn append_assert_disjoint(&mut self, other: &mut BTreeMap<K, V>) -> Result<(), OverlapError<K>> {
+        if let (Some(self_first), Some(self_last)) = (self.keys().next(), self.keys().next_back()) {
+            if let (Some(other_first), Some(other_last)) =
+                    (other.keys().next(), other.keys().next_back()) {
+                if other_first <= self_last && self_first <= other_last {
+                    let key = if self_first < other_first { other_first } else { self_first };
+                    return Err(OverlapError { key: key.clone() });
+                }
+            }
+        }
+        self.append(other);
+        Ok(())
+    }
+
+    fn rolling_range_iter(&self, width: K::Distance) -> RollingRangeIter<'_, K, V>
+        where K: KeyDistance
+    {
+        RollingRangeIter { start: self.iter(), len: 0, ahead: self.iter(), width }
+    }
+
+    fn page_after(&self, cursor: Option<&K>, n: usize) -> Page<'_, K, V> {
+        match cursor {
+            Some(cursor) => Page::take(self.range((Excluded(cursor), Unbounded)), n),
+            None => Page::take(self.iter(), n),
+        }
+    }
+
+    fn page_before(&self, cursor: Option<&K>, n: usize) -> Page<'_, K, V> {
+        match cursor {
+            Some(cursor) => Page::take(self.range((Unbounded, Excluded(cursor))).rev(), n),
+            None => Page::take(self.iter().rev(), n),
+        }
+    }
+
+    fn binary_partition<F>(self, mut pred: F) -> (BTreeMap<K, V>, BTreeMap<K, V>)
+        where F: FnMut(&K, &V) -> bool
+    {
+        let (matching, rest): (BTreeMap<K, V>, BTreeMap<K, V>) =
+            self.into_iter().partition(|(k, v)| pred(k, v));
+        (matching, rest)
+    }
+
+    fn ensure_keys<I, F>(&mut self, keys: I, mut default: F) -> usize
+        where I: IntoIterator<Item = K>, F: FnMut(&K) -> V
+    {
+        let mut keys: Vec<K> = keys.into_iter().collect();
+        keys.sort();
+        keys.dedup();
+        if crate::probing_is_cheaper(keys.len(), self.len()) {
+            let old_len = self.len();
+            for key in keys {
+                if let btree_map::Entry::Vacant(entry) = self.entry(key) {
+                    let val = default(entry.key());
+                    entry.insert(val);
+                }
+            }
+            self.len() - old_len
+        } else {
+            let mut missing = Vec::new();
+            {
+                let mut existing = self.keys().peekable();
+                for key in keys {
+                    while existing.peek().is_some_and(|&k| *k < key) {
+                        existing.next();
+                    }
+                    if existing.peek() != Some(&&key) {
+                        let val = default(&key);
+                        missing.push((key, val));
+                    }
+                }
+            }
+            let created = missing.len();
+            self.append(&mut missing.into_iter().collect());
+            created
+        }
+    }
+
+    fn map_keys_monotonic<K2, F>(self, mut f: F) -> BTreeMap<K2, V>
+        where K2: Ord, F: FnMut(K) -> K2
+    {
+        let mapped: Vec<(K2, V)> = self.into_iter().map(|(key, val)| (f(key), val)).collect();
+        debug_assert!(mapped.windows(2).all(|pair| pair[0].0 < pair[1].0),
+                      "map_keys_monotonic requires a strictly increasing key transformation");
+        mapped.into_iter().collect()
+    }
+
+    fn try_map_keys_monotonic<K2, F>(self, mut f: F)
+                                     -> Result<BTreeMap<K2, V>, NotMonotonicError<K2>>
+        where K2: Ord, F: FnMut(K) -> K2
+    {
+        let mut mapped: Vec<(K2, V)> = Vec::with_capacity(self.len());
+        for (key, val) in self {
+            let key = f(key);
+            if mapped.last().is_some_and(|(last, _)| key <= *last) {
+                return Err(NotMonotonicError { key });
+            }
+            mapped.push((key, val));
+        }
+        Ok(mapped.into_iter().collect())
+    }
+
+    fn map_values<W, F>(self, mut f: F) -> BTreeMap<K, W> where F: FnMut(&K, V) -> W {
+        self.into_iter().map(|(key, val)| {
+            let val = f(&key, val);
+            (key, val)
+        }).collect()
+    }
+
+    fn map_values_in_range<F>(mut self, from_key: &K, to_key: &K, f: F) -> BTreeMap<K, V>
+        where F: FnMut(&K, V) -> V
+    {
+        if from_key >= to_key {
+            return self;
+        }
+        let mut window = self.split_off(from_key);
+        let mut above = window.split_off(to_key);
+        self.append(&mut window.map_values(f));
+        self.append(&mut above);
+        self
+    }
+
+    fn first_run_by<G, F>(&self, proj: F) -> RunIter<'_, K, V, G, F>
+        where G: PartialEq, F: FnMut(&K) -> G
+    {
+        RunIter { iter: self.iter(), proj, head: None, rev: false, done: false }
+    }
+
+    fn last_run_by<G, F>(&self, proj: F) -> RunIter<'_, K, V, G, F>
+        where G: PartialEq, F: FnMut(&K) -> G
+    {
+        RunIter { iter: self.iter(), proj, head: None, rev: true, done: false }
+    }
+
+    fn first_run_remove_by<G, F>(&mut self, mut proj: F) -> BTreeMapRangeRemoveIter<K, V>
+        where G: PartialEq, F: FnMut(&K) -> G
+    {
+        let end = {
+            let mut keys = self.keys();
+            match keys.next() {
+                Some(first) => {
+                    let head = proj(first);
+                    keys.find(|&key| proj(key) != head).cloned()
+                }
+                None => None,
+            }
+        };
+        let rest = match end {
+            Some(ref end) => self.split_off(end),
+            None => BTreeMap::new(),
+        };
+        BTreeMapRangeRemoveIter { iter: mem::replace(self, rest).into_iter() }
+    }
+
+    fn chunk_range_by_weight<F>(&self, from_key: &K, to_key: &K, max: u64, weight: F)
+                                -> WeightChunks<'_, K, V, F>
+        where F: FnMut(&K, &V) -> u64
+    {
+        let range = if from_key < to_key {
+            self.range((Included(from_key), Excluded(to_key)))
+        } else {
+            self.range((Included(from_key), Excluded(from_key)))
+        };
+        WeightChunks { range, max, weight, pending: None }
+    }
+
+    fn dedup_values_in_range(&mut self, from_key: &K, to_key: &K) -> usize where V: PartialEq {
+        if from_key >= to_key {
+            return 0;
+        }
+        let doomed: Vec<K> = {
+            let mut retained = self.range((Unbounded, Excluded(from_key))).next_back()
+                                   .map(|(_, v)| v);
+            let mut doomed = Vec::new();
+            for (key, val) in self.range((Included(from_key), Excluded(to_key))) {
+                if retained == Some(val) {
+                    doomed.push(key.clone());
+                } else {
+                    retained = Some(val);
+                }
+            }
+            doomed
+        };
+        for key in doomed.iter() {
+            self.remove(key);
+        }
+        doomed.len()
+    }
+
+    fn drain_range_in_batches(&mut self, from_key: &K, to_key: &K, batch: usize)
+                              -> DrainBatches<'_, K, V> {
+        assert!(batch > 0, "drain_range_in_batches requires a nonzero batch size");
+        DrainBatches { map: self, from: from_key.clone(), to: to_key.clone(), last: None,
+                       batch }
+    }
+
+    fn k_nearest_entries_by<D, F>(&self, probe: &K, k: usize, dist: F) -> Vec<(&K, &V)>
+        where D: Ord, F: Fn(&K, &K) -> D
+    {
+        let mut below = self.range((Unbounded, Excluded(probe))).rev().peekable();
+        let mut above = self.range((Included(probe), Unbounded)).peekable();
+        let mut nearest = Vec::new();
+        while nearest.len() < k {
+            let take_below = match (below.peek(), above.peek()) {
+                (Some(&(lo, _)), Some(&(hi, _))) => dist(probe, lo) <= dist(probe, hi),
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            nearest.push(if take_below { below.next() } else { above.next() }.unwrap());
+        }
+        nearest
+    }
+
+    fn first_entry(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    fn last_entry(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
+
+    fn ceiling_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        let ceiling = self.range((Included(key), Unbounded)).next();
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("ceiling_entry", key, ceiling.map(|(k, _)| k),
+                              self.keys().filter(|&k| k.borrow() >= key).min());
+        ceiling
+    }
+
+    fn floor_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        let floor = self.range((Unbounded, Included(key))).next_back();
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("floor_entry", key, floor.map(|(k, _)| k),
+                              self.keys().filter(|&k| k.borrow() <= key).max());
+        floor
+    }
+
+    fn higher_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        let higher = self.range((Excluded(key), Unbounded)).next();
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("higher_entry", key, higher.map(|(k, _)| k),
+                              self.keys().filter(|&k| k.borrow() > key).min());
+        higher
+    }
+
+    fn lower_entry<Q>(&self, key: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        let lower = self.range((Unbounded, Excluded(key))).next_back();
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("lower_entry", key, lower.map(|(k, _)| k),
+                              self.keys().filter(|&k| k.borrow() < key).max());
+        lower
+    }
+
+    fn ceiling_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        // The search is checked through `range`, which descends the tree just as `range_mut`
+        // does, as the linear scan cannot borrow the map alongside the returned value.
+        #[cfg(feature = "debug-validate")]
+        {
+            let searched = self.range((Included(key), Unbounded)).next().map(|(k, _)| k);
+            crate::validate_navigation("ceiling_mut", key, searched,
+                                       self.keys().filter(|&k| k.borrow() >= key).min());
+        }
+        self.range_mut((Included(key), Unbounded)).next()
+    }
+
+    fn floor_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        #[cfg(feature = "debug-validate")]
+        {
+            let searched = self.range((Unbounded, Included(key))).next_back().map(|(k, _)| k);
+            crate::validate_navigation("floor_mut", key, searched,
+                                       self.keys().filter(|&k| k.borrow() <= key).max());
+        }
+        self.range_mut((Unbounded, Included(key))).next_back()
+    }
+
+    fn higher_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        #[cfg(feature = "debug-validate")]
+        {
+            let searched = self.range((Excluded(key), Unbounded)).next().map(|(k, _)| k);
+            crate::validate_navigation("higher_mut", key, searched,
+                                       self.keys().filter(|&k| k.borrow() > key).min());
+        }
+        self.range_mut((Excluded(key), Unbounded)).next()
+    }
+
+    fn lower_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+        where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+    {
+        #[cfg(feature = "debug-validate")]
+        {
+            let searched = self.range((Unbounded, Excluded(key))).next_back().map(|(k, _)| k);
+            crate::validate_navigation("lower_mut", key, searched,
+                                       self.keys().filter(|&k| k.borrow() < key).max());
+        }
+        self.range_mut((Unbounded, Excluded(key))).next_back()
+    }
+
+    fn neighbors(&self, key: &K) -> (Option<(&K, &V)>, Option<(&K, &V)>, Option<(&K, &V)>) {
+        let mut below = self.range((Unbounded, Included(key)));
+        let (lower, exact) = match below.next_back() {
+            Some(entry) if entry.0 == key => (below.next_back(), Some(entry)),
+            other => (other, None)
+        };
+        (lower, exact, self.range((Excluded(key), Unbounded)).next())
+    }
+
+    fn iter_from(&self, key: &K) -> BTreeMapRangeIter<'_, K, V> {
+        self.range_iter_bounded(Included(key), Unbounded)
+    }
+
+    fn iter_from_mut(&mut self, key: &K) -> BTreeMapRangeIterMut<'_, K, V> {
+        self.range_iter_mut_bounded(Included(key), Unbounded)
+    }
+
+    fn iter_until(&self, key: &K) -> BTreeMapRangeIter<'_, K, V> {
+        self.range_iter_bounded(Unbounded, Excluded(key))
+    }
+
+    fn iter_until_inclusive(&self, key: &K) -> BTreeMapRangeIter<'_, K, V> {
+        self.range_iter_bounded(Unbounded, Included(key))
+    }
+
+    fn iter_until_mut(&mut self, key: &K) -> BTreeMapRangeIterMut<'_, K, V> {
+        self.range_iter_mut_bounded(Unbounded, Excluded(key))
+    }
+
+    fn descending_iter(&self) -> BTreeMapDescendingIter<'_, K, V> {
+        BTreeMapDescendingIter { iter: self.iter().rev() }
+    }
+
+    fn descending_iter_mut(&mut self) -> BTreeMapDescendingIterMut<'_, K, V> {
+        BTreeMapDescendingIterMut { iter: self.iter_mut().rev() }
+    }
+
+    fn range_iter_desc(&self, from_key: &K, to_key: &K) -> BTreeMapRangeDescIter<'_, K, V> {
+        let to_key = if from_key > to_key { from_key } else { to_key };
+        BTreeMapRangeDescIter { iter: self.range((Included(from_key), Excluded(to_key))).rev() }
+    }
+
+    fn range_iter_desc_mut(&mut self, from_key: &K, to_key: &K)
+                           -> BTreeMapRangeDescIterMut<'_, K, V> {
+        let to_key = if from_key > to_key { from_key } else { to_key };
+        BTreeMapRangeDescIterMut {
+            iter: self.range_mut((Included(from_key), Excluded(to_key))).rev()
+        }
+    }
+
+    fn retain_range<F>(&mut self, from_key: &K, to_key: &K, mut pred: F)
+        where F: FnMut(&K, &mut V) -> bool
+    {
+        if from_key >= to_key {
+            return;
+        }
+        let doomed: Vec<K> = self.range_mut((Included(from_key), Excluded(to_key)))
+                                 .filter_map(|(k, v)| {
+                                     if pred(k, v) { None } else { Some(k.clone()) }
+                                 })
+                                 .collect();
+        for key in doomed.iter() {
+            self.remove(key);
+        }
+    }
+
+    fn range_remove_if<F>(&mut self, from_key: &K, to_key: &K, mut pred: F)
+                          -> BTreeMapRangeRemoveIter<K, V>
+        where F: FnMut(&K, &V) -> bool
+    {
+        let mut ret = BTreeMap::new();
+        if from_key < to_key {
+            let doomed: Vec<K> = self.range((Included(from_key), Excluded(to_key)))
+                                     .filter(|&(k, v)| pred(k, v))
+                                     .map(|(k, _)| k.clone())
+                                     .collect();
+            for key in doomed.into_iter() {
+                let val = self.remove(&key).unwrap();
+                ret.insert(key, val);
+            }
+        }
+        BTreeMapRangeRemoveIter { iter: ret.into_iter() }
+    }
+
+    fn split_off_range(&mut self, from_key: &K, to_key: &K) -> BTreeMap<K, V> {
+        if from_key >= to_key {
+            return BTreeMap::new();
+        }
+        let mut range = self.split_off(from_key);
+        let mut above = range.split_off(to_key);
+        self.append(&mut above);
+        range
+    }
+
+    fn pop_first_n(&mut self, n: usize) -> BTreeMapRangeRemoveIter<K, V> {
+        let rest = match self.keys().nth(n).cloned() {
+            Some(key) => self.split_off(&key),
+            None => BTreeMap::new()
+        };
+        BTreeMapRangeRemoveIter { iter: mem::replace(self, rest).into_iter() }
+    }
+
+    fn pop_last_n(&mut self, n: usize) -> BTreeMapRangeRemoveIter<K, V> {
+        let popped = if n >= self.len() {
+            std::mem::take(self)
+        } else if n == 0 {
+            BTreeMap::new()
+        } else {
+            let key = self.keys().rev().nth(n - 1).unwrap().clone();
+            self.split_off(&key)
+        };
+        BTreeMapRangeRemoveIter { iter: popped.into_iter() }
+    }
+
+    fn pop_while_front<F>(&mut self, mut pred: F) -> BTreeMapRangeRemoveIter<K, V>
+        where F: FnMut(&K, &V) -> bool
+    {
+        let rest = match self.iter().find(|&(k, v)| !pred(k, v)).map(|(k, _)| k.clone()) {
+            Some(key) => self.split_off(&key),
+            None => BTreeMap::new()
+        };
+        BTreeMapRangeRemoveIter { iter: mem::replace(self, rest).into_iter() }
+    }
+
+    fn pop_while_back<F>(&mut self, mut pred: F) -> BTreeMapRangeRemoveIter<K, V>
+        where F: FnMut(&K, &V) -> bool
+    {
+        let least_popped = self.iter().rev().take_while(|&(k, v)| pred(k, v)).last()
+                               .map(|(k, _)| k.clone());
+        let popped = match least_popped {
+            Some(key) => self.split_off(&key),
+            None => BTreeMap::new()
+        };
+        BTreeMapRangeRemoveIter { iter: popped.into_iter() }
+    }
+
+    fn truncate_before(&mut self, key: &K) -> usize {
+        let rest = self.split_off(key);
+        mem::replace(self, rest).len()
+    }
+
+    fn truncate_after(&mut self, key: &K) -> usize {
+        match self.range((Excluded(key), Unbounded)).next().map(|(k, _)| k.clone()) {
+            Some(higher) => self.split_off(&higher).len(),
+            None => 0
+        }
+    }
+
+    fn keys_range(&self, from_key: &K, to_key: &K) -> BTreeMapKeysRangeIter<'_, K, V> {
+        let to_key = if from_key > to_key { from_key } else { to_key };
+        BTreeMapKeysRangeIter { iter: self.range((Included(from_key), Excluded(to_key))) }
+    }
+
+    fn values_range(&self, from_key: &K, to_key: &K) -> BTreeMapValuesRangeIter<'_, K, V> {
+        let to_key = if from_key > to_key { from_key } else { to_key };
+        BTreeMapValuesRangeIter { iter: self.range((Included(from_key), Excluded(to_key))) }
+    }
+
+    fn values_range_mut(&mut self, from_key: &K, to_key: &K)
+                        -> BTreeMapValuesRangeIterMut<'_, K, V> {
+        let to_key = if from_key > to_key { from_key } else { to_key };
+        BTreeMapValuesRangeIterMut { iter: self.range_mut((Included(from_key), Excluded(to_key))) }
+    }
+
+    fn replace_range<I>(&mut self, from_key: &K, to_key: &K, entries: I)
+                        -> BTreeMapRangeRemoveIter<K, V>
+        where I: IntoIterator<Item=(K, V)>
+    {
+        let removed = self.split_off_range(from_key, to_key);
+        for (key, val) in entries {
+            self.insert(key, val);
+        }
+        BTreeMapRangeRemoveIter { iter: removed.into_iter() }
+    }
+
+    fn extend_from_sorted<I>(&mut self, iter: I) where I: IntoIterator<Item=(K, V)> {
+        let entries: Vec<(K, V)> = iter.into_iter().collect();
+        debug_assert!(entries.windows(2).all(|pair| pair[0].0 <= pair[1].0),
+                      "extend_from_sorted requires its input to be sorted by key");
+        let above = match (self.keys().next_back(), entries.first()) {
+            (Some(last), Some(first)) => first.0 > *last,
+            _ => true
+        };
+        if above || !crate::probing_is_cheaper(entries.len(), self.len()) {
+            self.append(&mut entries.into_iter().collect());
+        } else {
+            self.extend(entries);
+        }
+    }
+
+    fn gaps(&self) -> Gaps<'_, K, V> where K: KeyStep {
+        Gaps { keys: self.keys(), prev: None }
+    }
+
+    fn pairs_iter(&self) -> PairsIter<'_, K, V> {
+        PairsIter { iter: self.range_iter_bounded(Unbounded, Unbounded), front: None, back: None }
+    }
+
+    fn pairs_range_iter(&self, from_key: &K, to_key: &K) -> PairsIter<'_, K, V> {
+        PairsIter { iter: self.range_iter(from_key, to_key), front: None, back: None }
+    }
+
+    fn drain_sorted(&mut self) -> BTreeMapDrainSortedIter<K, V> {
+        BTreeMapDrainSortedIter { iter: std::mem::take(self).into_iter() }
+    }
+
+    fn merge_with<F>(&mut self, other: BTreeMap<K, V>, mut resolve: F)
+        where F: FnMut(&K, V, V) -> V
+    {
+        let above = match (self.keys().next_back(), other.keys().next()) {
+            (Some(last), Some(first)) => first > last,
+            _ => true
+        };
+        if above {
+            let mut other = other;
+            self.append(&mut other);
+        } else if crate::probing_is_cheaper(other.len(), self.len()) {
+            for (key, incoming) in other {
+                let val = match self.remove::<K>(&key) {
+                    Some(existing) => resolve(&key, existing, incoming),
+                    None => incoming
+                };
+                self.insert(key, val);
+            }
+        } else {
+            let mut mine = std::mem::take(self).into_iter().peekable();
+            let mut theirs = other.into_iter().peekable();
+            let mut merged: Vec<(K, V)> = Vec::with_capacity(mine.len() + theirs.len());
+            loop {
+                let order = match (mine.peek(), theirs.peek()) {
+                    (Some((a, _)), Some((b, _))) => a.cmp(b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => break
+                };
+                match order {
+                    Ordering::Less => merged.push(mine.next().unwrap()),
+                    Ordering::Greater => merged.push(theirs.next().unwrap()),
+                    Ordering::Equal => {
+                        let (key, existing) = mine.next().unwrap();
+                        let (_, incoming) = theirs.next().unwrap();
+                        let val = resolve(&key, existing, incoming);
+                        merged.push((key, val));
+                    }
+                }
+            }
+            *self = merged.into_iter().collect();
+        }
+    }
+
+    fn join_iter<'b, V2>(&'b self, other: &'b BTreeMap<K, V2>) -> JoinIter<'b, K, V, V2> {
+        JoinIter { left: self.iter().peekable(), right: other.iter().peekable() }
+    }
+
+    fn left_join_iter<'b, V2>(&'b self, other: &'b BTreeMap<K, V2>)
+                              -> LeftJoinIter<'b, K, V, V2> {
+        LeftJoinIter { left: self.iter(), right: other.iter().peekable() }
+    }
+
+    fn remove_keys_of<'b, I>(&mut self, keys: I) -> usize
+        where K: 'b, I: IntoIterator<Item=&'b K>
+    {
+        let keys: Vec<&K> = keys.into_iter().collect();
+        debug_assert!(keys.windows(2).all(|pair| pair[0] <= pair[1]),
+                      "remove_keys_of requires its keys to be sorted");
+        let len = self.len();
+        if crate::probing_is_cheaper(keys.len(), len) {
+            for key in keys {
+                self.remove(key);
+            }
+        } else {
+            let mut doomed = keys.into_iter().peekable();
+            let kept: BTreeMap<K, V> = std::mem::take(self).into_iter()
+                .filter(|(key, _)| {
+                    while doomed.peek().is_some_and(|&next| next < key) {
+                        doomed.next();
+                    }
+                    doomed.peek().is_none_or(|&next| next != key)
+                })
+                .collect();
+            *self = kept;
+        }
+        len - self.len()
+    }
+
+    fn symmetric_difference_keys<'b, V2>(&'b self, other: &'b BTreeMap<K, V2>)
+                                         -> SymmetricDifferenceKeys<'b, K, V, V2> {
+        SymmetricDifferenceKeys { left: self.iter().peekable(), right: other.iter().peekable() }
+    }
+
+    fn is_submap_by<F>(&self, other: &BTreeMap<K, V>, mut eq: F) -> bool
+        where F: FnMut(&V, &V) -> bool
+    {
+        if self.len() > other.len() {
+            return false;
+        }
+        let mut theirs = other.iter();
+        'outer: for (key, val) in self.iter() {
+            for (other_key, other_val) in theirs.by_ref() {
+                match other_key.cmp(key) {
+                    Ordering::Less => {}
+                    Ordering::Equal if eq(val, other_val) => continue 'outer,
+                    _ => return false
+                }
+            }
+            return false;
+        }
+        true
+    }
+
+    fn get_many_sorted<'b>(&'b self, keys: &'b [K]) -> GetManySorted<'b, K, V> {
+        debug_assert!(keys.windows(2).all(|pair| pair[0] <= pair[1]),
+                      "get_many_sorted requires its keys to be sorted");
+        GetManySorted { probes: keys.iter(), entries: self.iter().peekable() }
+    }
+
+    fn remove_many_sorted(&mut self, keys: &[K]) -> Vec<(K, V)> {
+        debug_assert!(keys.windows(2).all(|pair| pair[0] <= pair[1]),
+                      "remove_many_sorted requires its keys to be sorted");
+        let mut removed = Vec::new();
+        for key in keys {
+            if let Some(pair) = self.remove_entry(key) {
+                removed.push(pair);
+            }
+        }
+        removed
+    }
+
+    fn insert_max(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        if self.keys().next_back().is_some_and(|last| key <= *last) {
+            return Err((key, value));
+        }
+        self.insert(key, value);
+        Ok(())
+    }
+
+    fn insert_min(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        if self.keys().next().is_some_and(|first| key >= *first) {
+            return Err((key, value));
+        }
+        self.insert(key, value);
+        Ok(())
+    }
+
+    fn take_until_cumulative<A, M, S>(&mut self, target: A, mut measure: M, mut split: S)
+                                      -> (Vec<(K, V)>, Option<(&K, &V)>)
+        where A: Copy + Ord + Default + Sub<Output=A>,
+              M: FnMut(&K, &V) -> A,
+              S: FnMut(&K, V, A) -> (V, V)
+    {
+        let mut taken = Vec::new();
+        let mut wanted = target;
+        while wanted > A::default() {
+            let (key, val) = match self.pop_first() {
+                Some(pair) => pair,
+                None => break
+            };
+            let amount = measure(&key, &val);
+            if amount <= wanted {
+                wanted = wanted - amount;
+                taken.push((key, val));
+            } else {
+                let (take, leave) = split(&key, val, wanted);
+                self.insert(key.clone(), leave);
+                taken.push((key, take));
+                return (taken, self.first_key_value());
+            }
+        }
+        (taken, None)
+    }
+}
+
+/// Merges `sources`, each of which must yield its pairs in ascending key order, into a single map.
+/// The sources are merged through a heap in O(total · log sources) and the result is built in
+/// bulk from the merged, already-sorted sequence.
+///
+/// When the same key arrives more than once, `resolve` is called with the key, the value resolved
+/// so far and the newly arrived value. Pairs with equal keys always arrive in source order (and,
+/// within one source, in the order that source yields them), so the second argument to `resolve`
+/// always comes from a source at a lower or equal index than the third.
+///
+/// # Examples
+///
+/// ```
+/// use sorted_collections::sortedmap::merge_sorted_sources;
+///
+/// fn main() {
+///     let sources = vec![
+///         vec![(1u32, "a"), (3, "a")].into_iter(),
+///         vec![(2u32, "b"), (3, "b")].into_iter(),
+///     ];
+///     let map = merge_sorted_sources(sources, |_, first, _| first);
+///     assert_eq!(map.into_iter().collect::<Vec<(u32, &str)>>(), vec![(1u32, "a"), (2, "b"), (3, "a")]);
+/// }
+/// ```
+pub fn merge_sorted_sources<K, V, I, F>(mut sources: Vec<I>, mut resolve: F) -> BTreeMap<K, V>
+    where K: Ord, I: Iterator<Item = (K, V)>, F: FnMut(&K, V, V) -> V
+{
+    let mut heap = BinaryHeap::with_capacity(sources.len());
+    for (source, iter) in sources.iter_mut().enumerate() {
+        if let Some((key, val)) = iter.next() {
+            heap.push(MergeHead { key, val, source });
+        }
+    }
+    let mut merged: Vec<(K, V)> = Vec::new();
+    while let Some(MergeHead { key, val, source }) = heap.pop() {
+        if let Some((next_key, next_val)) = sources[source].next() {
+            heap.push(MergeHead { key: next_key, val: next_val, source });
+        }
+        if merged.last().is_some_and(|(last_key, _)| *last_key == key) {
+            let (_, resolved) = merged.pop().unwrap();
+            let val = resolve(&key, resolved, val);
+            merged.push((key, val));
+        } else {
+            merged.push((key, val));
+        }
+    }
+    merged.into_iter().collect()
+}
+
+// Consumes `map` and returns the part of it whose keys lie in [from_key, to_key), splitting the
+// tree rather than moving entries one at a time.
+fn into_range<K: Ord, V>(mut map: BTreeMap<K, V>, from_key: &K, to_key: &K) -> BTreeMap<K, V> {
+    let mut range = map.split_off(from_key);
+    range.split_off(to_key);
+    range
+}
+
+#[derive(Debug)]
+pub struct BTreeMapRangeIter<'a, K: 'a, V: 'a> {
+    iter: btree_map::Range<'a, K, V>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapRangeIter<'a, K, V> => (&'a K, &'a V));
+impl<'a, K, V> Clone for BTreeMapRangeIter<'a, K, V> {
+    fn clone(&self) -> BTreeMapRangeIter<'a, K, V> {
+        BTreeMapRangeIter { iter: self.iter.clone() }
+    }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapRangeIterMut<'a, K: 'a, V: 'a> {
+    iter: btree_map::RangeMut<'a, K, V>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapRangeIterMut<'a, K, V> => (&'a K, &'a mut V));
+
+#[derive(Debug)]
+pub struct BTreeMapRangeRemoveIter<K, V> {
+    iter: btree_map::IntoIter<K, V>
+}
+
+wrapper_iterator!(impl<K, V> BTreeMapRangeRemoveIter<K, V> => (K, V));
+impl<K, V> ExactSizeIterator for BTreeMapRangeRemoveIter<K, V> {
+    fn len(&self) -> usize { self.iter.len() }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapByValueIter<'a, K: 'a, V: 'a> {
+    iter: vec::IntoIter<(&'a K, &'a V)>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapByValueIter<'a, K, V> => (&'a K, &'a V));
+impl<'a, K, V> Clone for BTreeMapByValueIter<'a, K, V> {
+    fn clone(&self) -> BTreeMapByValueIter<'a, K, V> {
+        BTreeMapByValueIter { iter: self.iter.clone() }
+    }
+}
+impl<'a, K, V> ExactSizeIterator for BTreeMapByValueIter<'a, K, V> {
+    fn len(&self) -> usize { self.iter.len() }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapIntoKeysRangeIter<K, V> {
+    iter: btree_map::IntoIter<K, V>
+}
+
+wrapper_iterator!(impl<K, V> BTreeMapIntoKeysRangeIter<K, V> => K, |(k, _)| k);
+impl<K, V> ExactSizeIterator for BTreeMapIntoKeysRangeIter<K, V> {
+    fn len(&self) -> usize { self.iter.len() }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapIntoValuesRangeIter<K, V> {
+    iter: btree_map::IntoIter<K, V>
+}
+
+wrapper_iterator!(impl<K, V> BTreeMapIntoValuesRangeIter<K, V> => V, |(_, v)| v);
+impl<K, V> ExactSizeIterator for BTreeMapIntoValuesRangeIter<K, V> {
+    fn len(&self) -> usize { self.iter.len() }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapDescendingIter<'a, K: 'a, V: 'a> {
+    iter: iter::Rev<btree_map::Iter<'a, K, V>>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapDescendingIter<'a, K, V> => (&'a K, &'a V));
+impl<'a, K, V> Clone for BTreeMapDescendingIter<'a, K, V> {
+    fn clone(&self) -> BTreeMapDescendingIter<'a, K, V> {
+        BTreeMapDescendingIter { iter: self.iter.clone() }
+    }
+}
+impl<'a, K, V> ExactSizeIterator for BTreeMapDescendingIter<'a, K, V> {
+    fn len(&self) -> usize { self.iter.len() }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapDescendingIterMut<'a, K: 'a, V: 'a> {
+    iter: iter::Rev<btree_map::IterMut<'a, K, V>>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapDescendingIterMut<'a, K, V> => (&'a K, &'a mut V));
+impl<'a, K, V> ExactSizeIterator for BTreeMapDescendingIterMut<'a, K, V> {
+    fn len(&self) -> usize { self.iter.len() }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapRangeDescIter<'a, K: 'a, V: 'a> {
+    iter: iter::Rev<btree_map::Range<'a, K, V>>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapRangeDescIter<'a, K, V> => (&'a K, &'a V));
+impl<'a, K, V> Clone for BTreeMapRangeDescIter<'a, K, V> {
+    fn clone(&self) -> BTreeMapRangeDescIter<'a, K, V> {
+        BTreeMapRangeDescIter { iter: self.iter.clone() }
+    }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapRangeDescIterMut<'a, K: 'a, V: 'a> {
+    iter: iter::Rev<btree_map::RangeMut<'a, K, V>>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapRangeDescIterMut<'a, K, V> => (&'a K, &'a mut V));
+
+#[derive(Debug)]
+pub struct BTreeMapKeysRangeIter<'a, K: 'a, V: 'a> {
+    iter: btree_map::Range<'a, K, V>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapKeysRangeIter<'a, K, V> => &'a K, |(k, _)| k);
+impl<'a, K, V> Clone for BTreeMapKeysRangeIter<'a, K, V> {
+    fn clone(&self) -> BTreeMapKeysRangeIter<'a, K, V> {
+        BTreeMapKeysRangeIter { iter: self.iter.clone() }
+    }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapValuesRangeIter<'a, K: 'a, V: 'a> {
+    iter: btree_map::Range<'a, K, V>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapValuesRangeIter<'a, K, V> => &'a V, |(_, v)| v);
+impl<'a, K, V> Clone for BTreeMapValuesRangeIter<'a, K, V> {
+    fn clone(&self) -> BTreeMapValuesRangeIter<'a, K, V> {
+        BTreeMapValuesRangeIter { iter: self.iter.clone() }
+    }
+}
+
+#[derive(Debug)]
+pub struct BTreeMapValuesRangeIterMut<'a, K: 'a, V: 'a> {
+    iter: btree_map::RangeMut<'a, K, V>
+}
+
+wrapper_iterator!(impl<'a, K, V> BTreeMapValuesRangeIterMut<'a, K, V> => &'a mut V, |(_, v)| v);
+
+#[derive(Debug)]
+pub struct BTreeMapDrainSortedIter<K, V> {
+    iter: btree_map::IntoIter<K, V>
+}
+
+wrapper_iterator!(impl<K, V> BTreeMapDrainSortedIter<K, V> => (K, V));
+impl<K, V> ExactSizeIterator for BTreeMapDrainSortedIter<K, V> {
+    fn len(&self) -> usize { self.iter.len() }
+}
+
+/// An iterator yielding each key of a map along with a `RangeHandle` on the window of entries
+/// preceding it within a fixed distance. See `SortedMapExt::rolling_range_iter`.
+pub struct RollingRangeIter<'a, K: 'a + KeyDistance, V: 'a> {
+    // Positioned at the first entry of the current window, which holds `len` entries.
+    start: btree_map::Iter<'a, K, V>,
+    len: usize,
+    // Positioned just past the key most recently yielded.
+    ahead: btree_map::Iter<'a, K, V>,
+    width: K::Distance
+}
+
+impl<'a, K: KeyDistance, V> Iterator for RollingRangeIter<'a, K, V> {
+    type Item = (&'a K, RangeHandle<'a, K, V>);
+
+    fn next(&mut self) -> Option<(&'a K, RangeHandle<'a, K, V>)> {
+        let key = match self.ahead.next() {
+            Some((key, _)) => key,
+            None => return None,
+        };
+        while self.len > 0 && self.start.clone().next().unwrap().0.distance(key) > self.width {
+            self.start.next();
+            self.len -= 1;
+        }
+        let window = RangeHandle { iter: self.start.clone(), len: self.len };
+        self.len += 1;
+        Some((key, window))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { self.ahead.size_hint() }
+}
+impl<'a, K: KeyDistance, V> ExactSizeIterator for RollingRangeIter<'a, K, V> {
+    fn len(&self) -> usize { self.ahead.len() }
+}
+impl<'a, K: KeyDistance, V> iter::FusedIterator for RollingRangeIter<'a, K, V> {}
+
+/// A lightweight handle on a contiguous window of a map's entries, as yielded by
+/// `SortedMapExt::rolling_range_iter`.
+pub struct RangeHandle<'a, K: 'a, V: 'a> {
+    iter: btree_map::Iter<'a, K, V>,
+    len: usize
+}
+
+impl<'a, K, V> RangeHandle<'a, K, V> {
+    /// Returns an iterator over the key-value pairs in this window, in ascending key order.
+    pub fn iter(&self) -> iter::Take<btree_map::Iter<'a, K, V>> {
+        self.iter.clone().take(self.len)
+    }
+
+    /// Returns the number of key-value pairs in this window.
+    pub fn count(&self) -> usize {
+        self.len
+    }
+
+    /// Folds the key-value pairs in this window, in ascending key order, into an accumulator.
+    pub fn fold<B, F>(&self, init: B, mut f: F) -> B where F: FnMut(B, &'a K, &'a V) -> B {
+        self.iter().fold(init, |acc, (k, v)| f(acc, k, v))
+    }
+}
+
+impl<'a, K, V> Clone for RangeHandle<'a, K, V> {
+    fn clone(&self) -> RangeHandle<'a, K, V> {
+        RangeHandle { iter: self.iter.clone(), len: self.len }
+    }
+}
+
+/// A handle draining a range of a map's entries in batches. See
+/// `SortedMapExt::drain_range_in_batches`.
+///
+/// The map may be modified through `map_mut` between batches. Each batch resumes just past the
+/// greatest key drained so far (or at the start of the range, before the first batch), so a pair
+/// inserted there or later within the range is drained by a later batch, a pair inserted below
+/// it is not, and a pair removed before its turn is simply never drained.
+pub struct DrainBatches<'a, K: 'a, V: 'a> {
+    map: &'a mut BTreeMap<K, V>,
+    from: K,
+    to: K,
+    // The greatest key drained so far, if any.
+    last: Option<K>,
+    batch: usize
+}
+
+impl<'a, K: Clone + Ord, V> DrainBatches<'a, K, V> {
+    /// Removes the next batch of at most `batch` pairs from the map and returns them, in
+    /// ascending key order. Returns an empty vector once the range has been drained.
+    pub fn next_batch(&mut self) -> Vec<(K, V)> {
+        if self.from >= self.to {
+            return Vec::new();
+        }
+        let keys: Vec<K> = {
+            let range = match self.last {
+                Some(ref last) => self.map.range((Excluded(last), Excluded(&self.to))),
+                None => self.map.range((Included(&self.from), Excluded(&self.to))),
+            };
+            range.take(self.batch).map(|(k, _)| k.clone()).collect()
+        };
+        let drained: Vec<(K, V)> =
+            keys.iter().map(|key| self.map.remove_entry(key).unwrap()).collect();
+        if let Some((key, _)) = drained.last() {
+            self.last = Some(key.clone());
+        }
+        drained
+    }
+
+    /// Returns a reference to the map being drained.
+    pub fn map(&self) -> &BTreeMap<K, V> {
+        self.map
+    }
+
+    /// Returns a mutable reference to the map being drained.
+    pub fn map_mut(&mut self) -> &mut BTreeMap<K, V> {
+        self.map
+    }
+}
+
+/// An iterator over consecutive chunks of a range of a map's entries, each weighing at most a
+/// given limit. See `SortedMapExt::chunk_range_by_weight`.
+pub struct WeightChunks<'a, K: 'a, V: 'a, F> {
+    range: btree_map::Range<'a, K, V>,
+    max: u64,
+    weight: F,
+    // The weight of the entry at the front of `range`, if it has already been computed.
+    pending: Option<u64>
+}
+
+impl<'a, K, V, F> Iterator for WeightChunks<'a, K, V, F> where F: FnMut(&K, &V) -> u64 {
+    type Item = RangeChunk<'a, K, V>;
+
+    fn next(&mut self) -> Option<RangeChunk<'a, K, V>> {
+        let start = self.range.clone();
+        let mut chunk: Option<RangeChunk<K, V>> = None;
+        loop {
+            let mut ahead = self.range.clone();
+            let (key, val) = match ahead.next() {
+                Some(entry) => entry,
+                None => break,
+            };
+            let weight = match self.pending.take() {
+                Some(weight) => weight,
+                None => (self.weight)(key, val),
+            };
+            match chunk {
+                Some(ref mut chunk) => {
+                    if chunk.weight.saturating_add(weight) > self.max {
+                        self.pending = Some(weight);
+                        break;
+                    }
+                    chunk.weight += weight;
+                    chunk.len += 1;
+                    chunk.last = key;
+                }
+                None => {
+                    chunk = Some(RangeChunk { iter: start.clone(), len: 1, first: key, last: key,
+                                              weight });
+                }
+            }
+            self.range = ahead;
+        }
+        chunk
+    }
+}
+impl<'a, K, V, F> iter::FusedIterator for WeightChunks<'a, K, V, F> where F: FnMut(&K, &V) -> u64 {}
+
+/// A chunk of consecutive entries of a map, as yielded by `SortedMapExt::chunk_range_by_weight`.
+pub struct RangeChunk<'a, K: 'a, V: 'a> {
+    iter: btree_map::Range<'a, K, V>,
+    len: usize,
+    first: &'a K,
+    last: &'a K,
+    weight: u64
+}
+
+impl<'a, K, V> RangeChunk<'a, K, V> {
+    /// Returns an iterator over the key-value pairs in this chunk, in ascending key order.
+    pub fn iter(&self) -> iter::Take<btree_map::Range<'a, K, V>> {
+        self.iter.clone().take(self.len)
+    }
+
+    /// Returns the first (least) key in this chunk.
+    pub fn first_key(&self) -> &'a K {
+        self.first
+    }
+
+    /// Returns the last (greatest) key in this chunk.
+    pub fn last_key(&self) -> &'a K {
+        self.last
+    }
+
+    /// Returns the number of key-value pairs in this chunk.
+    pub fn count(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the total weight of the key-value pairs in this chunk.
+    pub fn weight(&self) -> u64 {
+        self.weight
+    }
+}
+
+/// An iterator over the run of key-value pairs at one end of a map whose keys share a projection.
+/// See `SortedMapExt::first_run_by` and `SortedMapExt::last_run_by`.
+pub struct RunIter<'a, K: 'a, V: 'a, G, F> {
+    iter: btree_map::Iter<'a, K, V>,
+    proj: F,
+    // The projection of the first key yielded, once there is one.
+    head: Option<G>,
+    rev: bool,
+    done: bool
+}
+
+impl<'a, K, V, G, F> Iterator for RunIter<'a, K, V, G, F> where G: PartialEq, F: FnMut(&K) -> G {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        if self.done {
+            return None;
+        }
+        let (key, val) = if self.rev { self.iter.next_back() } else { self.iter.next() }?;
+        let proj = (self.proj)(key);
+        match self.head {
+            Some(ref head) if *head != proj => {
+                self.done = true;
+                return None;
+            }
+            Some(_) => {}
+            None => self.head = Some(proj),
+        }
+        Some((key, val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done { (0, Some(0)) } else { (0, self.iter.size_hint().1) }
+    }
+}
+impl<'a, K, V, G, F> iter::FusedIterator for RunIter<'a, K, V, G, F>
+    where G: PartialEq, F: FnMut(&K) -> G {}
+
+/// An iterator over the keys present in both of two maps, along with their values in each. See
+/// `SortedMapExt::join_iter`.
+pub struct JoinIter<'a, K: 'a, V: 'a, V2: 'a> {
+    left: iter::Peekable<btree_map::Iter<'a, K, V>>,
+    right: iter::Peekable<btree_map::Iter<'a, K, V2>>
+}
+
+impl<'a, K: Ord, V, V2> Iterator for JoinIter<'a, K, V, V2> {
+    type Item = (&'a K, &'a V, &'a V2);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V, &'a V2)> {
+        loop {
+            let order = match (self.left.peek(), self.right.peek()) {
+                (Some(&(left, _)), Some(&(right, _))) => left.cmp(right),
+                _ => return None
+            };
+            match order {
+                Ordering::Less => { self.left.next(); }
+                Ordering::Greater => { self.right.next(); }
+                Ordering::Equal => {
+                    let (key, val) = self.left.next().unwrap();
+                    let (_, other_val) = self.right.next().unwrap();
+                    return Some((key, val, other_val));
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(cmp::min(self.left.len(), self.right.len())))
+    }
+}
+impl<'a, K: Ord, V, V2> iter::FusedIterator for JoinIter<'a, K, V, V2> {}
+
+/// An iterator over a sorted batch of keys, each paired with the value it maps to in a map, if
+/// any. See `SortedMapExt::get_many_sorted`.
+pub struct GetManySorted<'a, K: 'a, V: 'a> {
+    probes: slice::Iter<'a, K>,
+    entries: iter::Peekable<btree_map::Iter<'a, K, V>>
+}
+
+impl<'a, K: Ord, V> Iterator for GetManySorted<'a, K, V> {
+    type Item = (&'a K, Option<&'a V>);
+
+    fn next(&mut self) -> Option<(&'a K, Option<&'a V>)> {
+        let key = self.probes.next()?;
+        while self.entries.peek().is_some_and(|&(entry_key, _)| entry_key < key) {
+            self.entries.next();
+        }
+        let val = match self.entries.peek() {
+            Some(&(entry_key, val)) if entry_key == key => Some(val),
+            _ => None
+        };
+        Some((key, val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { self.probes.size_hint() }
+}
+impl<'a, K: Ord, V> ExactSizeIterator for GetManySorted<'a, K, V> {
+    fn len(&self) -> usize { self.probes.len() }
+}
+impl<'a, K: Ord, V> iter::FusedIterator for GetManySorted<'a, K, V> {}
+
+/// A key-value pair from one of two maps. See `SortedMapExt::symmetric_difference_keys`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EitherKey<'a, K: 'a, V: 'a, V2: 'a> {
+    /// A pair from the map `symmetric_difference_keys` was called on.
+    Left(&'a K, &'a V),
+    /// A pair from the other map.
+    Right(&'a K, &'a V2)
+}
+
+/// An iterator over the key-value pairs whose keys are in exactly one of two maps. See
+/// `SortedMapExt::symmetric_difference_keys`.
+pub struct SymmetricDifferenceKeys<'a, K: 'a, V: 'a, V2: 'a> {
+    left: iter::Peekable<btree_map::Iter<'a, K, V>>,
+    right: iter::Peekable<btree_map::Iter<'a, K, V2>>
+}
+
+impl<'a, K: Ord, V, V2> Iterator for SymmetricDifferenceKeys<'a, K, V, V2> {
+    type Item = EitherKey<'a, K, V, V2>;
+
+    fn next(&mut self) -> Option<EitherKey<'a, K, V, V2>> {
+        loop {
+            let order = match (self.left.peek(), self.right.peek()) {
+                (Some(&(left, _)), Some(&(right, _))) => left.cmp(right),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return None
+            };
+            match order {
+                Ordering::Less => {
+                    return self.left.next().map(|(key, val)| EitherKey::Left(key, val));
+                }
+                Ordering::Greater => {
+                    return self.right.next().map(|(key, val)| EitherKey::Right(key, val));
+                }
+                Ordering::Equal => {
+                    self.left.next();
+                    self.right.next();
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (left, right) = (self.left.len(), self.right.len());
+        (cmp::max(left, right) - cmp::min(left, right), Some(left + right))
+    }
+}
+impl<'a, K: Ord, V, V2> iter::FusedIterator for SymmetricDifferenceKeys<'a, K, V, V2> {}
+
+/// An iterator over the key-value pairs of one map, along with the values their keys map to in
+/// another, if any. See `SortedMapExt::left_join_iter`.
+pub struct LeftJoinIter<'a, K: 'a, V: 'a, V2: 'a> {
+    left: btree_map::Iter<'a, K, V>,
+    right: iter::Peekable<btree_map::Iter<'a, K, V2>>
+}
+
+impl<'a, K: Ord, V, V2> Iterator for LeftJoinIter<'a, K, V, V2> {
+    type Item = (&'a K, &'a V, Option<&'a V2>);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V, Option<&'a V2>)> {
+        let (key, val) = self.left.next()?;
+        while self.right.peek().is_some_and(|&(other_key, _)| other_key < key) {
+            self.right.next();
+        }
+        let other_val = match self.right.peek() {
+            Some(&(other_key, _)) if other_key == key => self.right.next().map(|(_, v)| v),
+            _ => None
+        };
+        Some((key, val, other_val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { self.left.size_hint() }
+}
+impl<'a, K: Ord, V, V2> ExactSizeIterator for LeftJoinIter<'a, K, V, V2> {
+    fn len(&self) -> usize { self.left.len() }
+}
+impl<'a, K: Ord, V, V2> iter::FusedIterator for LeftJoinIter<'a, K, V, V2> {}
+
+/// An iterator over the pairs of adjacent key-value pairs of a map. See
+/// `SortedMapExt::pairs_iter` and `SortedMapExt::pairs_range_iter`.
+pub struct PairsIter<'a, K: 'a, V: 'a> {
+    iter: BTreeMapRangeIter<'a, K, V>,
+    // The entries shared between the pairs already yielded from either end and those yet to be
+    // yielded, which are no longer in `iter`.
+    front: Option<(&'a K, &'a V)>,
+    back: Option<(&'a K, &'a V)>
+}
+
+impl<'a, K, V> Iterator for PairsIter<'a, K, V> {
+    type Item = ((&'a K, &'a V), (&'a K, &'a V));
+
+    fn next(&mut self) -> Option<((&'a K, &'a V), (&'a K, &'a V))> {
+        let first = self.front.take().or_else(|| self.iter.next())?;
+        let second = self.iter.next().or_else(|| self.back.take())?;
+        self.front = Some(second);
+        Some((first, second))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let held = self.front.iter().count() + self.back.iter().count();
+        let (lo, hi) = self.iter.size_hint();
+        ((lo + held).saturating_sub(1), hi.map(|hi| (hi + held).saturating_sub(1)))
+    }
+}
+impl<'a, K, V> DoubleEndedIterator for PairsIter<'a, K, V> {
+    fn next_back(&mut self) -> Option<((&'a K, &'a V), (&'a K, &'a V))> {
+        let second = self.back.take().or_else(|| self.iter.next_back())?;
+        let first = self.iter.next_back().or_else(|| self.front.take())?;
+        self.back = Some(first);
+        Some((first, second))
+    }
+}
+impl<'a, K, V> iter::FusedIterator for PairsIter<'a, K, V> {}
+
+/// An iterator over the runs of keys missing between the least and greatest keys of a map. See
+/// `SortedMapExt::gaps`.
+pub struct Gaps<'a, K: 'a, V: 'a> {
+    keys: btree_map::Keys<'a, K, V>,
+    prev: Option<&'a K>
+}
+
+impl<'a, K: Clone + KeyStep, V> Iterator for Gaps<'a, K, V> {
+    type Item = (K, K);
+
+    fn next(&mut self) -> Option<(K, K)> {
+        for key in self.keys.by_ref() {
+            if let Some(prev) = self.prev.replace(key) {
+                match prev.successor() {
+                    Some(ref from) if from < key => return Some((from.clone(), key.clone())),
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+}
+impl<'a, K: Clone + KeyStep, V> iter::FusedIterator for Gaps<'a, K, V> {}
+
+// Orders key-value pairs so that pairs with greater values, and then lesser keys, compare as
+// smaller. A max-heap of these therefore keeps the worst-ranked retained pair on top.
+struct ValueRank<'a, K: 'a, V: 'a> {
+    key: &'a K,
+    val: &'a V
+}
+
+impl<'a, K: Ord, V: Ord> Ord for ValueRank<'a, K, V> {
+    fn cmp(&self, other: &ValueRank<'a, K, V>) -> Ordering {
+        match other.val.cmp(self.val) {
+            Ordering::Equal => self.key.cmp(other.key),
+            ord => ord,
+        }
+    }
+}
+impl<'a, K: Ord, V: Ord> PartialOrd for ValueRank<'a, K, V> {
+    fn partial_cmp(&self, other: &ValueRank<'a, K, V>) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+impl<'a, K: Ord, V: Ord> PartialEq for ValueRank<'a, K, V> {
+    fn eq(&self, other: &ValueRank<'a, K, V>) -> bool { self.cmp(other) == Ordering::Equal }
+}
+impl<'a, K: Ord, V: Ord> Eq for ValueRank<'a, K, V> {}
+
+// The head of one source in `merge_sorted_sources`, ordered so that `BinaryHeap` pops the
+// smallest key first and, among equal keys, the one from the earliest source.
+struct MergeHead<K, V> {
+    key: K,
+    val: V,
+    source: usize
+}
+
+impl<K: Ord, V> Ord for MergeHead<K, V> {
+    fn cmp(&self, other: &MergeHead<K, V>) -> Ordering {
+        match other.key.cmp(&self.key) {
+            Ordering::Equal => other.source.cmp(&self.source),
+            ord => ord,
+        }
+    }
+}
+impl<K: Ord, V> PartialOrd for MergeHead<K, V> {
+    fn partial_cmp(&self, other: &MergeHead<K, V>) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+impl<K: Ord, V> PartialEq for MergeHead<K, V> {
+    fn eq(&self, other: &MergeHead<K, V>) -> bool { self.cmp(other) == Ordering::Equal }
+}
+impl<K: Ord, V> Eq for MergeHead<K, V> {}
+
+/// A page of key-value pairs, as returned by `SortedMapExt::page_after` and
+/// `SortedMapExt::page_before`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<'a, K: 'a, V: 'a> {
+    /// The key-value pairs on this page, in the order they were paged through.
+    pub entries: Vec<(&'a K, &'a V)>,
+    /// The cursor to pass to fetch the following page, or `None` if this is the final page.
+    /// A page of size zero is always final.
+    pub next_cursor: Option<K>
+}
+
+impl<'a, K: Clone, V> Page<'a, K, V> {
+    fn take<I>(mut iter: I, n: usize) -> Page<'a, K, V> where I: Iterator<Item = (&'a K, &'a V)> {
+        let entries: Vec<(&K, &V)> = iter.by_ref().take(n).collect();
+        let next_cursor = match (entries.last(), iter.next()) {
+            (Some(&(last, _)), Some(_)) => Some(last.clone()),
+            _ => None,
+        };
+        Page { entries, next_cursor }
+    }
+}
+
+/// The error returned by `SortedMapExt::invert_unique` when more than one key holds the same
+/// value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonUniqueValueError<V> {
+    /// A value held by more than one key.
+    pub value: V
+}
+
+impl<V: fmt::Debug> fmt::Display for NonUniqueValueError<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value {:?} is held by more than one key", self.value)
+    }
+}
+impl<V: fmt::Debug> Error for NonUniqueValueError<V> {}
+
+/// The error returned by `SortedMapExt::insert_unique` when the key is already present.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OccupiedError<'a, K, V: 'a> {
+    /// The rejected key.
+    pub key: K,
+    /// The rejected value.
+    pub value: V,
+    /// The value already associated with the key.
+    pub existing: &'a V
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug> fmt::Display for OccupiedError<'a, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "key {:?} is already present with value {:?}", self.key, self.existing)
+    }
+}
+impl<'a, K: fmt::Debug, V: fmt::Debug> Error for OccupiedError<'a, K, V> {}
+
+/// The error returned by `SortedMapExt::push_max` when the key is not strictly greater than
+/// every key in the map.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotGreaterError<K, V> {
+    /// The rejected key.
+    pub key: K,
+    /// The rejected value.
+    pub value: V
+}
+
+impl<K: fmt::Debug, V> fmt::Display for NotGreaterError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "key {:?} is not greater than every key in the map", self.key)
+    }
+}
+impl<K: fmt::Debug, V: fmt::Debug> Error for NotGreaterError<K, V> {}
+
+/// The error returned by `SortedMapExt::try_map_keys_monotonic` when the key transformation is
+/// not strictly increasing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotMonotonicError<K> {
+    /// The first transformed key not strictly greater than the one before it.
+    pub key: K
+}
+
+impl<K: fmt::Debug> fmt::Display for NotMonotonicError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "transformed key {:?} is not greater than the one before it", self.key)
+    }
+}
+impl<K: fmt::Debug> Error for NotMonotonicError<K> {}
+
+/// The error returned by `SortedMapExt::update_key` and `SortedMapExt::update_key_with` when the
+/// value cannot be moved. Each variant holds the rejected new key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UpdateKeyError<K> {
+    /// The old key is not present in the map.
+    Missing(K),
+    /// The new key is already present in the map.
+    Occupied(K)
+}
+
+impl<K: fmt::Debug> fmt::Display for UpdateKeyError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UpdateKeyError::Missing(ref key) => {
+                write!(f, "cannot move to key {:?}: the old key is not present", key)
+            }
+            UpdateKeyError::Occupied(ref key) => write!(f, "key {:?} is already present", key),
+        }
+    }
+}
+impl<K: fmt::Debug> Error for UpdateKeyError<K> {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::borrow::Borrow;
+    use std::cmp;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::iter::{self, FusedIterator};
+    use std::ops::Bound::{self, Included, Excluded, Unbounded};
+    use std::rc::Rc;
+    use std::slice;
+
+    use crate::{DebugKey, OverlapError};
+    use super::{merge_sorted_sources, NavigableMapCore, NonUniqueValueError, NotGreaterError,
+                NotMonotonicError, OccupiedError, SortedMapExt, SortedMapNav, SortedMapRemove,
+                UpdateKeyError};
+
+    #[test]
+    fn test_first() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.first().unwrap(), &1u32);
+    }
+
+    #[test]
+    fn test_first_remove() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.first_remove().unwrap(), (1u32, 1u32));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (3, 3), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_last() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.last().unwrap(), &5u32);
+    }
+
+    #[test]
+    fn test_last_remove() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.last_remove().unwrap(), (5u32, 5u32));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn test_ceiling() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.ceiling(&3).unwrap(), &3u32);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_ceiling_remove() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.ceiling_remove(&3).unwrap(), (3u32, 3u32));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_floor() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.floor(&3).unwrap(), &3u32);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_floor_remove() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.floor_remove(&3).unwrap(), (3u32, 3u32));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_higher() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.higher(&3).unwrap(), &4u32);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_higher_remove() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.higher_remove(&3).unwrap(), (4u32, 4u32));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (5, 5)]);
+    }
+
+    #[test]
+    fn test_lower() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.lower(&3).unwrap(), &2u32);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_lower_remove() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.lower_remove(&3).unwrap(), (2u32, 2u32));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (3, 3), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_range_iter() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.range_iter(&2, &4).map(|(&k, &v)| (k, v)).collect::<Vec<(u32, u32)>>(),
+            vec![(2u32, 2u32), (3, 3)]);
+    }
+
+    #[test]
+    fn test_range_iter_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        for (_, v) in map.range_iter_mut(&2, &4) {
+            *v += 1;
+        }
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+            vec![(1u32, 1u32), (2, 3), (3, 4), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_range_remove_iter() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.range_remove_iter(&2, &4).collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (3, 3)]);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+            vec![(1u32, 1u32), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.histogram(&[2, 4, 5]), vec![2, 1]);
+        assert_eq!(map.histogram(&[0, 1, 3, 10]), vec![0, 2, 3]);
+        assert_eq!(map.histogram(&[6, 7]), vec![0]);
+    }
+
+    #[test]
+    fn test_histogram_weighted() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.histogram_weighted(&[2, 4, 5], |&v| v as u64), vec![5u64, 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_histogram_unsorted_edges() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.histogram(&[3, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_histogram_single_edge() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.histogram(&[3]);
+    }
+
+    #[test]
+    fn test_max_gap() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (100, 2), (101, 3), (102, 4), (201, 5)].into_iter().collect();
+        assert_eq!(map.max_gap().unwrap(), (&1u32, &100u32));
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (200, 5)].into_iter().collect();
+        assert_eq!(map.max_gap().unwrap(), (&4u32, &200u32));
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32)].into_iter().collect();
+        assert_eq!(map.max_gap(), None);
+    }
+
+    #[test]
+    fn test_max_gap_in_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (100, 2), (101, 3), (102, 4), (201, 5)].into_iter().collect();
+        assert_eq!(map.max_gap_in_range(&100, &201).unwrap(), (&100u32, &101u32));
+        assert_eq!(map.max_gap_in_range(&100, &101), None);
+    }
+
+    #[test]
+    fn test_is_contiguous() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert!(map.is_contiguous());
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)].into_iter().collect();
+        assert!(!map.is_contiguous());
+        let map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert!(map.is_contiguous());
+    }
+
+    #[test]
+    fn test_is_contiguous_in_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)].into_iter().collect();
+        assert!(map.is_contiguous_in_range(&1, &3));
+        assert!(!map.is_contiguous_in_range(&1, &4));
+        assert!(!map.is_contiguous_in_range(&3, &6));
+        assert!(map.is_contiguous_in_range(&4, &6));
+        assert!(!map.is_contiguous_in_range(&4, &7));
+        assert!(map.is_contiguous_in_range(&3, &3));
+    }
+
+    #[test]
+    fn test_partition_point_key() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let keys: Vec<u32> = map.keys().cloned().collect();
+        for bound in 0u32..7 {
+            let expected = keys.iter().position(|&k| k >= bound).map(|i| &keys[i]);
+            assert_eq!(map.partition_point_key(|&k, _| k < bound), expected);
+        }
+    }
+
+    #[test]
+    fn test_sorted_by_value_iter() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 3u32), (2, 1), (3, 2), (4, 1), (5, 3)].into_iter().collect();
+        assert_eq!(map.sorted_by_value_iter().map(|(&k, &v)| (k, v)).collect::<Vec<(u32, u32)>>(),
+            vec![(2u32, 1u32), (4, 1), (3, 2), (1, 3), (5, 3)]);
+        assert_eq!(map.sorted_by_value_iter().len(), 5);
+    }
+
+    #[test]
+    fn test_sorted_by_value_iter_by() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 3u32), (2, 1), (3, 2), (4, 1), (5, 3)].into_iter().collect();
+        assert_eq!(map.sorted_by_value_iter_by(|a, b| b.cmp(a)).map(|(&k, &v)| (k, v))
+            .collect::<Vec<(u32, u32)>>(), vec![(1u32, 3u32), (5, 3), (3, 2), (2, 1), (4, 1)]);
+    }
+
+    #[test]
+    fn test_top_n_by_value() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 3u32), (2, 1), (3, 2), (4, 1), (5, 3)].into_iter().collect();
+        assert_eq!(map.top_n_by_value(3), vec![(&1u32, &3u32), (&5, &3), (&3, &2)]);
+        assert_eq!(map.top_n_by_value(4), vec![(&1u32, &3u32), (&5, &3), (&3, &2), (&2, &1)]);
+        assert_eq!(map.top_n_by_value(10).len(), 5);
+        assert!(map.top_n_by_value(0).is_empty());
+    }
+
+    #[test]
+    fn test_invert() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 2u32), (2, 1), (3, 2), (4, 1), (5, 3)].into_iter().collect();
+        assert_eq!(map.invert().into_iter().collect::<Vec<(u32, Vec<u32>)>>(),
+            vec![(1u32, vec![2u32, 4]), (2, vec![1, 3]), (3, vec![5])]);
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3)].into_iter().collect();
+        assert_eq!(map.invert().into_iter().collect::<Vec<(u32, Vec<u32>)>>(),
+            vec![(1u32, vec![1u32]), (2, vec![2]), (3, vec![3])]);
+        let map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert!(map.invert().is_empty());
+    }
+
+    #[test]
+    fn test_into_inverted() {
+        let map: BTreeMap<u32, String> = vec![(1u32, "b".to_string()), (2, "a".to_string()), (3, "b".to_string())]
+            .into_iter().collect();
+        assert_eq!(map.into_inverted().into_iter().collect::<Vec<(String, Vec<u32>)>>(),
+            vec![("a".to_string(), vec![2u32]), ("b".to_string(), vec![1, 3])]);
+        let map: BTreeMap<u32, String> = BTreeMap::new();
+        assert!(map.into_inverted().is_empty());
+    }
+
+    #[test]
+    fn test_invert_unique() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 10u32), (2, 30), (3, 20)].into_iter().collect();
+        assert_eq!(map.invert_unique().unwrap().into_iter().collect::<Vec<(u32, u32)>>(),
+            vec![(10u32, 1u32), (20, 3), (30, 2)]);
+        let map: BTreeMap<u32, u32> = vec![(1u32, 10u32), (2, 30), (3, 10)].into_iter().collect();
+        assert_eq!(map.invert_unique(), Err(NonUniqueValueError { value: 10u32 }));
+        let map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert!(map.invert_unique().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_group_range_by_value() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let groups = map.group_range_by_value(&2, &5, |_| 0u8);
+        assert_eq!(groups.into_iter().collect::<Vec<(u8, Vec<(&u32, &u32)>)>>(),
+            vec![(0u8, vec![(&2u32, &2u32), (&3, &3), (&4, &4)])]);
+        let groups = map.group_range_by_value(&2, &5, |&v| v);
+        assert_eq!(groups.into_iter().collect::<Vec<(u32, Vec<(&u32, &u32)>)>>(),
+            vec![(2u32, vec![(&2u32, &2u32)]), (3, vec![(&3, &3)]), (4, vec![(&4, &4)])]);
+        assert!(map.group_range_by_value(&3, &3, |&v| v).is_empty());
+        assert!(map.group_range_by_value(&4, &2, |&v| v).is_empty());
+    }
+
+    #[test]
+    fn test_group_range_by_value_cloned() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let groups = map.group_range_by_value_cloned(&1, &6, |&v| v % 2);
+        assert_eq!(groups.into_iter().collect::<Vec<(u32, Vec<(u32, u32)>)>>(),
+            vec![(0u32, vec![(2u32, 2u32), (4, 4)]), (1, vec![(1, 1), (3, 3), (5, 5)])]);
+        assert!(map.group_range_by_value_cloned(&4, &2, |&v| v).is_empty());
+    }
+
+    #[test]
+    fn test_insert_unique() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.insert_unique(3, 30), Err(OccupiedError { key: 3u32, value: 30u32, existing: &3u32 }));
+        assert_eq!(map.insert_unique(0, 0), Ok(()));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+            vec![(0u32, 0u32), (1, 1), (2, 2), (3, 3), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_push_max() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.push_max(5, 50), Err(NotGreaterError { key: 5u32, value: 50u32 }));
+        assert_eq!(map.push_max(3, 30), Err(NotGreaterError { key: 3u32, value: 30u32 }));
+        assert_eq!(map.push_max(6, 6), Ok(()));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+            vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5), (6, 6)]);
+        let mut map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(map.push_max(0, 0), Ok(()));
+    }
+
+    #[test]
+    fn test_first_remove_if() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut calls = 0;
+        assert_eq!(map.first_remove_if(|_, _| { calls += 1; false }), None);
+        assert_eq!(calls, 1);
+        assert_eq!(map.first_remove_if(|&k, &v| k == 1 && v == 1), Some((1u32, 1u32)));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (3, 3), (4, 4), (5, 5)]);
+        let mut map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(map.first_remove_if(|_, _| panic!("called on an empty map")), None);
+    }
+
+    #[test]
+    fn test_last_remove_if() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut calls = 0;
+        assert_eq!(map.last_remove_if(|_, _| { calls += 1; false }), None);
+        assert_eq!(calls, 1);
+        assert_eq!(map.last_remove_if(|&k, &v| k == 5 && v == 5), Some((5u32, 5u32)));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4)]);
+        let mut map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(map.last_remove_if(|_, _| panic!("called on an empty map")), None);
+    }
+
+    #[test]
+    fn test_keys_to_set() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.keys_to_set().into_iter().collect::<Vec<u32>>(), vec![1u32, 2, 3, 4, 5]);
+        let map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert!(map.keys_to_set().is_empty());
+    }
+
+    #[test]
+    fn test_retain_keys() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.retain_keys(&vec![0u32, 2, 4, 6, 7, 8].into_iter().collect());
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (4, 4)]);
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.retain_keys(&vec![6u32, 7, 8].into_iter().collect());
+        assert!(map.is_empty());
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.retain_keys(&(0u32..10).collect());
+        assert_eq!(map.len(), 5);
+        let mut map: BTreeMap<u32, u32> = (0u32..100).map(|k| (k, k)).collect();
+        map.retain_keys(&vec![10u32, 20, 200].into_iter().collect());
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(10u32, 10u32), (20, 20)]);
+    }
+
+    #[test]
+    fn test_remove_keys() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.remove_keys(&vec![0u32, 2, 4, 6, 7, 8].into_iter().collect()), 2);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (3, 3), (5, 5)]);
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.remove_keys(&vec![6u32, 7, 8].into_iter().collect()), 0);
+        assert_eq!(map.len(), 5);
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.remove_keys(&(0u32..10).collect()), 5);
+        assert!(map.is_empty());
+        let mut map: BTreeMap<u32, u32> = (0u32..100).map(|k| (k, k)).collect();
+        assert_eq!(map.remove_keys(&vec![10u32, 20, 200].into_iter().collect()), 2);
+        assert_eq!(map.len(), 98);
+        assert!(!map.contains_key(&10) && !map.contains_key(&20));
+    }
+
+    #[test]
+    fn test_into_keys_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut iter = map.clone().into_keys_range(&2, &5);
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_back(), Some(4u32));
+        assert_eq!(iter.collect::<Vec<u32>>(), vec![2u32, 3]);
+        assert_eq!(map.clone().into_keys_range(&0, &10).collect::<Vec<u32>>(), vec![1u32, 2, 3, 4, 5]);
+        assert_eq!(map.clone().into_keys_range(&3, &3).len(), 0);
+        assert_eq!(map.into_keys_range(&4, &2).len(), 0);
+    }
+
+    #[test]
+    fn test_into_values_range() {
+        let map: BTreeMap<u32, String> = (1u32..6).map(|k| (k, k.to_string())).collect();
+        let mut iter = map.clone().into_values_range(&2, &5);
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_back(), Some("4".to_string()));
+        assert_eq!(iter.collect::<Vec<String>>(), vec!["2".to_string(), "3".to_string()]);
+        assert_eq!(map.clone().into_values_range(&0, &10).count(), 5);
+        assert_eq!(map.into_values_range(&3, &3).len(), 0);
+    }
+
+    #[test]
+    fn test_ceiling_owned() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.ceiling_owned(3), Some((3u32, &3u32)));
+        assert_eq!(map.ceiling_owned(0), Some((1u32, &1u32)));
+        assert_eq!(map.ceiling_owned(6), None);
+    }
+
+    #[test]
+    fn test_remove_ceiling() {
+        let mut map: BTreeMap<Rc<u32>, u32> = (1u32..6).map(|k| (Rc::new(k), k)).collect();
+        assert_eq!(map.remove_ceiling(Rc::new(3)), Ok((Rc::new(3u32), 3u32)));
+        assert_eq!(map.remove_ceiling(Rc::new(3)), Ok((Rc::new(4u32), 4u32)));
+        let probe = Rc::new(6u32);
+        let witness = probe.clone();
+        let returned = map.remove_ceiling(probe).unwrap_err();
+        assert!(Rc::ptr_eq(&returned, &witness));
+        assert_eq!(Rc::strong_count(&witness), 2);
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn test_remove_floor() {
+        let mut map: BTreeMap<Rc<u32>, u32> = (1u32..6).map(|k| (Rc::new(k), k)).collect();
+        assert_eq!(map.remove_floor(Rc::new(3)), Ok((Rc::new(3u32), 3u32)));
+        assert_eq!(map.remove_floor(Rc::new(3)), Ok((Rc::new(2u32), 2u32)));
+        let probe = Rc::new(0u32);
+        let witness = probe.clone();
+        let returned = map.remove_floor(probe).unwrap_err();
+        assert!(Rc::ptr_eq(&returned, &witness));
+        assert_eq!(Rc::strong_count(&witness), 2);
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn test_range_position() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.range_position(&2, &4), (1, 3));
+        assert_eq!(map.range_position(&0, &10), (0, 5));
+        assert_eq!(map.range_position(&6, &10), (5, 5));
+        assert_eq!(map.range_position(&3, &3), (2, 2));
+        assert_eq!(map.range_position(&4, &2), (3, 3));
+        for from in 0u32..7 {
+            for to in from..7 {
+                let (start, end) = map.range_position(&from, &to);
+                assert_eq!(start, map.keys().filter(|&&k| k < from).count());
+                assert_eq!(end - start, map.range_iter(&from, &to).count());
+            }
+        }
+    }
+
+    #[test]
+    fn test_append_assert_disjoint() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2)].into_iter().collect();
+        let mut other: BTreeMap<u32, u32> = vec![(3u32, 3u32), (4, 4)].into_iter().collect();
+        assert_eq!(map.append_assert_disjoint(&mut other), Ok(()));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4)]);
+        assert!(other.is_empty());
+
+        let mut map: BTreeMap<u32, u32> = vec![(3u32, 3u32), (4, 4)].into_iter().collect();
+        let mut other: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2)].into_iter().collect();
+        assert_eq!(map.append_assert_disjoint(&mut other), Ok(()));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4)]);
+
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2)].into_iter().collect();
+        let mut other: BTreeMap<u32, u32> = vec![(2u32, 20u32), (3, 3)].into_iter().collect();
+        assert_eq!(map.append_assert_disjoint(&mut other), Err(OverlapError { key: 2u32 }));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2)]);
+        assert_eq!(other.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 20u32), (3, 3)]);
+
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (5, 5)].into_iter().collect();
+        let mut other: BTreeMap<u32, u32> = vec![(3u32, 3u32)].into_iter().collect();
+        assert_eq!(map.append_assert_disjoint(&mut other), Err(OverlapError { key: 3u32 }));
+        assert_eq!(map.len(), 2);
+        assert_eq!(other.len(), 1);
+
+        let mut map: BTreeMap<u32, u32> = BTreeMap::new();
+        let mut other: BTreeMap<u32, u32> = vec![(1u32, 1u32)].into_iter().collect();
+        assert_eq!(map.append_assert_disjoint(&mut other), Ok(()));
+        assert_eq!(map.len(), 1);
+        let mut other: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(map.append_assert_disjoint(&mut other), Ok(()));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_rolling_range_iter() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (4, 4), (7, 7), (8, 8), (20, 20)].into_iter().collect();
+        let windows: Vec<(u32, Vec<u32>)> = map.rolling_range_iter(3)
+            .map(|(&k, window)| (k, window.iter().map(|(&j, _)| j).collect()))
+            .collect();
+        assert_eq!(windows, vec![(1u32, vec![]), (2, vec![1u32]), (4, vec![1, 2]), (7, vec![4]),
+            (8, vec![7]), (20, vec![])]);
+        let counts: Vec<usize> = map.rolling_range_iter(100).map(|(_, window)| window.count()).collect();
+        assert_eq!(counts, vec![0, 1, 2, 3, 4, 5]);
+        let counts: Vec<usize> = map.rolling_range_iter(0).map(|(_, window)| window.count()).collect();
+        assert_eq!(counts, vec![0, 0, 0, 0, 0, 0]);
+        assert_eq!(map.rolling_range_iter(3).len(), 6);
+        let map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(map.rolling_range_iter(3).count(), 0);
+    }
+
+    #[test]
+    fn test_first_n_in_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.first_n_in_range(&2, &5, 2).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![2u32, 3]);
+        assert_eq!(map.first_n_in_range(&2, &5, 10).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![2u32, 3, 4]);
+        assert_eq!(map.first_n_in_range(&2, &5, 3).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![2u32, 3, 4]);
+        assert_eq!(map.first_n_in_range(&2, &5, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_last_n_in_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.last_n_in_range(&2, &5, 2).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![3u32, 4]);
+        assert_eq!(map.last_n_in_range(&2, &5, 10).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![2u32, 3, 4]);
+        assert_eq!(map.last_n_in_range(&2, &5, 3).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![2u32, 3, 4]);
+        assert_eq!(map.last_n_in_range(&2, &5, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_page_after() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let page = map.page_after(None, 2);
+        assert_eq!(page.entries, vec![(&1u32, &1u32), (&2, &2)]);
+        assert_eq!(page.next_cursor, Some(2u32));
+        let cursor = page.next_cursor.unwrap();
+        map.remove(&cursor);
+        let page = map.page_after(Some(&cursor), 2);
+        assert_eq!(page.entries, vec![(&3u32, &3u32), (&4, &4)]);
+        assert_eq!(page.next_cursor, Some(4u32));
+        let page = map.page_after(Some(&4), 2);
+        assert_eq!(page.entries, vec![(&5u32, &5u32)]);
+        assert_eq!(page.next_cursor, None);
+        let page = map.page_after(Some(&3), 2);
+        assert_eq!(page.entries, vec![(&4u32, &4u32), (&5, &5)]);
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(map.page_after(Some(&5), 2).entries, vec![]);
+        assert_eq!(map.page_after(None, 0).next_cursor, None);
+    }
+
+    #[test]
+    fn test_page_before() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let page = map.page_before(None, 2);
+        assert_eq!(page.entries, vec![(&5u32, &5u32), (&4, &4)]);
+        assert_eq!(page.next_cursor, Some(4u32));
+        let cursor = page.next_cursor.unwrap();
+        map.remove(&cursor);
+        let page = map.page_before(Some(&cursor), 2);
+        assert_eq!(page.entries, vec![(&3u32, &3u32), (&2, &2)]);
+        assert_eq!(page.next_cursor, Some(2u32));
+        let page = map.page_before(Some(&2), 2);
+        assert_eq!(page.entries, vec![(&1u32, &1u32)]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_merge_sorted_sources() {
+        let sources = vec![
+            vec![(1u32, vec!["a"]), (4, vec!["a"]), (6, vec!["a"])].into_iter(),
+            vec![(2u32, vec!["b"]), (4, vec!["b"])].into_iter(),
+            vec![(3u32, vec!["c"]), (4, vec!["c"]), (6, vec!["c"])].into_iter(),
+        ];
+        let map = merge_sorted_sources(sources, |_, mut first, second| { first.extend(second); first });
+        assert_eq!(map.into_iter().collect::<Vec<(u32, Vec<&str>)>>(),
+                   vec![(1u32, vec!["a"]), (2, vec!["b"]), (3, vec!["c"]), (4, vec!["a", "b", "c"]),
+                        (6, vec!["a", "c"])]);
+    }
+
+    #[test]
+    fn test_merge_sorted_sources_empty_source() {
+        let sources = vec![
+            vec![].into_iter(),
+            vec![(1u32, 1u32), (2, 2)].into_iter(),
+            vec![].into_iter(),
+        ];
+        let map = merge_sorted_sources(sources, |_, _, _| panic!());
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2)]);
+        let map = merge_sorted_sources(Vec::<::std::vec::IntoIter<(u32, u32)>>::new(), |_, _, _| panic!());
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_merge_sorted_sources_same_key() {
+        let sources = (0..5u32).map(|i| vec![(7u32, i)].into_iter()).collect();
+        let mut order = Vec::new();
+        let map = merge_sorted_sources(sources, |&key, first, second| {
+            assert_eq!(key, 7);
+            order.push((first, second));
+            first * 10 + second
+        });
+        assert_eq!(order, vec![(0u32, 1u32), (1, 2), (12, 3), (123, 4)]);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(7u32, 1234u32)]);
+    }
+
+    #[cfg(feature = "debug-validate")]
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct FlakyKey(u32);
+
+    #[cfg(feature = "debug-validate")]
+    thread_local!(static FLAKY_REVERSED: ::std::cell::Cell<bool> =
+        const { ::std::cell::Cell::new(false) });
+
+    // An `Ord` implementation which can be reversed after the keys have been inserted.
+    #[cfg(feature = "debug-validate")]
+    impl Ord for FlakyKey {
+        fn cmp(&self, other: &FlakyKey) -> ::std::cmp::Ordering {
+            if FLAKY_REVERSED.with(|r| r.get()) {
+                other.0.cmp(&self.0)
             } else {
-                None
+                self.0.cmp(&other.0)
+            }
+        }
+    }
+    #[cfg(feature = "debug-validate")]
+    impl PartialOrd for FlakyKey {
+        fn partial_cmp(&self, other: &FlakyKey) -> Option<::std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    #[cfg(feature = "debug-validate")]
+    #[test]
+    #[should_panic(expected = "inconsistent Ord implementation: ceiling(FlakyKey(3)) found")]
+    fn test_debug_validate_inconsistent_ord() {
+        let map: BTreeMap<FlakyKey, u32> =
+            vec![(FlakyKey(1), 1u32), (FlakyKey(2), 2), (FlakyKey(4), 4), (FlakyKey(5), 5)]
+                .into_iter().collect();
+        assert_eq!(map.ceiling(&FlakyKey(3)), Some(&FlakyKey(4)));
+        FLAKY_REVERSED.with(|r| r.set(true));
+        map.ceiling(&FlakyKey(3));
+    }
+
+    #[cfg(feature = "debug-validate")]
+    #[test]
+    #[should_panic(expected = "inconsistent Ord implementation: floor_mut(FlakyKey(3)) found")]
+    fn test_debug_validate_inconsistent_ord_mut() {
+        let mut map: BTreeMap<FlakyKey, u32> =
+            vec![(FlakyKey(1), 1u32), (FlakyKey(2), 2), (FlakyKey(4), 4), (FlakyKey(5), 5)]
+                .into_iter().collect();
+        assert_eq!(map.floor_mut(&FlakyKey(3)).map(|(k, _)| k.clone()), Some(FlakyKey(2)));
+        FLAKY_REVERSED.with(|r| r.set(true));
+        map.floor_mut(&FlakyKey(3));
+    }
+
+    #[test]
+    fn test_binary_partition() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let (all, none) = map.clone().binary_partition(|_, _| true);
+        assert_eq!(all, map);
+        assert!(none.is_empty());
+        let (none, all) = map.clone().binary_partition(|_, _| false);
+        assert!(none.is_empty());
+        assert_eq!(all, map);
+        let (odd, even) = map.binary_partition(|&k, _| k % 2 == 1);
+        assert_eq!(odd.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (3, 3), (5, 5)]);
+        assert_eq!(even.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (4, 4)]);
+    }
+
+    #[test]
+    fn test_binary_partition_moves_values() {
+        let witness = Rc::new(());
+        let map: BTreeMap<u32, Rc<()>> = (1u32..7).map(|k| (k, witness.clone())).collect();
+        assert_eq!(Rc::strong_count(&witness), 7);
+        let (odd, even) = map.binary_partition(|&k, _| k % 2 == 1);
+        assert_eq!(Rc::strong_count(&witness), 7);
+        assert_eq!((odd.len(), even.len()), (3, 3));
+        drop(odd);
+        assert_eq!(Rc::strong_count(&witness), 4);
+        drop(even);
+        assert_eq!(Rc::strong_count(&witness), 1);
+    }
+
+    #[test]
+    fn test_partition_keys() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let (odd, even) = map.partition_keys(|&k, _| k % 2 == 1);
+        assert_eq!(odd.into_iter().collect::<Vec<u32>>(), vec![1u32, 3, 5]);
+        assert_eq!(even.into_iter().collect::<Vec<u32>>(), vec![2u32, 4]);
+        let (all, none) = map.partition_keys(|_, _| true);
+        assert_eq!(all.len(), 5);
+        assert!(none.is_empty());
+        assert_eq!(map.len(), 5);
+    }
+
+    #[test]
+    fn test_ensure_keys() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.ensure_keys(vec![4u32, 2, 4, 1], |_| panic!()), 0);
+        assert_eq!(map.len(), 5);
+        let mut seen = Vec::new();
+        assert_eq!(map.ensure_keys(vec![8u32, 0, 6, 8], |&k| { seen.push(k); k * 10 }), 3);
+        assert_eq!(seen, vec![0u32, 6, 8]);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(0u32, 0u32), (1, 1), (2, 2), (3, 3), (4, 4), (5, 5), (6, 60), (8, 80)]);
+    }
+
+    #[test]
+    fn test_ensure_keys_interleaved() {
+        let mut map: BTreeMap<u32, u32> = (0u32..50).map(|k| (k * 2, k * 2)).collect();
+        assert_eq!(map.ensure_keys(0u32..100, |&k| k + 1000), 50);
+        assert_eq!(map.len(), 100);
+        assert!(map.iter().all(|(&k, &v)| if k % 2 == 0 { v == k } else { v == k + 1000 }));
+        let mut empty: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(empty.ensure_keys(vec![3u32, 1, 2], |&k| k), 3);
+        assert_eq!(empty.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 1u32), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn test_ensure_keys_stride() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.ensure_keys_stride(&1, &6, 2, |_| panic!()), 0);
+        assert_eq!(map.ensure_keys_stride(&0, &20, 4, |&k| k * 10), 4);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(0u32, 0u32), (1, 1), (2, 2), (3, 3), (4, 4), (5, 5), (8, 80), (12, 120),
+                        (16, 160)]);
+        let mut map: BTreeMap<u8, u8> = BTreeMap::new();
+        assert_eq!(map.ensure_keys_stride(&250, &255, 3, |&k| k), 2);
+        assert_eq!(map.ensure_keys_stride(&5, &5, 1, |&k| k), 0);
+        assert_eq!(map.into_iter().collect::<Vec<(u8, u8)>>(), vec![(250u8, 250u8), (253, 253)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ensure_keys_stride_zero_step() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.ensure_keys_stride(&0, &10, 0, |_| 0);
+    }
+
+    #[test]
+    fn test_map_keys_monotonic() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let map = map.map_keys_monotonic(|k| format!("ns/{}", k));
+        assert_eq!(map.into_iter().collect::<Vec<(String, u32)>>(),
+                   vec![("ns/1".to_string(), 1u32), ("ns/2".to_string(), 2),
+                        ("ns/3".to_string(), 3), ("ns/4".to_string(), 4),
+                        ("ns/5".to_string(), 5)]);
+        let witness = Rc::new(());
+        let map: BTreeMap<u32, Rc<()>> = (1u32..6).map(|k| (k, witness.clone())).collect();
+        let map = map.map_keys_monotonic(|k| k as u64 * 1000);
+        assert_eq!(map.keys().cloned().collect::<Vec<u64>>(),
+                   vec![1000u64, 2000, 3000, 4000, 5000]);
+        assert_eq!(Rc::strong_count(&witness), 6);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn test_map_keys_monotonic_not_increasing() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.map_keys_monotonic(|k| 10 - k);
+    }
+
+    #[test]
+    fn test_try_map_keys_monotonic() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.clone().try_map_keys_monotonic(|k| k / 2),
+                   Err(NotMonotonicError { key: 1u32 }));
+        assert_eq!(map.clone().try_map_keys_monotonic(|k| if k == 4 { 0 } else { k }),
+                   Err(NotMonotonicError { key: 0u32 }));
+        let map = map.try_map_keys_monotonic(|k| k as i64 - 3).unwrap();
+        assert_eq!(map.into_iter().collect::<Vec<(i64, u32)>>(),
+                   vec![(-2i64, 1u32), (-1, 2), (0, 3), (1, 4), (2, 5)]);
+        let witness = Rc::new(());
+        let map: BTreeMap<u32, Rc<()>> = (1u32..6).map(|k| (k, witness.clone())).collect();
+        let map = map.try_map_keys_monotonic(|k| k + 1).unwrap();
+        assert_eq!(map.len(), 5);
+        assert_eq!(Rc::strong_count(&witness), 6);
+    }
+
+    #[test]
+    fn test_map_values() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut seen = Vec::new();
+        let mapped = map.clone().map_values(|&k, v| { seen.push(k); (v * 10) as u64 });
+        assert_eq!(seen, vec![1u32, 2, 3, 4, 5]);
+        assert!(mapped.keys().eq(map.keys()));
+        assert_eq!(mapped.values().cloned().collect::<Vec<u64>>(), vec![10u64, 20, 30, 40, 50]);
+        let witness = Rc::new(());
+        let map: BTreeMap<u32, Rc<()>> = (1u32..6).map(|k| (k, witness.clone())).collect();
+        let map = map.map_values(|_, v| Some(v));
+        assert_eq!(Rc::strong_count(&witness), 6);
+        assert!(map.values().all(|v| Rc::ptr_eq(v.as_ref().unwrap(), &witness)));
+    }
+
+    #[test]
+    fn test_map_values_ref() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mapped = map.map_values_ref(|&k, &v| k + v);
+        assert!(mapped.keys().eq(map.keys()));
+        assert_eq!(mapped.values().cloned().collect::<Vec<u32>>(), vec![2u32, 4, 6, 8, 10]);
+        assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_map_values_in_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut seen = Vec::new();
+        let mapped = map.clone().map_values_in_range(&2, &4, |&k, v| { seen.push(k); v * 10 });
+        assert_eq!(seen, vec![2u32, 3]);
+        assert_eq!(mapped.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 1u32), (2, 20), (3, 30), (4, 4), (5, 5)]);
+        let mapped = map.clone().map_values_in_range(&0, &10, |_, v| v + 1);
+        assert_eq!(mapped.values().cloned().collect::<Vec<u32>>(), vec![2u32, 3, 4, 5, 6]);
+        assert_eq!(map.clone().map_values_in_range(&4, &2, |_, _| panic!()), map);
+        assert_eq!(map.clone().map_values_in_range(&6, &9, |_, _| panic!()), map);
+        let witness = Rc::new(());
+        let map: BTreeMap<u32, Rc<()>> = (1u32..6).map(|k| (k, witness.clone())).collect();
+        let map = map.map_values_in_range(&2, &4, |_, v| v);
+        assert_eq!(map.len(), 5);
+        assert_eq!(Rc::strong_count(&witness), 6);
+    }
+
+    #[test]
+    fn test_collect_range_truncated() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.collect_range_truncated(&2, &5, 3),
+                   (vec![(2u32, 2u32), (3, 3), (4, 4)], false));
+        assert_eq!(map.collect_range_truncated(&2, &5, 2), (vec![(2u32, 2u32), (3, 3)], true));
+        assert_eq!(map.collect_range_truncated(&2, &5, 10),
+                   (vec![(2u32, 2u32), (3, 3), (4, 4)], false));
+        assert_eq!(map.collect_range_truncated(&2, &5, 0), (vec![], true));
+        assert_eq!(map.collect_range_truncated(&6, &9, 0), (vec![], false));
+        assert_eq!(map.collect_range_truncated(&5, &2, 3), (vec![], false));
+        assert_eq!(map.collect_range_truncated(&3, &3, 3), (vec![], false));
+    }
+
+    #[test]
+    fn test_first_run_by() {
+        let map: BTreeMap<(u32, u32), u32> =
+            vec![((1u32, 1u32), 1u32), ((1, 2), 2), ((2, 1), 3), ((3, 1), 4), ((3, 2), 5)]
+            .into_iter().collect();
+        let run: Vec<(&(u32, u32), &u32)> = map.first_run_by(|&(shard, _)| shard).collect();
+        assert_eq!(run, vec![(&(1u32, 1u32), &1u32), (&(1, 2), &2)]);
+        let run: Vec<(&(u32, u32), &u32)> = map.first_run_by(|&k| k).collect();
+        assert_eq!(run, vec![(&(1u32, 1u32), &1u32)]);
+        assert_eq!(map.first_run_by(|_| ()).count(), 5);
+        let empty: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(empty.first_run_by(|&k| k).next(), None);
+    }
+
+    #[test]
+    fn test_last_run_by() {
+        let map: BTreeMap<(u32, u32), u32> =
+            vec![((1u32, 1u32), 1u32), ((1, 2), 2), ((2, 1), 3), ((3, 1), 4), ((3, 2), 5)]
+            .into_iter().collect();
+        let run: Vec<(&(u32, u32), &u32)> = map.last_run_by(|&(shard, _)| shard).collect();
+        assert_eq!(run, vec![(&(3u32, 2u32), &5u32), (&(3, 1), &4)]);
+        let run: Vec<(&(u32, u32), &u32)> = map.last_run_by(|&k| k).collect();
+        assert_eq!(run, vec![(&(3u32, 2u32), &5u32)]);
+        let empty: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(empty.last_run_by(|&k| k).next(), None);
+    }
+
+    #[test]
+    fn test_first_run_remove_by() {
+        let mut map: BTreeMap<(u32, u32), u32> =
+            vec![((1u32, 1u32), 1u32), ((1, 2), 2), ((2, 1), 3), ((3, 1), 4), ((3, 2), 5)]
+            .into_iter().collect();
+        let run: Vec<((u32, u32), u32)> = map.first_run_remove_by(|&(shard, _)| shard).collect();
+        assert_eq!(run, vec![((1u32, 1u32), 1u32), ((1, 2), 2)]);
+        let run: Vec<((u32, u32), u32)> = map.first_run_remove_by(|&(shard, _)| shard).collect();
+        assert_eq!(run, vec![((2u32, 1u32), 3u32)]);
+        assert_eq!(map.first_run_remove_by(|_| ()).count(), 2);
+        assert!(map.is_empty());
+        assert_eq!(map.first_run_remove_by(|&k| k).next(), None);
+    }
+
+    #[test]
+    fn test_contains_each_sorted() {
+        let map: BTreeMap<u32, u32> = (0u32..50).map(|k| (k * 2 + 10, k)).collect();
+        assert_eq!(map.contains_each_sorted(&[0, 1, 2]), vec![false, false, false]);
+        assert_eq!(map.contains_each_sorted(&[200, 201]), vec![false, false]);
+        assert_eq!(map.contains_each_sorted(&[]), vec![]);
+        let probes: Vec<u32> = (0u32..120).collect();
+        let expected: Vec<bool> = probes.iter().map(|p| map.contains_key(p)).collect();
+        assert_eq!(map.contains_each_sorted(&probes), expected);
+        assert_eq!(map.contains_each_sorted(&[12, 12, 13, 13]), vec![true, true, false, false]);
+    }
+
+    #[test]
+    fn test_contains_each_sorted_into() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut out = [true; 5];
+        map.contains_each_sorted_into(&[0, 1, 3, 3, 9], &mut out);
+        assert_eq!(out, [false, true, true, true, false]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_contains_each_sorted_into_wrong_length() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.contains_each_sorted_into(&[1, 2], &mut [false; 3]);
+    }
+
+    #[test]
+    fn test_chunk_range_by_weight() {
+        let map: BTreeMap<u32, u32> = (1u32..9).map(|k| (k, k)).collect();
+        let mut calls = 0;
+        let chunks: Vec<(u32, u32, usize, u64)> = map.chunk_range_by_weight(&1, &9, 3, |_, _| {
+            calls += 1;
+            1
+        }).map(|c| (*c.first_key(), *c.last_key(), c.count(), c.weight())).collect();
+        assert_eq!(chunks, vec![(1u32, 3u32, 3, 3u64), (4, 6, 3, 3), (7, 8, 2, 2)]);
+        assert_eq!(calls, 8);
+        let chunks: Vec<Vec<u32>> = map.chunk_range_by_weight(&2, &6, 3, |_, _| 1)
+            .map(|c| c.iter().map(|(&k, _)| k).collect()).collect();
+        assert_eq!(chunks, vec![vec![2u32, 3, 4], vec![5]]);
+    }
+
+    #[test]
+    fn test_chunk_range_by_weight_oversized() {
+        let map: BTreeMap<u32, u64> =
+            vec![(1u32, 1u64), (2, 1), (3, 10), (4, 1), (5, 1)].into_iter().collect();
+        let chunks: Vec<Vec<u32>> = map.chunk_range_by_weight(&0, &10, 3, |_, &v| v)
+            .map(|c| c.iter().map(|(&k, _)| k).collect()).collect();
+        assert_eq!(chunks, vec![vec![1u32, 2], vec![3], vec![4, 5]]);
+        let chunks: Vec<usize> = map.chunk_range_by_weight(&0, &10, 0, |_, &v| v)
+            .map(|c| c.count()).collect();
+        assert_eq!(chunks, vec![1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_chunk_range_by_weight_empty() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.chunk_range_by_weight(&6, &9, 3, |_, _| 1).count(), 0);
+        assert_eq!(map.chunk_range_by_weight(&3, &3, 3, |_, _| 1).count(), 0);
+        assert_eq!(map.chunk_range_by_weight(&4, &2, 3, |_, _| 1).count(), 0);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample_range() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let map: BTreeMap<u32, u32> = (0u32..20).map(|k| (k, k)).collect();
+        let mut rng = StdRng::seed_from_u64(42);
+        let window: Vec<(&u32, &u32)> = map.range_iter(&5, &10).collect();
+        assert_eq!(map.sample_range(&5, &10, 5, &mut rng), window);
+        assert_eq!(map.sample_range(&5, &10, 100, &mut rng), window);
+        assert_eq!(map.sample_range(&5, &10, 0, &mut rng), vec![]);
+        assert_eq!(map.sample_range(&10, &5, 3, &mut rng), vec![]);
+        assert_eq!(map.sample_range(&30, &40, 3, &mut rng), vec![]);
+
+        let mut hits = [0u32; 10];
+        for _ in 0..10000 {
+            let sample = map.sample_range(&5, &15, 3, &mut rng);
+            assert_eq!(sample.len(), 3);
+            assert!(sample.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            for (&k, _) in sample {
+                hits[(k - 5) as usize] += 1;
+            }
+        }
+        // Each key is expected to be sampled 3000 times; allow for generous statistical noise.
+        assert!(hits.iter().all(|&h| h > 2700 && h < 3300), "{:?}", hits);
+    }
+
+    #[test]
+    fn test_dedup_values_in_range() {
+        let mut map: BTreeMap<u32, u32> =
+            vec![(1u32, 7u32), (2, 7), (3, 8), (4, 8), (5, 8), (6, 7), (7, 7)]
+            .into_iter().collect();
+        assert_eq!(map.dedup_values_in_range(&1, &10), 4);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 7u32), (3, 8), (6, 7)]);
+    }
+
+    #[test]
+    fn test_dedup_values_in_range_seeded() {
+        let mut map: BTreeMap<u32, u32> =
+            vec![(1u32, 7u32), (2, 7), (3, 7), (4, 8), (5, 8), (6, 8)].into_iter().collect();
+        assert_eq!(map.dedup_values_in_range(&2, &5), 2);
+        assert_eq!(map.clone().into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 7u32), (4, 8), (5, 8), (6, 8)]);
+        assert_eq!(map.dedup_values_in_range(&4, &4), 0);
+        assert_eq!(map.dedup_values_in_range(&6, &4), 0);
+        assert_eq!(map.dedup_values_in_range(&5, &7), 2);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 7u32), (4, 8)]);
+    }
+
+    #[test]
+    fn test_drain_range_in_batches() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        {
+            let mut batches = map.drain_range_in_batches(&0, &10, 10);
+            assert_eq!(batches.next_batch(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)]);
+            assert_eq!(batches.next_batch(), vec![]);
+        }
+        assert!(map.is_empty());
+        let mut map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        assert_eq!(map.drain_range_in_batches(&4, &2, 1).next_batch(), vec![]);
+        assert_eq!(map.drain_range_in_batches(&6, &9, 1).next_batch(), vec![]);
+        assert_eq!(map.len(), 5);
+    }
+
+    #[test]
+    fn test_drain_range_in_batches_interleaved() {
+        let mut map: BTreeMap<u32, u32> = (0u32..10).map(|k| (k * 10, k)).collect();
+        let mut batches = map.drain_range_in_batches(&10, &80, 2);
+        assert_eq!(batches.next_batch(), vec![(10u32, 1u32), (20, 2)]);
+        batches.map_mut().insert(15, 100);
+        batches.map_mut().insert(25, 100);
+        batches.map_mut().insert(85, 100);
+        batches.map_mut().remove(&30);
+        assert_eq!(batches.next_batch(), vec![(25u32, 100u32), (40, 4)]);
+        batches.map_mut().insert(45, 100);
+        assert_eq!(batches.next_batch(), vec![(45u32, 100u32), (50, 5)]);
+        assert_eq!(batches.next_batch(), vec![(60u32, 6u32), (70, 7)]);
+        assert_eq!(batches.next_batch(), vec![]);
+        assert_eq!(batches.map().keys().cloned().collect::<Vec<u32>>(), vec![0u32, 15, 80, 85, 90]);
+    }
+
+    #[test]
+    fn test_drain_range_in_batches_dropped() {
+        let mut map: BTreeMap<u32, u32> = (0u32..10).map(|k| (k, k)).collect();
+        {
+            let mut batches = map.drain_range_in_batches(&2, &8, 2);
+            assert_eq!(batches.next_batch(), vec![(2u32, 2u32), (3, 3)]);
+        }
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![0u32, 1, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_drain_range_in_batches_zero_batch() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.drain_range_in_batches(&0, &10, 0);
+    }
+
+    #[test]
+    fn test_k_nearest_entries_by() {
+        let map: BTreeMap<i32, u32> =
+            vec![(1i32, 1u32), (4, 4), (6, 6), (10, 10)].into_iter().collect();
+        let dist = |a: &i32, b: &i32| (a - b).abs();
+        assert_eq!(map.k_nearest_entries_by(&5, 3, dist),
+                   vec![(&4i32, &4u32), (&6, &6), (&1, &1)]);
+        assert_eq!(map.k_nearest_entries_by(&-5, 1, dist), vec![(&1i32, &1u32)]);
+        assert_eq!(map.k_nearest_entries_by(&50, 2, dist), vec![(&10i32, &10u32), (&6, &6)]);
+        assert_eq!(map.k_nearest_entries_by(&5, 0, dist), vec![]);
+        assert_eq!(map.k_nearest_entries_by(&7, 9, dist).len(), 4);
+    }
+
+    #[test]
+    fn test_range_any() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut visited = Vec::new();
+        assert!(map.range_any(&2, &5, |&k, _| { visited.push(k); k == 2 }));
+        assert_eq!(visited, vec![2u32]);
+        assert!(map.range_any(&2, &5, |&k, _| k == 4));
+        assert!(!map.range_any(&2, &5, |&k, _| k == 5));
+        assert!(!map.range_any(&4, &2, |_, _| true));
+        assert!(!map.range_any(&3, &3, |_, _| true));
+    }
+
+    #[test]
+    fn test_range_all() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut visited = Vec::new();
+        assert!(!map.range_all(&2, &5, |&k, _| { visited.push(k); k != 2 }));
+        assert_eq!(visited, vec![2u32]);
+        assert!(!map.range_all(&2, &5, |&k, _| k != 4));
+        assert!(map.range_all(&2, &5, |&k, _| k != 5));
+        assert!(map.range_all(&4, &2, |_, _| false));
+        assert!(map.range_all(&3, &3, |_, _| false));
+    }
+
+    #[test]
+    fn test_try_for_each_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut seen = Vec::new();
+        assert_eq!(map.try_for_each_range(&2, &5, |&k, _| { seen.push(k); Err(k) }), Err(2u32));
+        assert_eq!(seen, vec![2u32]);
+        seen.clear();
+        let res = map.try_for_each_range(&2, &5, |&k, _| {
+            seen.push(k);
+            if k == 4 { Err(k) } else { Ok(()) }
+        });
+        assert_eq!(res, Err(4u32));
+        assert_eq!(seen, vec![2u32, 3, 4]);
+        assert_eq!(map.try_for_each_range(&2, &5, |_, _| Ok::<(), u32>(())), Ok(()));
+        assert_eq!(map.try_for_each_range(&5, &2, |&k, _| Err(k)), Ok(()));
+        assert_eq!(map.try_for_each_range(&3, &3, |&k, _| Err(k)), Ok(()));
+    }
+
+    #[test]
+    fn test_try_for_each_range_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let res = map.try_for_each_range_mut(&2, &5, |&k, v| {
+            if k == 4 {
+                return Err(k);
             }
+            *v += 10;
+            Ok(())
+        });
+        assert_eq!(res, Err(4u32));
+        assert_eq!(map.clone().into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 1u32), (2, 12), (3, 13), (4, 4), (5, 5)]);
+        let res = map.try_for_each_range_mut(&1, &6, |_, v| { *v *= 2; Ok::<(), u32>(()) });
+        assert_eq!(res, Ok(()));
+        assert_eq!(map.try_for_each_range_mut(&5, &1, |&k, _| Err(k)), Ok(()));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 2u32), (2, 24), (3, 26), (4, 8), (5, 10)]);
+    }
+
+    #[test]
+    fn test_first_entry() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.first_entry(), Some((&1u32, &1u32)));
+        assert_eq!(BTreeMap::<u32, u32>::new().first_entry(), None);
+    }
+
+    #[test]
+    fn test_last_entry() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.last_entry(), Some((&5u32, &5u32)));
+        assert_eq!(BTreeMap::<u32, u32>::new().last_entry(), None);
+    }
+
+    #[test]
+    fn test_ceiling_entry() {
+        let map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (4, 40), (6, 60)].into_iter().collect();
+        assert_eq!(map.ceiling_entry(&1), Some((&2u32, &20u32)));
+        assert_eq!(map.ceiling_entry(&4), Some((&4u32, &40u32)));
+        assert_eq!(map.ceiling_entry(&5), Some((&6u32, &60u32)));
+        assert_eq!(map.ceiling_entry(&7), None);
+    }
+
+    #[test]
+    fn test_floor_entry() {
+        let map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (4, 40), (6, 60)].into_iter().collect();
+        assert_eq!(map.floor_entry(&1), None);
+        assert_eq!(map.floor_entry(&4), Some((&4u32, &40u32)));
+        assert_eq!(map.floor_entry(&5), Some((&4u32, &40u32)));
+        assert_eq!(map.floor_entry(&7), Some((&6u32, &60u32)));
+    }
+
+    #[test]
+    fn test_higher_entry() {
+        let map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (4, 40), (6, 60)].into_iter().collect();
+        assert_eq!(map.higher_entry(&1), Some((&2u32, &20u32)));
+        assert_eq!(map.higher_entry(&4), Some((&6u32, &60u32)));
+        assert_eq!(map.higher_entry(&5), Some((&6u32, &60u32)));
+        assert_eq!(map.higher_entry(&6), None);
+        let single: BTreeMap<u32, u32> = vec![(3u32, 30u32)].into_iter().collect();
+        assert_eq!(single.higher_entry(&3), None);
+    }
+
+    #[test]
+    fn test_lower_entry() {
+        let map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (4, 40), (6, 60)].into_iter().collect();
+        assert_eq!(map.lower_entry(&2), None);
+        assert_eq!(map.lower_entry(&4), Some((&2u32, &20u32)));
+        assert_eq!(map.lower_entry(&5), Some((&4u32, &40u32)));
+        assert_eq!(map.lower_entry(&7), Some((&6u32, &60u32)));
+        let single: BTreeMap<u32, u32> = vec![(3u32, 30u32)].into_iter().collect();
+        assert_eq!(single.lower_entry(&3), None);
+    }
+
+    #[test]
+    fn test_ceiling_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (4, 40)].into_iter().collect();
+        *map.ceiling_mut(&3).unwrap().1 += 1;
+        *map.ceiling_mut(&2).unwrap().1 += 1;
+        assert!(map.ceiling_mut(&5).is_none());
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 21u32), (4, 41)]);
+    }
+
+    #[test]
+    fn test_floor_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (4, 40)].into_iter().collect();
+        {
+            let (key, value) = map.floor_mut(&3).unwrap();
+            assert_eq!(key, &2u32);
+            *value += 1;
+        }
+        assert_eq!(map.get(&2), Some(&21u32));
+        *map.floor_mut(&4).unwrap().1 += 1;
+        assert!(map.floor_mut(&1).is_none());
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 21u32), (4, 41)]);
+    }
+
+    #[test]
+    fn test_higher_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (4, 40)].into_iter().collect();
+        *map.higher_mut(&2).unwrap().1 += 1;
+        assert!(map.higher_mut(&4).is_none());
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 20u32), (4, 41)]);
+    }
+
+    #[test]
+    fn test_lower_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (4, 40)].into_iter().collect();
+        *map.lower_mut(&4).unwrap().1 += 1;
+        assert!(map.lower_mut(&2).is_none());
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 21u32), (4, 40)]);
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (4, 40), (6, 60)].into_iter().collect();
+        assert_eq!(map.neighbors(&1), (None, None, Some((&2, &20))));
+        assert_eq!(map.neighbors(&7), (Some((&6, &60)), None, None));
+        assert_eq!(map.neighbors(&4), (Some((&2, &20)), Some((&4, &40)), Some((&6, &60))));
+        assert_eq!(map.neighbors(&2), (None, Some((&2, &20)), Some((&4, &40))));
+        assert_eq!(map.neighbors(&6), (Some((&4, &40)), Some((&6, &60)), None));
+        assert_eq!(map.neighbors(&5), (Some((&4, &40)), None, Some((&6, &60))));
+        let empty: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(empty.neighbors(&5), (None, None, None));
+    }
+
+    #[test]
+    fn test_navigation_borrowed_str() {
+        let mut map: BTreeMap<String, u32> = vec![("b", 1u32), ("d", 2), ("f", 3)]
+            .into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        assert_eq!(map.ceiling("c").map(|k| &k[..]), Some("d"));
+        assert_eq!(map.floor("c").map(|k| &k[..]), Some("b"));
+        assert_eq!(map.higher("d").map(|k| &k[..]), Some("f"));
+        assert_eq!(map.lower("d").map(|k| &k[..]), Some("b"));
+        assert_eq!(map.ceiling_entry("e"), Some((&"f".to_string(), &3u32)));
+        assert_eq!(map.lower_entry("a"), None);
+        *map.floor_mut("e").unwrap().1 += 10;
+        assert_eq!(map.get("d"), Some(&12u32));
+        assert_eq!(map.higher_remove("b"), Some(("d".to_string(), 12u32)));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_navigation_borrowed_slice() {
+        let mut map: BTreeMap<Vec<u8>, u32> =
+            vec![(vec![1u8, 2], 1u32), (vec![1, 4], 2), (vec![3], 3)].into_iter().collect();
+        let probe: &[u8] = &[1, 3];
+        assert_eq!(map.ceiling(probe), Some(&vec![1u8, 4]));
+        assert_eq!(map.floor(probe), Some(&vec![1u8, 2]));
+        assert_eq!(map.higher_entry(probe), Some((&vec![1u8, 4], &2u32)));
+        assert_eq!(map.lower_mut(probe).map(|(k, _)| k.clone()), Some(vec![1u8, 2]));
+        assert_eq!(map.ceiling_remove(probe), Some((vec![1u8, 4], 2u32)));
+        assert_eq!(map.floor_remove(probe), Some((vec![1u8, 2], 1u32)));
+        assert_eq!(map.lower_remove(probe), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_range_iter_bounded() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let keys = |min, max| {
+            map.range_iter_bounded(min, max).map(|(&k, _)| k).collect::<Vec<u32>>()
+        };
+        assert_eq!(keys(Unbounded, Excluded(&3)), vec![1u32, 2]);
+        assert_eq!(keys(Excluded(&3), Unbounded), vec![4u32, 5]);
+        assert_eq!(keys(Included(&2), Included(&4)), vec![2u32, 3, 4]);
+        assert_eq!(keys(Included(&4), Included(&9)), vec![4u32, 5]);
+        assert_eq!(keys(Unbounded, Unbounded), vec![1u32, 2, 3, 4, 5]);
+        assert_eq!(keys(Included(&4), Included(&2)), vec![]);
+        assert_eq!(keys(Excluded(&3), Excluded(&3)), vec![]);
+        assert_eq!(keys(Included(&3), Excluded(&3)), vec![]);
+        assert_eq!(keys(Included(&3), Included(&3)), vec![3u32]);
+    }
+
+    #[test]
+    fn test_range_iter_mut_bounded() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        for (_, v) in map.range_iter_mut_bounded(Unbounded, Included(&2)) {
+            *v += 10;
+        }
+        for (_, v) in map.range_iter_mut_bounded(Included(&5), Unbounded) {
+            *v += 20;
+        }
+        for (_, v) in map.range_iter_mut_bounded(Excluded(&2), Included(&3)) {
+            *v += 30;
+        }
+        assert_eq!(map.range_iter_mut_bounded(Included(&4), Included(&2)).count(), 0);
+        assert_eq!(map.range_iter_mut_bounded(Excluded(&3), Excluded(&3)).count(), 0);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 11u32), (2, 12), (3, 33), (4, 4), (5, 25)]);
+    }
+
+    #[test]
+    fn test_range_iter_by() {
+        let map: BTreeMap<u32, u32> = (1u32..11).map(|k| (k, k)).collect();
+        assert_eq!(map.range_iter_by(5..).map(|(&k, _)| k).collect::<Vec<u32>>(),
+                   vec![5u32, 6, 7, 8, 9, 10]);
+        assert_eq!(map.range_iter_by(..=9).map(|(&k, _)| k).collect::<Vec<u32>>(),
+                   vec![1u32, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(map.range_iter_by(..3).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![1u32, 2]);
+        assert_eq!(map.range_iter_by(3..=4).map(|(&k, _)| k).collect::<Vec<u32>>(),
+                   vec![3u32, 4]);
+        assert_eq!(map.range_iter_by(..).count(), 10);
+        assert_eq!(map.range_iter_by(20..).count(), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let inverted = 4..2;
+        assert_eq!(map.range_iter_by(inverted).count(), 0);
+        assert_eq!(map.range_iter_by((Excluded(3), Excluded(3))).count(), 0);
+    }
+
+    #[test]
+    fn test_range_iter_mut_by() {
+        let mut map: BTreeMap<u32, u32> = (1u32..11).map(|k| (k, k)).collect();
+        for (_, v) in map.range_iter_mut_by(8..) {
+            *v += 10;
+        }
+        for (_, v) in map.range_iter_mut_by(..=2) {
+            *v += 20;
+        }
+        for (_, v) in map.range_iter_mut_by(..) {
+            *v += 100;
+        }
+        #[allow(clippy::reversed_empty_ranges)]
+        let inverted = 4..2;
+        assert_eq!(map.range_iter_mut_by(inverted).count(), 0);
+        assert_eq!(map.range_iter_mut_by((Excluded(3), Excluded(3))).count(), 0);
+        assert_eq!(map.into_values().collect::<Vec<u32>>(),
+                   vec![121u32, 122, 103, 104, 105, 106, 107, 118, 119, 120]);
+    }
+
+    #[test]
+    fn test_range_iter_inclusive() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.range_iter_inclusive(&3, &5).map(|(&k, _)| k).collect::<Vec<u32>>(),
+                   vec![3u32, 4, 5]);
+        assert_eq!(map.range_iter_inclusive(&4, &4).map(|(&k, _)| k).collect::<Vec<u32>>(),
+                   vec![4u32]);
+        assert_eq!(map.range_iter_inclusive(&6, &6).count(), 0);
+        assert_eq!(map.range_iter_inclusive(&4, &2).count(), 0);
+        let max: BTreeMap<u32, u32> = vec![(1u32, 1u32), (!0, 2)].into_iter().collect();
+        assert_eq!(max.range_iter_inclusive(&1, &!0).count(), 2);
+    }
+
+    #[test]
+    fn test_range_iter_mut_inclusive() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        for (_, v) in map.range_iter_mut_inclusive(&4, &5) {
+            *v += 10;
+        }
+        for (_, v) in map.range_iter_mut_inclusive(&1, &1) {
+            *v += 20;
+        }
+        assert_eq!(map.range_iter_mut_inclusive(&5, &1).count(), 0);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 21u32), (2, 2), (3, 3), (4, 14), (5, 15)]);
+    }
+
+    #[test]
+    fn test_range_remove_iter_inclusive() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.range_remove_iter_inclusive(&4, &5).collect::<Vec<(u32, u32)>>(),
+                   vec![(4u32, 4u32), (5, 5)]);
+        assert_eq!(map.range_remove_iter_inclusive(&2, &2).collect::<Vec<(u32, u32)>>(),
+                   vec![(2u32, 2u32)]);
+        assert_eq!(map.range_remove_iter_inclusive(&2, &2).count(), 0);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (3, 3)]);
+    }
+
+    #[test]
+    fn test_iter_from() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.iter_from(&1).map(|(&k, _)| k).collect::<Vec<u32>>(),
+                   vec![1u32, 2, 3, 4, 5]);
+        assert_eq!(map.iter_from(&0).count(), 5);
+        assert_eq!(map.iter_from(&5).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![5u32]);
+        assert_eq!(map.iter_from(&6).count(), 0);
+    }
+
+    #[test]
+    fn test_iter_from_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        for (_, v) in map.iter_from_mut(&1) {
+            *v += 1;
+        }
+        assert_eq!(map.iter_from_mut(&6).count(), 0);
+        for (_, v) in map.iter_from_mut(&5) {
+            *v += 10;
+        }
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 2u32), (2, 3), (3, 4), (4, 5), (5, 16)]);
+    }
+
+    #[test]
+    fn test_iter_until() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.iter_until(&1).count(), 0);
+        assert_eq!(map.iter_until(&5).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![1u32, 2, 3, 4]);
+        assert_eq!(map.iter_until(&9).count(), 5);
+    }
+
+    #[test]
+    fn test_iter_until_inclusive() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.iter_until_inclusive(&0).count(), 0);
+        assert_eq!(map.iter_until_inclusive(&1).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![1u32]);
+        assert_eq!(map.iter_until_inclusive(&5).count(), 5);
+    }
+
+    #[test]
+    fn test_iter_until_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        for (_, v) in map.iter_until_mut(&4) {
+            *v += 10;
+        }
+        assert_eq!(map.iter_until_mut(&1).count(), 0);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 11u32), (2, 12), (3, 13), (4, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn test_descending_iter() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut iter = map.descending_iter();
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        assert_eq!(iter.next(), Some((&5u32, &5u32)));
+        assert_eq!(iter.next_back(), Some((&1u32, &1u32)));
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        assert_eq!(iter.map(|(&k, _)| k).collect::<Vec<u32>>(), vec![4u32, 3, 2]);
+        let empty: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(empty.descending_iter().size_hint(), (0, Some(0)));
+        assert_eq!(empty.descending_iter().next(), None);
+    }
+
+    #[test]
+    fn test_descending_iter_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        {
+            let mut iter = map.descending_iter_mut();
+            assert_eq!(iter.size_hint(), (5, Some(5)));
+            *iter.next().unwrap().1 += 10;
+            assert_eq!(iter.size_hint(), (4, Some(4)));
+            assert_eq!(iter.map(|(&k, _)| k).collect::<Vec<u32>>(), vec![4u32, 3, 2, 1]);
+        }
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 15)]);
+    }
+
+    #[test]
+    fn test_range_iter_desc() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.range_iter_desc(&1, &6).map(|(&k, _)| k).collect::<Vec<u32>>(),
+                   vec![5u32, 4, 3, 2, 1]);
+        assert_eq!(map.range_iter_desc(&3, &4).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![3u32]);
+        assert_eq!(map.range_iter_desc(&3, &3).count(), 0);
+        assert_eq!(map.range_iter_desc(&6, &9).count(), 0);
+        assert_eq!(map.range_iter_desc(&5, &2).count(), 0);
+        let mut iter = map.range_iter_desc(&2, &5);
+        assert_eq!(iter.next_back(), Some((&2u32, &2u32)));
+        assert_eq!(iter.next(), Some((&4u32, &4u32)));
+    }
+
+    #[test]
+    fn test_range_iter_desc_mut() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        for (_, v) in map.range_iter_desc_mut(&4, &5) {
+            *v += 10;
+        }
+        assert_eq!(map.range_iter_desc_mut(&2, &2).count(), 0);
+        assert_eq!(map.range_iter_desc_mut(&5, &2).count(), 0);
+        assert_eq!(map.range_iter_desc_mut(&2, &4).map(|(&k, _)| k).collect::<Vec<u32>>(),
+                   vec![3u32, 2]);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 1u32), (2, 2), (3, 3), (4, 14), (5, 5)]);
+    }
+
+    #[test]
+    fn test_count_range() {
+        let map: BTreeMap<u32, u32> = vec![(10u32, 1u32), (20, 2), (30, 3)].into_iter().collect();
+        assert_eq!(map.count_range(&20, &20), 0);
+        assert_eq!(map.count_range(&40, &50), 0);
+        assert_eq!(map.count_range(&0, &100), 3);
+        assert_eq!(map.count_range(&10, &30), 2);
+        assert_eq!(map.count_range(&15, &25), 1);
+        assert_eq!(map.count_range(&11, &19), 0);
+        assert_eq!(map.count_range(&30, &10), 0);
+    }
+
+    #[test]
+    fn test_count_range_bounded() {
+        let map: BTreeMap<u32, u32> = vec![(10u32, 1u32), (20, 2), (30, 3)].into_iter().collect();
+        assert_eq!(map.count_range_bounded(Unbounded, Unbounded), 3);
+        assert_eq!(map.count_range_bounded(Excluded(&10), Included(&30)), 2);
+        assert_eq!(map.count_range_bounded(Unbounded, Excluded(&10)), 0);
+        assert_eq!(map.count_range_bounded(Included(&15), Unbounded), 2);
+        assert_eq!(map.count_range_bounded(Included(&30), Included(&10)), 0);
+        assert_eq!(map.count_range_bounded(Excluded(&20), Excluded(&20)), 0);
+    }
+
+    #[test]
+    fn test_fold_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.fold_range(&1, &6, 0, |sum, _, &v| sum + v), 15);
+        assert_eq!(map.fold_range(&2, &4, Vec::new(), |mut keys, &k, _| { keys.push(k); keys }),
+                   vec![2u32, 3]);
+        assert_eq!(map.fold_range(&4, &2, 7, |sum, _, &v| sum + v), 7);
+    }
+
+    #[test]
+    fn test_try_fold_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let full: Result<u32, u32> = map.try_fold_range(&1, &6, 0, |sum, _, &v| Ok(sum + v));
+        assert_eq!(full, Ok(15));
+        let mut visited = Vec::new();
+        let res = map.try_fold_range(&1, &6, 0, |sum, &k, &v| {
+            visited.push(k);
+            if sum + v >= 6 { Err(sum + v) } else { Ok(sum + v) }
+        });
+        assert_eq!(res, Err(6));
+        assert_eq!(visited, vec![1u32, 2, 3]);
+        assert_eq!(map.try_fold_range(&3, &3, 1, |_, _, _| Err(())), Ok(1));
+    }
+
+    #[test]
+    fn test_retain_range() {
+        let mut map: BTreeMap<u32, u32> = (1u32..9).map(|k| (k, k * 10)).collect();
+        let mut visited = Vec::new();
+        map.retain_range(&3, &7, |&k, v| {
+            visited.push(k);
+            *v += 1;
+            k % 2 == 0
+        });
+        assert_eq!(visited, vec![3u32, 4, 5, 6]);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 10u32), (2, 20), (4, 41), (6, 61), (7, 70), (8, 80)]);
+    }
+
+    #[test]
+    fn test_retain_range_empty() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.retain_range(&4, &2, |_, _| panic!("visited an inverted range"));
+        map.retain_range(&3, &3, |_, _| panic!("visited an empty range"));
+        map.retain_range(&6, &9, |_, _| panic!("visited a range past the end"));
+        assert_eq!(map.len(), 5);
+    }
+
+    #[test]
+    fn test_range_remove_if() {
+        let mut map: BTreeMap<u32, u32> = (1u32..9).map(|k| (k, k * 10)).collect();
+        let mut visited = Vec::new();
+        let removed: Vec<(u32, u32)> = map.range_remove_if(&2, &8, |&k, _| {
+            visited.push(k);
+            k % 2 == 1
+        }).collect();
+        assert_eq!(visited, vec![2u32, 3, 4, 5, 6, 7]);
+        assert_eq!(removed, vec![(3u32, 30u32), (5, 50), (7, 70)]);
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 4, 6, 8]);
+        assert_eq!(map.range_remove_if(&8, &2, |_, _| true).count(), 0);
+        assert_eq!(map.range_remove_if(&1, &9, |_, _| false).count(), 0);
+        assert_eq!(map.len(), 5);
+    }
+
+    #[test]
+    fn test_split_off_range() {
+        let mut map: BTreeMap<u32, u32> = (1u32..9).map(|k| (k, k * 10)).collect();
+        let range = map.split_off_range(&3, &6);
+        assert_eq!(range.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(3u32, 30u32), (4, 40), (5, 50)]);
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 6, 7, 8]);
+        let tail = map.split_off_range(&7, &100);
+        assert_eq!(tail.keys().cloned().collect::<Vec<u32>>(), vec![7u32, 8]);
+        assert!(map.split_off_range(&6, &2).is_empty());
+        assert!(map.split_off_range(&3, &6).is_empty());
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 6]);
+    }
+
+    #[test]
+    fn test_pop_first_n() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.pop_first_n(0).len(), 0);
+        assert_eq!(map.len(), 5);
+        let mut popped = map.pop_first_n(2);
+        assert_eq!(popped.len(), 2);
+        assert_eq!(popped.next(), Some((1u32, 1u32)));
+        assert_eq!(popped.next(), Some((2u32, 2u32)));
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![3u32, 4, 5]);
+        assert_eq!(map.pop_first_n(9).collect::<Vec<(u32, u32)>>(),
+                   vec![(3u32, 3u32), (4, 4), (5, 5)]);
+        assert!(map.is_empty());
+        assert_eq!(map.pop_first_n(1).len(), 0);
+    }
+
+    #[test]
+    fn test_pop_last_n() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.pop_last_n(0).len(), 0);
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.pop_last_n(1).collect::<Vec<(u32, u32)>>(), vec![(5u32, 5u32)]);
+        assert_eq!(map.pop_last_n(3).collect::<Vec<(u32, u32)>>(),
+                   vec![(2u32, 2u32), (3, 3), (4, 4)]);
+        assert_eq!(map.pop_last_n(9).collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32)]);
+        assert!(map.is_empty());
+        assert_eq!(map.pop_last_n(1).len(), 0);
+    }
+
+    #[test]
+    fn test_pop_while_front() {
+        let mut map: BTreeMap<u32, u32> =
+            vec![(1u32, 1u32), (2, 2), (3, 30), (4, 4), (5, 5)].into_iter().collect();
+        let mut visited = Vec::new();
+        let removed: Vec<(u32, u32)> = map.pop_while_front(|&k, &v| {
+            visited.push(k);
+            v < 10
+        }).collect();
+        assert_eq!(removed, vec![(1u32, 1u32), (2, 2)]);
+        assert_eq!(visited, vec![1u32, 2, 3]);
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![3u32, 4, 5]);
+        assert_eq!(map.pop_while_front(|_, &v| v < 10).count(), 0);
+        assert_eq!(map.pop_while_front(|_, _| true).count(), 3);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_pop_while_back() {
+        let mut map: BTreeMap<u32, u32> =
+            vec![(1u32, 1u32), (2, 2), (3, 30), (4, 4), (5, 5)].into_iter().collect();
+        let mut visited = Vec::new();
+        let removed: Vec<(u32, u32)> = map.pop_while_back(|&k, &v| {
+            visited.push(k);
+            v < 10
+        }).collect();
+        assert_eq!(removed, vec![(4u32, 4u32), (5, 5)]);
+        assert_eq!(visited, vec![5u32, 4, 3]);
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 3]);
+        assert_eq!(map.pop_while_back(|_, &v| v < 10).count(), 0);
+        assert_eq!(map.pop_while_back(|_, _| true).count(), 3);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_truncate_before() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.truncate_before(&1), 0);
+        assert_eq!(map.truncate_before(&3), 2);
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![3u32, 4, 5]);
+        assert_eq!(map.truncate_before(&3), 0);
+        assert_eq!(map.truncate_before(&9), 3);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_truncate_after() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.truncate_after(&5), 0);
+        assert_eq!(map.truncate_after(&3), 2);
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 3]);
+        assert_eq!(map.truncate_after(&3), 0);
+        assert_eq!(map.truncate_after(&0), 3);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_nth_key() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.nth_key(0), Some((&1u32, &1u32)));
+        assert_eq!(map.nth_key(2), Some((&3u32, &3u32)));
+        assert_eq!(map.nth_key(4), Some((&5u32, &5u32)));
+        assert_eq!(map.nth_key(5), None);
+        assert_eq!(BTreeMap::<u32, u32>::new().nth_key(0), None);
+    }
+
+    #[test]
+    fn test_nth_key_remove() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.nth_key_remove(5), None);
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.nth_key_remove(4), Some((5u32, 5u32)));
+        assert_eq!(map.nth_key_remove(0), Some((1u32, 1u32)));
+        assert_eq!(map.nth_key_remove(1), Some((3u32, 3u32)));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (4, 4)]);
+    }
+
+    #[test]
+    fn test_rank() {
+        let map: BTreeMap<u32, u32> = vec![(10u32, 1u32), (20, 2), (30, 3)].into_iter().collect();
+        assert_eq!(map.rank(&0), 0);
+        assert_eq!(map.rank(&10), 0);
+        assert_eq!(map.rank(&15), 1);
+        assert_eq!(map.rank(&20), 1);
+        assert_eq!(map.rank(&25), 2);
+        assert_eq!(map.rank(&30), 2);
+        assert_eq!(map.rank(&31), 3);
+        assert_eq!(BTreeMap::<u32, u32>::new().rank(&5), 0);
+    }
+
+    #[test]
+    fn test_keys_range() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.keys_range(&2, &5).collect::<Vec<&u32>>(),
+                   map.range_iter(&2, &5).map(|(k, _)| k).collect::<Vec<&u32>>());
+        assert_eq!(map.keys_range(&2, &5).rev().cloned().collect::<Vec<u32>>(), vec![4u32, 3, 2]);
+        let mut keys = map.keys_range(&1, &6);
+        assert_eq!(keys.next(), Some(&1u32));
+        assert_eq!(keys.next_back(), Some(&5u32));
+        assert_eq!(keys.cloned().collect::<Vec<u32>>(), vec![2u32, 3, 4]);
+        assert_eq!(map.keys_range(&3, &3).count(), 0);
+        assert_eq!(map.keys_range(&5, &2).count(), 0);
+    }
+
+    #[test]
+    fn test_values_range() {
+        let map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k * 10)).collect();
+        assert_eq!(map.values_range(&2, &5).cloned().collect::<Vec<u32>>(), vec![20u32, 30, 40]);
+        assert_eq!(map.values_range(&2, &5).rev().cloned().collect::<Vec<u32>>(),
+                   vec![40u32, 30, 20]);
+        assert_eq!(map.values_range(&4, &4).count(), 0);
+        assert_eq!(map.values_range(&5, &2).count(), 0);
+    }
+
+    #[test]
+    fn test_values_range_mut() {
+        let mut map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k * 10)).collect();
+        for v in map.values_range_mut(&2, &4) {
+            *v += 1;
         }
+        *map.values_range_mut(&1, &6).next_back().unwrap() += 2;
+        assert_eq!(map.values_range_mut(&5, &2).count(), 0);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 10u32), (2, 21), (3, 31), (4, 40), (5, 52)]);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let map: BTreeMap<i32, u32> =
+            vec![(-10i32, 1u32), (0, 2), (10, 3), (13, 4)].into_iter().collect();
+        assert_eq!(map.nearest(&0), Some((&0i32, &2u32)));
+        assert_eq!(map.nearest(&-5), Some((&-10i32, &1u32)));
+        assert_eq!(map.nearest(&4), Some((&0i32, &2u32)));
+        assert_eq!(map.nearest(&6), Some((&10i32, &3u32)));
+        assert_eq!(map.nearest(&12), Some((&13i32, &4u32)));
+        assert_eq!(map.nearest(&-100), Some((&-10i32, &1u32)));
+        assert_eq!(map.nearest(&100), Some((&13i32, &4u32)));
+        assert_eq!(BTreeMap::<i32, u32>::new().nearest(&0), None);
+    }
+
+    #[test]
+    fn test_get_closest_n() {
+        let map: BTreeMap<i32, u32> =
+            vec![(-10i32, 1u32), (0, 2), (10, 3), (13, 4)].into_iter().collect();
+        let keys = |key, n| map.get_closest_n(&key, n).into_iter().map(|(&k, _)| k)
+                               .collect::<Vec<i32>>();
+        assert_eq!(keys(11, 2), vec![10i32, 13]);
+        assert_eq!(keys(5, 3), vec![0i32, 10, 13]);
+        assert_eq!(keys(-5, 2), vec![-10i32, 0]);
+        assert_eq!(keys(0, 1), vec![0i32]);
+        assert_eq!(keys(100, 9), vec![13i32, 10, 0, -10]);
+        assert_eq!(keys(0, 0), vec![]);
+    }
+
+    #[test]
+    fn test_update_key() {
+        let mut map: BTreeMap<u32, Rc<u32>> = (1u32..6).map(|k| (k, Rc::new(k))).collect();
+        let witness = map[&3].clone();
+        assert_eq!(map.update_key(&3, 9), Ok(()));
+        assert!(Rc::ptr_eq(&map[&9], &witness));
+        assert_eq!(Rc::strong_count(&witness), 2);
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 4, 5, 9]);
+        assert_eq!(map.update_key(&3, 0), Err(UpdateKeyError::Missing(0)));
+        assert_eq!(map.update_key(&9, 4), Err(UpdateKeyError::Occupied(4)));
+        assert_eq!(map.update_key(&9, 9), Ok(()));
+        assert_eq!(map.update_key(&9, 0), Ok(()));
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![0u32, 1, 2, 4, 5]);
+        assert!(Rc::ptr_eq(&map[&0], &witness));
+    }
 
-        fn last(&self) -> Option<&K> {
-            self.keys().max()
-        }
+    #[test]
+    fn test_update_key_with() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        let mut merged = None;
+        assert_eq!(map.update_key_with(&2, 5, |&k, existing, moved| {
+            merged = Some((k, existing, moved));
+            existing * 10 + moved
+        }), Ok(()));
+        assert_eq!(merged, Some((5u32, 5u32, 2u32)));
+        assert_eq!(map.update_key_with(&1, 0, |_, _, _| panic!("nothing to merge")), Ok(()));
+        assert_eq!(map.update_key_with(&2, 6, |_, _, _| panic!("nothing to move")),
+                   Err(UpdateKeyError::Missing(6)));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(0u32, 1u32), (3, 3), (4, 4), (5, 52)]);
+    }
 
-        fn last_remove(&mut self) -> Option<(K, V)> {
-            if let Some(key) = self.last().cloned() {
-                let val = self.remove(&key);
-                assert!(val.is_some());
-                Some((key, val.unwrap()))
-            } else {
-                None
-            }
-        }
+    #[test]
+    fn test_replace_range() {
+        let mut map: BTreeMap<u32, u32> = (1u32..9).map(|k| (k, k)).collect();
+        let removed: Vec<(u32, u32)> =
+            map.replace_range(&3, &7, vec![(4u32, 40u32), (6, 60)]).collect();
+        assert_eq!(removed, vec![(3u32, 3u32), (4, 4), (5, 5), (6, 6)]);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 1u32), (2, 2), (4, 40), (6, 60), (7, 7), (8, 8)]);
+    }
 
-        fn ceiling(&self, key: &K) -> Option<&K> {
-            self.keys().filter(|&k| k >= key).min()
-        }
+    #[test]
+    fn test_replace_range_outside_entries() {
+        let mut map: BTreeMap<u32, u32> = (1u32..9).map(|k| (k, k)).collect();
+        // Entries outside the range are inserted too, replacing any existing value.
+        let removed: Vec<(u32, u32)> =
+            map.replace_range(&3, &5, vec![(1u32, 10u32), (3, 30), (9, 90)]).collect();
+        assert_eq!(removed, vec![(3u32, 3u32), (4, 4)]);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 10u32), (2, 2), (3, 30), (5, 5), (6, 6), (7, 7), (8, 8), (9, 90)]);
+    }
 
-        fn ceiling_remove(&mut self, key: &K) -> Option<(K, V)> {
-            if let Some(ceiling) = self.ceiling(key).cloned() {
-                let val = self.remove(&ceiling);
-                assert!(val.is_some());
-                Some((ceiling, val.unwrap()))
-            } else {
-                None
-            }
-        }
+    #[test]
+    fn test_replace_range_inverted() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.replace_range(&4, &2, vec![(3u32, 30u32)]).count(), 0);
+        assert_eq!(map.values().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 30, 4, 5]);
+    }
 
-        fn floor(&self, key: &K) -> Option<&K> {
-            self.keys().filter(|&k| k <= key).max()
+    #[test]
+    fn test_extend_from_sorted() {
+        let base: BTreeMap<u32, u32> = (0u32..100).map(|k| (k * 2, k)).collect();
+        let batches: Vec<Vec<(u32, u32)>> = vec![
+            vec![],
+            vec![(500u32, 1u32), (501, 2), (502, 3)],
+            vec![(10u32, 1u32), (11, 2)],
+            (0u32..300).map(|k| (k, k + 1000)).collect(),
+            vec![(3u32, 1u32), (3, 2), (198, 3), (198, 4), (199, 5)],
+        ];
+        for batch in batches {
+            let mut fast = base.clone();
+            fast.extend_from_sorted(batch.clone());
+            let mut naive = base.clone();
+            naive.extend(batch);
+            assert_eq!(fast, naive);
         }
+        let mut empty = BTreeMap::new();
+        empty.extend_from_sorted(vec![(1u32, 1u32), (1, 2)]);
+        assert_eq!(empty.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 2u32)]);
+    }
 
-        fn floor_remove(&mut self, key: &K) -> Option<(K, V)> {
-            if let Some(floor) = self.floor(key).cloned() {
-                let val = self.remove(&floor);
-                assert!(val.is_some());
-                Some((floor, val.unwrap()))
-            } else {
-                None
-            }
-        }
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn test_extend_from_sorted_unsorted() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        map.extend_from_sorted(vec![(7u32, 7u32), (6, 6)]);
+    }
 
-        fn higher(&self, key: &K) -> Option<&K> {
-            self.keys().filter(|&k| k > key).min()
-        }
+    #[test]
+    fn test_min_max_by_value() {
+        let map: BTreeMap<u32, u32> =
+            vec![(1u32, 7u32), (2, 3), (3, 9), (4, 3), (5, 9)].into_iter().collect();
+        assert_eq!(map.min_by_value(), Some((&2u32, &3u32)));
+        assert_eq!(map.max_by_value(), Some((&3u32, &9u32)));
+        let empty: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(empty.min_by_value(), None);
+        assert_eq!(empty.max_by_value(), None);
+    }
 
-        fn higher_remove(&mut self, key: &K) -> Option<(K, V)> {
-            if let Some(higher) = self.higher(key).cloned() {
-                let val = self.remove(&higher);
-                assert!(val.is_some());
-                Some((higher, val.unwrap()))
-            } else {
-                None
-            }
-        }
+    #[test]
+    fn test_min_max_by_value_by_key() {
+        let map: BTreeMap<u32, f64> =
+            vec![(1u32, 0.5f64), (2, -1.5), (3, 1.5), (4, 0.5)].into_iter().collect();
+        let magnitude = |&v: &f64| (v.abs() * 10.0) as u32;
+        assert_eq!(map.min_by_value_by_key(&magnitude), Some((&1u32, &0.5f64)));
+        assert_eq!(map.max_by_value_by_key(&magnitude), Some((&2u32, &-1.5f64)));
+        assert_eq!(BTreeMap::<u32, f64>::new().max_by_value_by_key(&magnitude), None);
+    }
 
-        fn lower(&self, key: &K) -> Option<&K> {
-            self.keys().filter(|&k| k < key).max()
-        }
+    #[test]
+    fn test_gaps() {
+        let map: BTreeMap<u64, u32> =
+            vec![(0u64, 0u32), (4, 4), (5, 5), (9, 9), (10, 10), (12, 12)].into_iter().collect();
+        assert_eq!(map.gaps().collect::<Vec<(u64, u64)>>(), vec![(1u64, 4u64), (6, 9), (11, 12)]);
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
+        assert_eq!(map.gaps().count(), 0);
+        let single: BTreeMap<u32, u32> = vec![(7u32, 7u32)].into_iter().collect();
+        assert_eq!(single.gaps().count(), 0);
+        assert_eq!(BTreeMap::<u32, u32>::new().gaps().count(), 0);
+        let extremes: BTreeMap<u8, u32> = vec![(0u8, 0u32), (255, 1)].into_iter().collect();
+        assert_eq!(extremes.gaps().collect::<Vec<(u8, u8)>>(), vec![(1u8, 255u8)]);
+    }
 
-        fn lower_remove(&mut self, key: &K) -> Option<(K, V)> {
-            if let Some(lower) = self.lower(key).cloned() {
-                let val = self.remove(&lower);
-                assert!(val.is_some());
-                Some((lower, val.unwrap()))
-            } else {
-                None
+    #[test]
+    fn test_gaps_newtype_key() {
+        use crate::distance::KeyStep;
+
+        #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Slot(u32);
+
+        impl KeyStep for Slot {
+            fn successor(&self) -> Option<Slot> { self.0.successor().map(Slot) }
+            fn forward(&self, steps: usize) -> Option<Slot> { self.0.forward(steps).map(Slot) }
+            fn steps_between(&self, other: &Slot) -> Option<usize> {
+                self.0.steps_between(&other.0)
             }
         }
-    );
-}
 
-// An impl of SortedMapExt for the standard library BTreeMap
-impl<'a, K, V> SortedMapExt<K, V> for BTreeMap<K, V>
-    where K: Clone + Ord,
-          V: Clone
-{
-    type RangeIter = BTreeMapRangeIter<'a, K, V>;
-    type RangeIterMut = BTreeMapRangeIterMut<'a, K, V>;
-    type RangeRemoveIter = BTreeMapRangeRemoveIter<K, V>;
+        let map: BTreeMap<Slot, ()> =
+            vec![Slot(1), Slot(2), Slot(6)].into_iter().map(|k| (k, ())).collect();
+        assert_eq!(map.gaps().collect::<Vec<(Slot, Slot)>>(), vec![(Slot(3), Slot(6))]);
+    }
 
-    sortedmap_impl!(BTreeMap<K, V>);
+    #[test]
+    fn test_pairs_iter() {
+        let map: BTreeMap<u32, u32> =
+            vec![(1u32, 10u32), (2, 13), (4, 20), (7, 21)].into_iter().collect();
+        let keys: Vec<(u32, u32)> = map.pairs_iter().map(|((&a, _), (&b, _))| (a, b)).collect();
+        assert_eq!(keys, vec![(1u32, 2u32), (2, 4), (4, 7)]);
+        let keys: Vec<(u32, u32)> =
+            map.pairs_iter().rev().map(|((&a, _), (&b, _))| (a, b)).collect();
+        assert_eq!(keys, vec![(4u32, 7u32), (2, 4), (1, 2)]);
+        assert_eq!(map.pairs_iter().map(|((_, a), (_, b))| b - a).collect::<Vec<u32>>(),
+                   vec![3u32, 7, 1]);
+        let single: BTreeMap<u32, u32> = vec![(1u32, 1u32)].into_iter().collect();
+        assert_eq!(single.pairs_iter().count(), 0);
+        assert_eq!(single.pairs_iter().next_back(), None);
+        assert_eq!(BTreeMap::<u32, u32>::new().pairs_iter().count(), 0);
+    }
 
-    fn range_iter(&self, from_key: &K, to_key: &K) -> BTreeMapRangeIter<K, V> {
-        BTreeMapRangeIter { iter: self.range(Included(from_key), Excluded(to_key)) }
+    #[test]
+    fn test_pairs_iter_both_ends() {
+        let map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        let mut iter = map.pairs_iter();
+        assert_eq!(iter.next().map(|((&a, _), (&b, _))| (a, b)), Some((1u32, 2u32)));
+        assert_eq!(iter.next_back().map(|((&a, _), (&b, _))| (a, b)), Some((4u32, 5u32)));
+        assert_eq!(iter.next_back().map(|((&a, _), (&b, _))| (a, b)), Some((3u32, 4u32)));
+        assert_eq!(iter.next().map(|((&a, _), (&b, _))| (a, b)), Some((2u32, 3u32)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+        let mut iter = map.pairs_range_iter(&2, &4);
+        assert_eq!(iter.next_back().map(|((&a, _), (&b, _))| (a, b)), Some((2u32, 3u32)));
+        assert_eq!(iter.next(), None);
     }
 
-    fn range_iter_mut(&mut self, from_key: &K, to_key: &K) -> BTreeMapRangeIterMut<K, V> {
-        BTreeMapRangeIterMut { iter: self.range_mut(Included(from_key), Excluded(to_key)) }
+    #[test]
+    fn test_pairs_range_iter() {
+        let map: BTreeMap<u32, u32> = (1u32..9).map(|k| (k, k)).collect();
+        assert_eq!(map.pairs_range_iter(&3, &6).map(|((&a, _), (&b, _))| (a, b))
+                      .collect::<Vec<(u32, u32)>>(), vec![(3u32, 4u32), (4, 5)]);
+        assert_eq!(map.pairs_range_iter(&3, &4).count(), 0);
+        assert_eq!(map.pairs_range_iter(&20, &30).count(), 0);
     }
 
-    fn range_remove_iter(&mut self, from_key: &K, to_key: &K) -> BTreeMapRangeRemoveIter<K, V> {
-        let ret: BTreeMap<K, V> = 
-                self.range_iter(from_key, to_key)
-                .map(|(ref k, ref v)| ((**k).clone(), (**v).clone()))
-                .collect();
+    #[test]
+    fn test_drain_sorted() {
+        let mut map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k * 10)).collect();
+        let mut iter = map.drain_sorted();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next(), Some((1u32, 10u32)));
+        assert_eq!(iter.next_back(), Some((5u32, 50u32)));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.collect::<Vec<(u32, u32)>>(), vec![(2u32, 20u32), (3, 30), (4, 40)]);
+        assert!(map.is_empty());
+        assert_eq!(map.drain_sorted().len(), 0);
+    }
 
-        for key in ret.keys() {
-            assert!(self.remove(key).is_some());
+    #[test]
+    fn test_drain_sorted_dropped_early() {
+        let mut map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        {
+            let mut iter = map.drain_sorted();
+            assert_eq!(iter.next(), Some((1u32, 1u32)));
+            assert_eq!(iter.next(), Some((2u32, 2u32)));
         }
-        BTreeMapRangeRemoveIter { iter: ret.into_iter() }
+        assert!(map.is_empty());
+        map.insert(7, 7);
+        assert_eq!(map.drain_sorted().collect::<Vec<(u32, u32)>>(), vec![(7u32, 7u32)]);
     }
-}
 
-pub struct BTreeMapRangeIter<'a, K: 'a, V: 'a> {
-    iter: btree_map::Range<'a, K, V>
-}
+    #[test]
+    fn test_range_iter_inverted_and_equal_bounds() {
+        let mut map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        assert_eq!(map.range_iter(&4, &2).count(), 0);
+        assert_eq!(map.range_iter(&3, &3).count(), 0);
+        assert_eq!(map.range_iter_mut(&4, &2).count(), 0);
+        assert_eq!(map.range_iter_mut(&3, &3).count(), 0);
+        assert_eq!(map.range_remove_iter(&4, &2).count(), 0);
+        assert_eq!(map.range_remove_iter(&3, &3).count(), 0);
+        assert_eq!(map.len(), 5);
+    }
 
-impl<'a, K, V> Iterator for BTreeMapRangeIter<'a, K, V> {
-    type Item = (&'a K, &'a V);
+    #[test]
+    fn test_merge_with_disjoint() {
+        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (5, 5)].into_iter().collect();
+        let other: BTreeMap<u32, u32> = vec![(3u32, 3u32), (7, 7)].into_iter().collect();
+        map.merge_with(other, |_, _, _| panic!("no keys collide"));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 1u32), (3, 3), (5, 5), (7, 7)]);
+        let mut map: BTreeMap<u32, u32> = (1u32..4).map(|k| (k, k)).collect();
+        map.merge_with((4u32..6).map(|k| (k, k)).collect(), |_, _, _| panic!("no keys collide"));
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![1u32, 2, 3, 4, 5]);
+        let mut empty: BTreeMap<u32, u32> = BTreeMap::new();
+        empty.merge_with((1u32..3).map(|k| (k, k)).collect(), |_, _, _| panic!("no keys collide"));
+        assert_eq!(empty.len(), 2);
+    }
 
-    fn next(&mut self) -> Option<(&'a K, &'a V)> { self.iter.next() }
-    fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
-}
-impl<'a, K, V> DoubleEndedIterator for BTreeMapRangeIter<'a, K, V> {
-    fn next_back(&mut self) -> Option<(&'a K, &'a V)> { self.iter.next_back() }
-}
+    #[test]
+    fn test_merge_with_overlapping() {
+        let mut map: BTreeMap<u32, u32> = (1u32..5).map(|k| (k, k)).collect();
+        let other: BTreeMap<u32, u32> = (1u32..5).map(|k| (k, k * 10)).collect();
+        let mut seen = Vec::new();
+        map.merge_with(other, |&k, mine, theirs| { seen.push(k); mine + theirs });
+        assert_eq!(seen, vec![1u32, 2, 3, 4]);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(1u32, 11u32), (2, 22), (3, 33), (4, 44)]);
+    }
 
-pub struct BTreeMapRangeIterMut<'a, K: 'a, V: 'a> {
-    iter: btree_map::RangeMut<'a, K, V>
-}
+    #[test]
+    fn test_merge_with_small_into_large() {
+        let mut map: BTreeMap<u32, u32> = (0u32..1000).map(|k| (k, 1)).collect();
+        let other: BTreeMap<u32, u32> = vec![(10u32, 2u32), (2000, 2)].into_iter().collect();
+        map.merge_with(other, |_, mine, theirs| mine + theirs);
+        assert_eq!(map.len(), 1001);
+        assert_eq!(map.get(&10), Some(&3u32));
+        assert_eq!(map.get(&2000), Some(&2u32));
+    }
 
-impl<'a, K, V> Iterator for BTreeMapRangeIterMut<'a, K, V> {
-    type Item = (&'a K, &'a mut V);
+    #[test]
+    fn test_join_iter() {
+        let large: BTreeMap<u32, u32> = (0u32..1000).map(|k| (k, k * 2)).collect();
+        let small: BTreeMap<u32, &str> =
+            vec![(5u32, "a"), (500, "b"), (2000, "c")].into_iter().collect();
+        let joined: Vec<(u32, u32, &str)> =
+            large.join_iter(&small).map(|(&k, &v, &w)| (k, v, w)).collect();
+        assert_eq!(joined, vec![(5u32, 10u32, "a"), (500, 1000, "b")]);
+        let joined: Vec<(u32, &str, u32)> =
+            small.join_iter(&large).map(|(&k, &v, &w)| (k, v, w)).collect();
+        assert_eq!(joined, vec![(5u32, "a", 10u32), (500, "b", 1000)]);
+    }
 
-    fn next(&mut self) -> Option<(&'a K, &'a mut V)> { self.iter.next() }
-    fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
-}
-impl<'a, K, V> DoubleEndedIterator for BTreeMapRangeIterMut<'a, K, V> {
-    fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> { self.iter.next_back() }
-}
+    #[test]
+    fn test_join_iter_no_overlap() {
+        let evens: BTreeMap<u32, u32> = (0u32..10).map(|k| (k * 2, k)).collect();
+        let odds: BTreeMap<u32, u32> = (0u32..10).map(|k| (k * 2 + 1, k)).collect();
+        assert_eq!(evens.join_iter(&odds).count(), 0);
+        assert_eq!(evens.join_iter(&BTreeMap::<u32, u32>::new()).count(), 0);
+        assert_eq!(BTreeMap::<u32, u32>::new().join_iter(&odds).count(), 0);
+    }
 
-pub struct BTreeMapRangeRemoveIter<K, V> {
-    iter: btree_map::IntoIter<K, V>
-}
+    #[test]
+    fn test_left_join_iter() {
+        let map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        let other: BTreeMap<u32, u32> =
+            vec![(0u32, 0u32), (2, 20), (4, 40), (9, 90)].into_iter().collect();
+        let joined: Vec<(u32, Option<u32>)> =
+            map.left_join_iter(&other).map(|(&k, _, w)| (k, w.cloned())).collect();
+        assert_eq!(joined, vec![(1u32, None), (2, Some(20u32)), (3, None), (4, Some(40)),
+                                (5, None)]);
+        assert_eq!(map.left_join_iter(&other).len(), 5);
+    }
 
-impl<K, V> Iterator for BTreeMapRangeRemoveIter<K, V> {
-    type Item = (K, V);
+    #[test]
+    fn test_left_join_iter_empty_and_superset() {
+        let map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        let empty: BTreeMap<u32, u32> = BTreeMap::new();
+        assert!(map.left_join_iter(&empty).all(|(_, _, w)| w.is_none()));
+        assert_eq!(map.left_join_iter(&empty).count(), 5);
+        let superset: BTreeMap<u32, u32> = (0u32..10).map(|k| (k, k * 10)).collect();
+        assert!(map.left_join_iter(&superset).all(|(&k, _, w)| w == Some(&(k * 10))));
+        assert_eq!(empty.left_join_iter(&superset).count(), 0);
+    }
 
-    fn next(&mut self) -> Option<(K, V)> { self.iter.next() }
-    fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
-}
-impl<K, V> DoubleEndedIterator for BTreeMapRangeRemoveIter<K, V> {
-    fn next_back(&mut self) -> Option<(K, V)> { self.iter.next_back() }
-}
-impl<K, V> ExactSizeIterator for BTreeMapRangeRemoveIter<K, V> {
-    fn len(&self) -> usize { self.iter.len() }
-}
+    #[test]
+    fn test_remove_keys_of() {
+        let mut map: BTreeMap<u32, u32> = (0u32..10).map(|k| (k, k)).collect();
+        let tombstones: BTreeSet<u32> = vec![1u32, 3, 11, 20].into_iter().collect();
+        assert_eq!(map.remove_keys_of(&tombstones), 2);
+        assert_eq!(map.keys().cloned().collect::<Vec<u32>>(), vec![0u32, 2, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(map.remove_keys_of(&tombstones), 0);
+        let other: BTreeMap<u32, u32> = (0u32..20).map(|k| (k, k)).collect();
+        assert_eq!(map.remove_keys_of(other.keys()), 8);
+        assert!(map.is_empty());
+    }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::BTreeMap;
+    #[test]
+    fn test_remove_keys_of_few() {
+        let mut map: BTreeMap<u32, u32> = (0u32..1000).map(|k| (k, k)).collect();
+        let tombstones = vec![10u32, 500, 5000];
+        assert_eq!(map.remove_keys_of(&tombstones), 2);
+        assert_eq!(map.len(), 998);
+        assert!(!map.contains_key(&500));
+    }
 
-    use super::SortedMapExt;
+    #[test]
+    fn test_symmetric_difference_keys() {
+        use super::EitherKey::{Left, Right};
+        let map: BTreeMap<u32, u32> = vec![(1u32, 10u32), (2, 20), (4, 40)].into_iter().collect();
+        let other: BTreeMap<u32, &str> =
+            vec![(0u32, "a"), (2, "c"), (3, "d"), (5, "f")].into_iter().collect();
+        assert_eq!(map.symmetric_difference_keys(&other).collect::<Vec<_>>(),
+                   vec![Right(&0u32, &"a"), Left(&1u32, &10u32), Right(&3, &"d"), Left(&4, &40),
+                        Right(&5, &"f")]);
+    }
 
     #[test]
-    fn test_first() {
-        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.first().unwrap(), &1u32);
+    fn test_symmetric_difference_keys_identical_and_disjoint() {
+        use super::EitherKey::{Left, Right};
+        let map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        let same: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k * 10)).collect();
+        assert_eq!(map.symmetric_difference_keys(&same).count(), 0);
+        let disjoint: BTreeMap<u32, u32> = (6u32..8).map(|k| (k, k)).collect();
+        let all: Vec<_> = map.symmetric_difference_keys(&disjoint).collect();
+        assert_eq!(all.len(), 7);
+        assert_eq!(all[0], Left(&1u32, &1u32));
+        assert_eq!(all[6], Right(&7u32, &7u32));
     }
 
     #[test]
-    fn test_first_remove() {
-        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.first_remove().unwrap(), (1u32, 1u32));
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (3, 3), (4, 4), (5, 5)]);
+    fn test_is_submap() {
+        let map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        let empty: BTreeMap<u32, u32> = BTreeMap::new();
+        assert!(map.is_submap(&map.clone()));
+        assert!(empty.is_submap(&map));
+        assert!(empty.is_submap(&empty));
+        assert!(!map.is_submap(&empty));
+        let strict: BTreeMap<u32, u32> = vec![(2u32, 2u32), (5, 5)].into_iter().collect();
+        assert!(strict.is_submap(&map));
+        assert!(!map.is_submap(&strict));
+        let changed: BTreeMap<u32, u32> = vec![(2u32, 2u32), (5, 50)].into_iter().collect();
+        assert!(!changed.is_submap(&map));
+        let missing: BTreeMap<u32, u32> = vec![(2u32, 2u32), (7, 7)].into_iter().collect();
+        assert!(!missing.is_submap(&map));
     }
 
     #[test]
-    fn test_last() {
-        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.last().unwrap(), &5u32);
+    fn test_is_submap_by() {
+        let map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        let other: BTreeMap<u32, u32> = (0u32..10).map(|k| (k, k * 2)).collect();
+        let mut compared = 0;
+        assert!(map.is_submap_by(&other, |&a, &b| { compared += 1; a * 2 == b }));
+        assert_eq!(compared, 5);
+        let mut compared = 0;
+        assert!(!map.is_submap_by(&other, |a, b| { compared += 1; a == b }));
+        assert_eq!(compared, 1);
     }
 
     #[test]
-    fn test_last_remove() {
-        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.last_remove().unwrap(), (5u32, 5u32));
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4)]);
+    fn test_get_many_sorted_dense_probes() {
+        let map: BTreeMap<u32, u32> = vec![(2u32, 20u32), (5, 50), (7, 70)].into_iter().collect();
+        let probes: Vec<u32> = (0u32..10).collect();
+        let found: Vec<(u32, Option<u32>)> =
+            map.get_many_sorted(&probes).map(|(&k, v)| (k, v.cloned())).collect();
+        assert_eq!(found.len(), 10);
+        assert_eq!(found.iter().filter(|&&(_, v)| v.is_some()).count(), 3);
+        assert_eq!(found[2], (2u32, Some(20u32)));
+        assert_eq!(found[3], (3u32, None));
+        assert_eq!(found[7], (7u32, Some(70u32)));
+        let repeated = [5u32, 5];
+        assert_eq!(map.get_many_sorted(&repeated).filter(|&(_, v)| v == Some(&50)).count(), 2);
     }
 
     #[test]
-    fn test_ceiling() {
-        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.ceiling(&3).unwrap(), &3u32);
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)]);
+    fn test_get_many_sorted_sparse_probes() {
+        let map: BTreeMap<u32, u32> = (0u32..1000).map(|k| (k * 2, k)).collect();
+        let probes = [1u32, 500, 1998, 5000];
+        let found: Vec<(u32, Option<u32>)> =
+            map.get_many_sorted(&probes).map(|(&k, v)| (k, v.cloned())).collect();
+        assert_eq!(found, vec![(1u32, None), (500, Some(250u32)), (1998, Some(999)), (5000, None)]);
+        assert_eq!(BTreeMap::<u32, u32>::new().get_many_sorted(&probes).len(), 4);
     }
 
+    #[cfg(debug_assertions)]
     #[test]
-    fn test_ceiling_remove() {
-        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.ceiling_remove(&3).unwrap(), (3u32, 3u32));
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)]);
+    #[should_panic]
+    fn test_get_many_sorted_unsorted() {
+        let map: BTreeMap<u32, u32> = (0u32..10).map(|k| (k, k)).collect();
+        map.get_many_sorted(&[3u32, 1]).count();
     }
 
     #[test]
-    fn test_floor() {
-        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.floor(&3).unwrap(), &3u32);
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)]);
+    fn test_remove_many_sorted() {
+        let mut map: BTreeMap<u32, u32> = (0u32..10).map(|k| (k, k * 10)).collect();
+        let removed = map.remove_many_sorted(&[1u32, 2, 3, 3, 11, 12, 13, 14]);
+        assert_eq!(removed, vec![(1u32, 10u32), (2, 20), (3, 30)]);
+        assert_eq!(map.len(), 7);
+        assert_eq!(map.remove_many_sorted(&[]), vec![]);
+        assert_eq!(map.remove_many_sorted(&[1u32, 2]), vec![]);
+
+        let stored = Rc::new(5u32);
+        let mut map: BTreeMap<Rc<u32>, u32> = vec![(stored.clone(), 50u32)].into_iter().collect();
+        let removed = map.remove_many_sorted(&[Rc::new(5u32)]);
+        assert!(Rc::ptr_eq(&removed[0].0, &stored));
     }
 
     #[test]
-    fn test_floor_remove() {
-        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.floor_remove(&3).unwrap(), (3u32, 3u32));
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (4, 4), (5, 5)]);
+    fn test_insert_max() {
+        let mut map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(map.insert_max(5, 5), Ok(()));
+        assert_eq!(map.insert_max(7, 7), Ok(()));
+        assert_eq!(map.insert_max(7, 70), Err((7u32, 70u32)));
+        assert_eq!(map.insert_max(6, 6), Err((6u32, 6u32)));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(5u32, 5u32), (7, 7)]);
     }
 
     #[test]
-    fn test_higher() {
-        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.higher(&3).unwrap(), &4u32);
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)]);
+    fn test_insert_min() {
+        let mut map: BTreeMap<u32, u32> = BTreeMap::new();
+        assert_eq!(map.insert_min(5, 5), Ok(()));
+        assert_eq!(map.insert_min(3, 3), Ok(()));
+        assert_eq!(map.insert_min(3, 30), Err((3u32, 30u32)));
+        assert_eq!(map.insert_min(4, 4), Err((4u32, 4u32)));
+        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(3u32, 3u32), (5, 5)]);
     }
 
     #[test]
-    fn test_higher_remove() {
-        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.higher_remove(&3).unwrap(), (4u32, 4u32));
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (5, 5)]);
+    fn test_take_until_cumulative() {
+        let mut book: BTreeMap<u32, u32> =
+            vec![(100u32, 5u32), (101, 5), (102, 10)].into_iter().collect();
+        let (taken, left) = book.take_until_cumulative(10, |_, &qty| qty,
+                                                       |_, _, _| panic!("no split needed"));
+        assert_eq!(taken, vec![(100u32, 5u32), (101, 5)]);
+        assert_eq!(left, None);
+        let (taken, left) = book.take_until_cumulative(0, |_, &qty| qty,
+                                                       |_, _, _| panic!("no split needed"));
+        assert_eq!((taken, left), (vec![], None));
+        assert_eq!(book.len(), 1);
     }
 
     #[test]
-    fn test_lower() {
-        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.lower(&3).unwrap(), &2u32);
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)]);
+    fn test_take_until_cumulative_first_exceeds() {
+        let mut book: BTreeMap<u32, u32> = vec![(100u32, 50u32), (101, 5)].into_iter().collect();
+        let (taken, left) = book.take_until_cumulative(20, |_, &qty| qty,
+                                                       |_, qty, want| (want, qty - want));
+        assert_eq!(taken, vec![(100u32, 20u32)]);
+        assert_eq!(left, Some((&100u32, &30u32)));
+        assert_eq!(book.into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(100u32, 30u32), (101, 5)]);
     }
 
     #[test]
-    fn test_lower_remove() {
-        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.lower_remove(&3).unwrap(), (2u32, 2u32));
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(), vec![(1u32, 1u32), (3, 3), (4, 4), (5, 5)]);
+    fn test_take_until_cumulative_short() {
+        let mut book: BTreeMap<u32, u32> = vec![(100u32, 5u32), (101, 5)].into_iter().collect();
+        let (taken, left) = book.take_until_cumulative(100, |_, &qty| qty,
+                                                       |_, _, _| panic!("no split needed"));
+        assert_eq!(taken, vec![(100u32, 5u32), (101, 5)]);
+        assert_eq!(left, None);
+        assert!(book.is_empty());
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct CountedKey(u32);
+
+    thread_local!(static COMPARISONS: ::std::cell::Cell<usize> =
+        const { ::std::cell::Cell::new(0) });
+
+    // An `Ord` implementation which counts how many times it is called.
+    impl Ord for CountedKey {
+        fn cmp(&self, other: &CountedKey) -> ::std::cmp::Ordering {
+            COMPARISONS.with(|c| c.set(c.get() + 1));
+            self.0.cmp(&other.0)
+        }
+    }
+    impl PartialOrd for CountedKey {
+        fn partial_cmp(&self, other: &CountedKey) -> Option<::std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
     }
 
     #[test]
-    fn test_range_iter() {
-        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.range_iter(&2, &4).map(|(&k, &v)| (k, v)).collect::<Vec<(u32, u32)>>(),
-            vec![(2u32, 2u32), (3, 3)]);
+    fn test_first_last_do_not_scan() {
+        let mut map: BTreeMap<CountedKey, u32> =
+            (0u32..100000).map(|k| (CountedKey(k), k)).collect();
+        COMPARISONS.with(|c| c.set(0));
+        assert_eq!(map.first(), Some(&CountedKey(0)));
+        assert_eq!(map.last(), Some(&CountedKey(99999)));
+        assert_eq!(map.first_entry(), Some((&CountedKey(0), &0u32)));
+        assert_eq!(map.last_entry(), Some((&CountedKey(99999), &99999u32)));
+        assert_eq!(COMPARISONS.with(|c| c.get()), 0);
+        assert_eq!(map.first_remove(), Some((CountedKey(0), 0u32)));
+        assert_eq!(map.last_remove(), Some((CountedKey(99999), 99999u32)));
+        assert!(COMPARISONS.with(|c| c.get()) < 100);
+        assert_eq!(map.len(), 99998);
     }
 
     #[test]
-    fn test_range_iter_mut() {
-        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        for (_, v) in map.range_iter_mut(&2, &4) {
+    fn test_navigation_large_map() {
+        let map: BTreeMap<CountedKey, u32> =
+            (0u32..100000).map(|k| (CountedKey(k * 2), k)).collect();
+        COMPARISONS.with(|c| c.set(0));
+        assert_eq!(map.ceiling(&CountedKey(99999)), Some(&CountedKey(100000)));
+        assert_eq!(map.floor(&CountedKey(99999)), Some(&CountedKey(99998)));
+        assert_eq!(map.higher(&CountedKey(100000)), Some(&CountedKey(100002)));
+        assert_eq!(map.lower(&CountedKey(100000)), Some(&CountedKey(99998)));
+        assert_eq!(map.ceiling(&CountedKey(200000)), None);
+        assert_eq!(map.lower(&CountedKey(0)), None);
+        // Each lookup is a descent from the root, not a walk over the keys.
+        #[cfg(not(feature = "debug-validate"))]
+        assert!(COMPARISONS.with(|c| c.get()) < 1000);
+    }
+
+    #[cfg(feature = "unstable")]
+    #[bench]
+    fn bench_ceiling_large_map(b: &mut ::test::Bencher) {
+        let map: BTreeMap<u32, u32> = (0u32..100000).map(|k| (k * 2, k)).collect();
+        b.iter(|| map.ceiling(&99999).cloned());
+    }
+
+    // A value type which deliberately does not implement `Clone`.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Handle(u32);
+
+    #[test]
+    fn test_remove_without_clone_values() {
+        let mut map: BTreeMap<u32, Handle> = (1u32..10).map(|k| (k, Handle(k))).collect();
+        assert_eq!(map.floor(&0), None);
+        assert_eq!(map.ceiling_entry(&4), Some((&4u32, &Handle(4))));
+        assert_eq!(map.first_remove(), Some((1u32, Handle(1))));
+        assert_eq!(map.last_remove(), Some((9u32, Handle(9))));
+        assert_eq!(map.ceiling_remove(&3), Some((3u32, Handle(3))));
+        assert_eq!(map.floor_remove(&3), Some((2u32, Handle(2))));
+        assert_eq!(map.higher_remove(&4), Some((5u32, Handle(5))));
+        assert_eq!(map.lower_remove(&4), None);
+        assert_eq!(map.range_remove_iter(&6, &8).collect::<Vec<(u32, Handle)>>(),
+                   vec![(6u32, Handle(6)), (7, Handle(7))]);
+        assert_eq!(map.into_iter().collect::<Vec<(u32, Handle)>>(),
+                   vec![(4u32, Handle(4)), (8, Handle(8))]);
+    }
+
+    #[test]
+    fn test_range_remove_iter_moves_entries() {
+        let mut map: BTreeMap<u32, Handle> = (0u32..20).map(|k| (k, Handle(k))).collect();
+        let mut removed = map.range_remove_iter(&5, &15);
+        assert_eq!(removed.len(), 10);
+        assert_eq!(removed.next(), Some((5u32, Handle(5))));
+        assert_eq!(removed.next_back(), Some((14u32, Handle(14))));
+        assert_eq!(removed.len(), 8);
+        drop(removed);
+        assert_eq!(map.len(), 10);
+        assert!(map.keys().cloned().eq((0u32..5).chain(15..20)));
+        assert_eq!(map.get(&4), Some(&Handle(4)));
+        assert_eq!(map.get(&15), Some(&Handle(15)));
+        assert_eq!(map.range_remove_iter(&100, &200).len(), 0);
+        assert_eq!(map.range_remove_iter(&0, &100).len(), 10);
+        assert!(map.is_empty());
+    }
+
+    // A key type which deliberately does not implement `Clone`.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct UniqueKey(u32);
+
+    #[test]
+    fn test_navigation_without_clone() {
+        // Neither the key nor the value type is `Clone`, so this only compiles if navigation
+        // needs nothing more than `SortedMapNav`.
+        let mut map: BTreeMap<UniqueKey, Handle> =
+            (1u32..6).map(|k| (UniqueKey(k * 2), Handle(k))).collect();
+        assert_eq!(map.first(), Some(&UniqueKey(2)));
+        assert_eq!(map.last(), Some(&UniqueKey(10)));
+        assert_eq!(map.ceiling(&UniqueKey(5)), Some(&UniqueKey(6)));
+        assert_eq!(map.floor(&UniqueKey(5)), Some(&UniqueKey(4)));
+        assert_eq!(map.higher(&UniqueKey(6)), Some(&UniqueKey(8)));
+        assert_eq!(map.lower(&UniqueKey(2)), None);
+        assert_eq!(map.range_iter(&UniqueKey(4), &UniqueKey(8)).map(|(_, v)| v.0)
+                      .collect::<Vec<u32>>(), vec![2u32, 3]);
+        for (_, v) in map.range_iter_mut(&UniqueKey(4), &UniqueKey(8)) {
+            v.0 *= 10;
+        }
+        assert_eq!(map.get(&UniqueKey(6)), Some(&Handle(30)));
+    }
+
+    // Sums the values of `map` whose keys lie in [from_key, to_key), knowing nothing of the map
+    // but its `SortedMapExt` impl.
+    fn sum_range<M>(map: &M, from_key: &u32, to_key: &u32) -> u32 where M: SortedMapExt<u32, u32> {
+        map.range_iter(from_key, to_key).map(|(_, &v)| v).sum()
+    }
+
+    // Returns the greatest key of `map` in [from_key, to_key), reading the range backwards.
+    fn last_in_range<'a, M>(map: &'a M, from_key: &u32, to_key: &u32) -> Option<&'a u32>
+        where M: SortedMapExt<u32, u32>
+    {
+        let mut range: M::RangeIter<'a> = map.range_iter(from_key, to_key);
+        range.next_back().map(|(k, _)| k)
+    }
+
+    #[test]
+    fn test_generic_range_iter() {
+        let map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k * 10)).collect();
+        assert_eq!(sum_range(&map, &2, &5), 90);
+        assert_eq!(sum_range(&map, &5, &2), 0);
+        assert_eq!(last_in_range(&map, &2, &5), Some(&4u32));
+        assert_eq!(last_in_range(&map, &6, &9), None);
+    }
+
+    // A map backed by a sorted vector, which gets its navigation and removal methods only through
+    // its `NavigableMapCore` impl.
+    pub(crate) struct VecMap<K, V>(pub(crate) Vec<(K, V)>);
+
+    type VecMapIter<'a, K, V> = iter::Map<slice::Iter<'a, (K, V)>, fn(&(K, V)) -> (&K, &V)>;
+    type VecMapIterMut<'a, K, V> =
+        iter::Map<slice::IterMut<'a, (K, V)>, fn(&mut (K, V)) -> (&K, &mut V)>;
+
+    fn pair<K, V>(entry: &(K, V)) -> (&K, &V) {
+        (&entry.0, &entry.1)
+    }
+
+    fn pair_mut<K, V>(entry: &mut (K, V)) -> (&K, &mut V) {
+        (&entry.0, &mut entry.1)
+    }
+
+    impl<K: Ord, V> VecMap<K, V> {
+        // Returns the index of the first entry satisfying the lower bound `bound`.
+        fn lower_index<Q>(&self, bound: Bound<&Q>) -> usize where K: Borrow<Q>, Q: ?Sized + Ord {
+            match bound {
+                Included(key) => self.0.partition_point(|(k, _)| k.borrow() < key),
+                Excluded(key) => self.0.partition_point(|(k, _)| k.borrow() <= key),
+                Unbounded => 0
+            }
+        }
+
+        // Returns the index one past the last entry satisfying the upper bound `bound`.
+        fn upper_index<Q>(&self, bound: Bound<&Q>) -> usize where K: Borrow<Q>, Q: ?Sized + Ord {
+            match bound {
+                Included(key) => self.0.partition_point(|(k, _)| k.borrow() <= key),
+                Excluded(key) => self.0.partition_point(|(k, _)| k.borrow() < key),
+                Unbounded => self.0.len()
+            }
+        }
+    }
+
+    impl<K, V> NavigableMapCore<K, V> for VecMap<K, V>
+        where K: Ord + DebugKey
+    {
+        type Iter<'a> = VecMapIter<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+        type IterMut<'a> = VecMapIterMut<'a, K, V> where Self: 'a, K: 'a, V: 'a;
+
+        fn seek_ge<Q>(&self, bound: Bound<&Q>) -> Option<&K>
+            where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+        {
+            self.0.get(self.lower_index(bound)).map(|(k, _)| k)
+        }
+
+        fn seek_le<Q>(&self, bound: Bound<&Q>) -> Option<&K>
+            where K: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+        {
+            self.upper_index(bound).checked_sub(1).map(|i| &self.0[i].0)
+        }
+
+        fn remove_key(&mut self, key: &K) -> Option<(K, V)> {
+            self.0.binary_search_by(|(k, _)| k.cmp(key)).ok().map(|i| self.0.remove(i))
+        }
+
+        fn iter_from_bound(&self, min: Bound<&K>, max: Bound<&K>) -> VecMapIter<'_, K, V> {
+            let lo = self.lower_index(min);
+            let hi = cmp::max(lo, self.upper_index(max));
+            self.0[lo..hi].iter().map(pair as fn(&(K, V)) -> (&K, &V))
+        }
+
+        fn iter_mut_from_bound(&mut self, min: Bound<&K>, max: Bound<&K>)
+                               -> VecMapIterMut<'_, K, V> {
+            let lo = self.lower_index(min);
+            let hi = cmp::max(lo, self.upper_index(max));
+            self.0[lo..hi].iter_mut().map(pair_mut as fn(&mut (K, V)) -> (&K, &mut V))
+        }
+    }
+
+    #[test]
+    fn test_navigable_map_core_backend() {
+        let mut map = VecMap(vec![(1u32, 10u32), (3, 30), (5, 50), (7, 70), (9, 90)]);
+        assert_eq!(map.first(), Some(&1u32));
+        assert_eq!(map.last(), Some(&9u32));
+        assert_eq!(map.ceiling(&4), Some(&5u32));
+        assert_eq!(map.floor(&4), Some(&3u32));
+        assert_eq!(map.higher(&5), Some(&7u32));
+        assert_eq!(map.lower(&5), Some(&3u32));
+        assert_eq!(map.higher(&9), None);
+        assert_eq!(map.range_iter(&3, &7).map(|(&k, _)| k).collect::<Vec<u32>>(), vec![3u32, 5]);
+        assert_eq!(map.range_iter(&7, &3).count(), 0);
+        for (_, v) in map.range_iter_mut_inclusive(&3, &7) {
             *v += 1;
         }
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-            vec![(1u32, 1u32), (2, 3), (3, 4), (4, 4), (5, 5)]);
+        assert_eq!(map.ceiling_remove(&4), Some((5u32, 51u32)));
+        assert_eq!(map.lower_remove(&3), Some((1u32, 10u32)));
+        assert_eq!(map.last_remove(), Some((9u32, 90u32)));
+        assert_eq!(map.floor_remove(&0), None);
+        assert_eq!(map.range_remove_iter(&3, &8).collect::<Vec<(u32, u32)>>(),
+            vec![(3u32, 31u32), (7, 71)]);
+        assert!(map.0.is_empty());
     }
 
     #[test]
-    fn test_range_remove_iter() {
-        let mut map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (3, 3), (4, 4), (5, 5)].into_iter().collect();
-        assert_eq!(map.range_remove_iter(&2, &4).collect::<Vec<(u32, u32)>>(), vec![(2u32, 2u32), (3, 3)]);
-        assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
-            vec![(1u32, 1u32), (4, 4), (5, 5)]);
+    fn test_remove_range_matches_default() {
+        let pairs: Vec<(u32, u32)> = (0u32..10).map(|k| (k * 2, k)).collect();
+        let mins = [Included(&6), Excluded(&6), Included(&7), Unbounded];
+        let maxes = [Included(&12), Excluded(&12), Included(&13), Unbounded];
+        for &min in mins.iter() {
+            for &max in maxes.iter() {
+                let mut tree: BTreeMap<u32, u32> = pairs.iter().cloned().collect();
+                let mut vec = VecMap(pairs.clone());
+                assert_eq!(tree.remove_range(min, max), vec.remove_range(min, max));
+                assert_eq!(tree.into_iter().collect::<Vec<(u32, u32)>>(), vec.0);
+            }
+        }
+    }
+
+    // Drains `iter`, checks that it keeps returning `None` afterwards and returns how many items
+    // it yielded.
+    fn drain_fused<I: FusedIterator>(mut iter: I) -> usize {
+        let count = iter.by_ref().count();
+        for _ in 0..3 {
+            assert!(iter.next().is_none());
+        }
+        count
+    }
+
+    #[test]
+    fn test_range_iters_are_fused() {
+        let mut map: BTreeMap<u32, u32> = (1u32..6).map(|k| (k, k)).collect();
+        assert_eq!(drain_fused(map.range_iter(&2, &4)), 2);
+        assert_eq!(drain_fused(map.range_iter_mut(&2, &4)), 2);
+        assert_eq!(drain_fused(map.range_iter_desc(&2, &4)), 2);
+        assert_eq!(drain_fused(map.range_iter_desc_mut(&2, &4)), 2);
+        assert_eq!(drain_fused(map.descending_iter()), 5);
+        assert_eq!(drain_fused(map.descending_iter_mut()), 5);
+        assert_eq!(drain_fused(map.keys_range(&2, &4)), 2);
+        assert_eq!(drain_fused(map.values_range(&2, &4)), 2);
+        assert_eq!(drain_fused(map.values_range_mut(&2, &4)), 2);
+        assert_eq!(drain_fused(map.sorted_by_value_iter()), 5);
+        assert_eq!(drain_fused(map.clone().into_keys_range(&2, &4)), 2);
+        assert_eq!(drain_fused(map.clone().into_values_range(&2, &4)), 2);
+        assert_eq!(drain_fused(map.range_remove_iter(&2, &4)), 2);
+        assert_eq!(drain_fused(map.drain_sorted()), 3);
+    }
+
+    #[test]
+    fn test_adaptor_iters_are_fused() {
+        let map: BTreeMap<u32, u32> = vec![(1u32, 1u32), (2, 2), (4, 4), (7, 7)].into_iter().collect();
+        let other: BTreeMap<u32, u32> = vec![(2u32, 20u32), (3, 30), (7, 70)].into_iter().collect();
+        assert_eq!(drain_fused(map.rolling_range_iter(1)), 4);
+        assert_eq!(drain_fused(map.chunk_range_by_weight(&1, &8, 2, |_, _| 1)), 2);
+        assert_eq!(drain_fused(map.first_run_by(|&k| k < 3)), 2);
+        assert_eq!(drain_fused(map.join_iter(&other)), 2);
+        assert_eq!(drain_fused(map.left_join_iter(&other)), 4);
+        assert_eq!(drain_fused(map.symmetric_difference_keys(&other)), 3);
+        assert_eq!(drain_fused(map.get_many_sorted(&[2, 3, 9])), 3);
+        assert_eq!(drain_fused(map.pairs_iter()), 3);
+        assert_eq!(drain_fused(map.gaps()), 2);
+    }
+
+    #[test]
+    fn test_clone_range_iter() {
+        let map: BTreeMap<u32, u32> = (1u32..8).map(|k| (k, k * 10)).collect();
+        let mut iter = map.range_iter(&2, &6);
+        assert_eq!(iter.next(), Some((&2u32, &20u32)));
+        let copy = iter.clone();
+        assert_eq!(iter.next(), Some((&3u32, &30u32)));
+        assert_eq!(iter.next_back(), Some((&5u32, &50u32)));
+        assert_eq!(copy.map(|(&k, _)| k).collect::<Vec<u32>>(), vec![3u32, 4, 5]);
+        assert_eq!(iter.map(|(&k, _)| k).collect::<Vec<u32>>(), vec![4u32]);
+
+        let mut keys = map.keys_range(&2, &6);
+        keys.next_back();
+        let copy = keys.clone();
+        assert_eq!(keys.next(), Some(&2u32));
+        assert_eq!(copy.cloned().collect::<Vec<u32>>(), vec![2u32, 3, 4]);
+        assert_eq!(keys.cloned().collect::<Vec<u32>>(), vec![3u32, 4]);
+
+        let mut desc = map.range_iter_desc(&2, &6);
+        desc.next();
+        let copy = desc.clone();
+        assert_eq!(desc.map(|(&k, _)| k).collect::<Vec<u32>>(), vec![4u32, 3, 2]);
+        assert_eq!(copy.map(|(&k, _)| k).collect::<Vec<u32>>(), vec![4u32, 3, 2]);
+    }
+
+    #[test]
+    fn test_range_iter_debug() {
+        let mut map: BTreeMap<u32, u32> = (1u32..5).map(|k| (k, k * 10)).collect();
+        let debug = format!("{:?}", map.range_iter(&2, &4));
+        assert!(debug.starts_with("BTreeMapRangeIter"));
+        assert!(debug.contains("(2, 20)") && debug.contains("(3, 30)") && !debug.contains("(4, 40)"));
+        let debug = format!("{:?}", map.range_iter_mut(&2, &4));
+        assert!(debug.starts_with("BTreeMapRangeIterMut"));
+        assert!(debug.contains("(3, 30)"));
+        assert!(format!("{:?}", map.range_remove_iter(&1, &2)).contains("(1, 10)"));
     }
 }
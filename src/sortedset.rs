@@ -4,20 +4,33 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::collections::Bound::{Included, Excluded};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::btree_map::BTreeMap;
 use std::collections::btree_set::{BTreeSet, self};
+use std::iter;
+use std::ops::Bound::{Included, Excluded, Unbounded};
+use std::vec;
+
+use crate::{DebugKey, OverlapError};
+use crate::distance::{KeyDistance, KeyStep};
 
 /// An extension trait for a `Set` whose elements have a defined total ordering.
 /// This trait provides convenience methods which take advantage of the set's ordering.
+///
+/// The navigation methods (`ceiling`, `floor`, `higher`, `lower` and their variants) accept any
+/// borrowed form of the element type, as `BTreeSet::contains` does.
 pub trait SortedSetExt<T>
-    where T: Clone + Ord
+    where T: Clone + Ord + DebugKey
 {
     /// An iterator over immutable references to this set's elements within a given range.
-    type RangeIter;
+    type RangeIter<'a>: DoubleEndedIterator<Item=&'a T>
+        where Self: 'a, T: 'a;
 
     /// A by-value iterator yielding elements within a given range which have just been removed
     /// from this set.
-    type RangeRemoveIter;
+    type RangeRemoveIter: DoubleEndedIterator<Item=T>;
 
     /// Returns an immutable reference to the first (least) element currently in this set.
     /// Returns `None` if this set is empty.
@@ -25,8 +38,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -43,8 +54,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -62,8 +71,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -80,8 +87,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -99,8 +104,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -109,7 +112,7 @@ pub trait SortedSetExt<T>
     ///     assert_eq!(set.ceiling(&3).unwrap(), &3u32);
     /// }
     /// ```
-    fn ceiling(&self, elem: &T) -> Option<&T>;
+    fn ceiling<Q>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
     /// Removes and returns the least element in this set greater than or equal to `elem`.
     /// Returns `None` if there is no such element.
@@ -117,8 +120,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -128,7 +129,8 @@ pub trait SortedSetExt<T>
     ///     assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![1u32, 2, 4, 5]);
     /// }
     /// ```
-    fn ceiling_remove(&mut self, elem: &T) -> Option<T>;
+    fn ceiling_remove<Q>(&mut self, elem: &Q) -> Option<T>
+        where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
     /// Returns an immutable reference to the greatest element in this set less than or equal to `elem`.
     /// Returns `None` if there is no such element.
@@ -136,8 +138,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -146,7 +146,7 @@ pub trait SortedSetExt<T>
     ///     assert_eq!(set.floor(&3).unwrap(), &3u32);
     /// }
     /// ```
-    fn floor(&self, elem: &T) -> Option<&T>;
+    fn floor<Q>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
     /// Removes and returns the greatest element in this set less than or equal to `elem`.
     /// Returns `None` if there is no such element.
@@ -154,8 +154,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -165,7 +163,8 @@ pub trait SortedSetExt<T>
     ///     assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![1u32, 2, 4, 5]);
     /// }
     /// ```
-    fn floor_remove(&mut self, elem: &T) -> Option<T>;
+    fn floor_remove<Q>(&mut self, elem: &Q) -> Option<T>
+        where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
     /// Returns an immutable reference to the least element in this set strictly greater than `elem`.
     /// Returns `None` if there is no such element.
@@ -173,8 +172,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -183,7 +180,7 @@ pub trait SortedSetExt<T>
     ///     assert_eq!(set.higher(&3).unwrap(), &4u32);
     /// }
     /// ```
-    fn higher(&self, elem: &T) -> Option<&T>;
+    fn higher<Q>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
     /// Removes and returns the least element in this set strictly greater than `elem`.
     /// Returns `None` if there is no such element.
@@ -191,8 +188,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -202,7 +197,8 @@ pub trait SortedSetExt<T>
     ///     assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![1u32, 2, 3, 5]);
     /// }
     /// ```
-    fn higher_remove(&mut self, elem: &T) -> Option<T>;
+    fn higher_remove<Q>(&mut self, elem: &Q) -> Option<T>
+        where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
     /// Returns an immutable reference to the greatest element in this set strictly less than `elem`.
     /// Returns `None` if there is no such element.
@@ -210,8 +206,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -220,7 +214,7 @@ pub trait SortedSetExt<T>
     ///     assert_eq!(set.lower(&3).unwrap(), &2u32);
     /// }
     /// ```
-    fn lower(&self, elem: &T) -> Option<&T>;
+    fn lower<Q>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
     /// Removes and returns the greatest element in this set strictly less than `elem`.
     /// Returns `None` if there is no such element.
@@ -228,8 +222,6 @@ pub trait SortedSetExt<T>
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -239,16 +231,16 @@ pub trait SortedSetExt<T>
     ///     assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![1u32, 3, 4, 5]);
     /// }
     /// ```
-    fn lower_remove(&mut self, elem: &T) -> Option<T>;
+    fn lower_remove<Q>(&mut self, elem: &Q) -> Option<T>
+        where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey;
 
     /// Returns an iterator over immutable references to the elements
-    /// of this set in the range [from_elem, to_elem).
+    /// of this set in the range [from_elem, to_elem). The iterator is empty if `from_elem`
+    /// is not less than `to_elem`.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -257,16 +249,15 @@ pub trait SortedSetExt<T>
     ///     assert_eq!(set.range_iter(&2, &4).map(|&x| x).collect::<Vec<u32>>(), vec![2u32, 3]);
     /// }
     /// ```
-    fn range_iter(&self, from_elem: &T, to_elem: &T) -> Self::RangeIter;
+    fn range_iter(&self, from_elem: &T, to_elem: &T) -> Self::RangeIter<'_>;
 
     /// Removes the elements of this set in the range [from_elem, to_elem), and returns
-    /// a by-value iterator over the removed elements.
+    /// a by-value iterator over the removed elements. Nothing is removed if `from_elem`
+    /// is not less than `to_elem`.
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate "sorted-collections" as sorted_collections;
-    ///
     /// use std::collections::BTreeSet;
     /// use sorted_collections::SortedSetExt;
     ///
@@ -277,149 +268,952 @@ pub trait SortedSetExt<T>
     /// }
     /// ```
     fn range_remove_iter(&mut self, from_elem: &T, to_elem: &T) -> Self::RangeRemoveIter;
-}
-
-// A generic reusable impl of SortedSetExt.
-macro_rules! sortedset_impl {
-    ($typ:ty) => (
-        fn first(&self) -> Option<&T> {
-            self.iter().min()
-        }
-
-        fn first_remove(&mut self) -> Option<T> {
-            if let Some(ret) = self.first().cloned() {
-                assert!(self.remove(&ret));
-                Some(ret)
-            } else {
-                None
-            }
-        }
-
-        fn last(&self) -> Option<&T> {
-            self.iter().max()
-        }
-
-        fn last_remove(&mut self) -> Option<T> {
-            if let Some(ret) = self.last().cloned() {
-                assert!(self.remove(&ret));
-                Some(ret)
-            } else {
-                None
-            }
-        }
 
-        fn ceiling(&self, elem: &T) -> Option<&T> {
-            self.range_iter(elem, self.last().unwrap()).min()
-        }
+    /// Counts the elements of this set falling into each of the buckets described by `edges`.
+    /// Bucket `i` is the range `[edges[i], edges[i + 1])`, so an element lying exactly on an
+    /// interior edge is counted in the bucket to its right, and elements outside
+    /// `[edges[0], edges[n])` are ignored. The counts are computed in a single ordered pass over
+    /// the covered range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `edges` has fewer than two elements or is not strictly ascending.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert_eq!(set.histogram(&[0, 2, 4, 5]), vec![1, 2, 1]);
+    /// }
+    /// ```
+    fn histogram(&self, edges: &[T]) -> Vec<usize>;
 
-        fn ceiling_remove(&mut self, elem: &T) -> Option<T> {
-            if let Some(ceiling) = self.ceiling(elem).cloned() {
-                assert!(self.remove(&ceiling));
-                Some(ceiling)
-            } else {
-                None
-            }
-        }
+    /// Returns the pair of adjacent elements in this set with the largest distance between them.
+    /// If several pairs share the largest distance, the least pair is returned.
+    /// Returns `None` if this set has fewer than two elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 6, 7, 9].into_iter().collect();
+    ///     assert_eq!(set.max_gap().unwrap(), (&2u32, &6u32));
+    /// }
+    /// ```
+    fn max_gap(&self) -> Option<(&T, &T)> where T: KeyDistance;
 
-        fn floor(&self, elem: &T) -> Option<&T> {
-            self.iter().filter(|&x| x <= elem).max()
-        }
+    /// Returns the pair of adjacent elements in the range [from_elem, to_elem) of this set with
+    /// the largest distance between them. If several pairs share the largest distance, the least
+    /// pair is returned. Returns `None` if the range holds fewer than two elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 6, 7, 9].into_iter().collect();
+    ///     assert_eq!(set.max_gap_in_range(&6, &10).unwrap(), (&7u32, &9u32));
+    /// }
+    /// ```
+    fn max_gap_in_range(&self, from_elem: &T, to_elem: &T) -> Option<(&T, &T)> where T: KeyDistance;
 
-        fn floor_remove(&mut self, elem: &T) -> Option<T> {
-            if let Some(floor) = self.floor(elem).cloned() {
-                assert!(self.remove(&floor));
-                Some(floor)
-            } else {
-                None
-            }
-        }
+    /// Returns `true` if every value between the first and last elements of this set is present.
+    /// An empty set is considered contiguous.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert!(set.is_contiguous());
+    /// }
+    /// ```
+    fn is_contiguous(&self) -> bool where T: KeyStep;
 
-        fn higher(&self, elem: &T) -> Option<&T> {
-            self.iter().filter(|&x| x > elem).min()
-        }
+    /// Returns `true` if every value in the range [from_elem, to_elem) is present in this set.
+    /// An empty range is considered contiguous.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 5, 6].into_iter().collect();
+    ///     assert!(set.is_contiguous_in_range(&1, &4));
+    ///     assert!(!set.is_contiguous_in_range(&1, &6));
+    /// }
+    /// ```
+    fn is_contiguous_in_range(&self, from_elem: &T, to_elem: &T) -> bool where T: KeyStep;
 
-        fn higher_remove(&mut self, elem: &T) -> Option<T> {
-            if let Some(higher) = self.higher(elem).cloned() {
-                assert!(self.remove(&higher));
-                Some(higher)
-            } else {
-                None
-            }
-        }
+    /// Returns the first element in this set for which `pred` returns `false`, or `None` if
+    /// `pred` holds for every element. `pred` must be monotone over the set's ordering, i.e. it
+    /// must return `true` for some prefix of the elements and `false` for the rest; if it does
+    /// not, it is unspecified which of the elements at which it flips is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert_eq!(set.partition_point_elem(|&x| x * x < 10).unwrap(), &4u32);
+    /// }
+    /// ```
+    fn partition_point_elem<F>(&self, pred: F) -> Option<&T> where F: FnMut(&T) -> bool;
 
-        fn lower(&self, elem: &T) -> Option<&T> {
-            self.range_iter(self.first().unwrap(), elem).max()
-        }
+    /// Removes and returns the first (least) element currently in this set, but only if `pred`
+    /// returns `true` for it. `pred` is called at most once.
+    /// Returns `None` if this set is empty or `pred` returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let mut set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert_eq!(set.first_remove_if(|&x| x > 1), None);
+    ///     assert_eq!(set.first_remove_if(|&x| x == 1).unwrap(), 1u32);
+    /// }
+    /// ```
+    fn first_remove_if<F>(&mut self, pred: F) -> Option<T> where F: FnOnce(&T) -> bool;
 
-        fn lower_remove(&mut self, elem: &T) -> Option<T> {
-            if let Some(lower) = self.lower(elem).cloned() {
-                assert!(self.remove(&lower));
-                Some(lower)
-            } else {
-                None
-            }
-        }
-    );
-}
+    /// Removes and returns the last (greatest) element currently in this set, but only if `pred`
+    /// returns `true` for it. `pred` is called at most once.
+    /// Returns `None` if this set is empty or `pred` returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let mut set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert_eq!(set.last_remove_if(|&x| x < 5), None);
+    ///     assert_eq!(set.last_remove_if(|&x| x == 5).unwrap(), 5u32);
+    /// }
+    /// ```
+    fn last_remove_if<F>(&mut self, pred: F) -> Option<T> where F: FnOnce(&T) -> bool;
 
-// An impl of SortedSetExt for the standard library BTreeSet
-impl<'a, T> SortedSetExt<T> for BTreeSet<T>
-    where T: Clone + Ord
-{
-    type RangeIter = BTreeSetRangeIter<'a, T>;
-    type RangeRemoveIter = BTreeSetRangeRemoveIter<T>;
+    /// Moves all elements of `other` into this set, provided that every element of one set is
+    /// less than every element of the other. Otherwise, returns an error naming an element of one
+    /// set that lies within the span of the other, and leaves both sets untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let mut set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     let mut other: BTreeSet<u32> = vec![6u32, 7].into_iter().collect();
+    ///     assert!(set.append_assert_disjoint(&mut other).is_ok());
+    ///     assert_eq!(set.len(), 7);
+    ///     assert!(other.is_empty());
+    /// }
+    /// ```
+    fn append_assert_disjoint(&mut self, other: &mut BTreeSet<T>) -> Result<(), OverlapError<T>>;
 
-    sortedset_impl!(BTreeSet<T>);
+    /// Returns an iterator over immutable references to at most the first `n` elements of this
+    /// set in the range [from_elem, to_elem), in ascending order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert_eq!(set.first_n_in_range(&2, &5, 2).map(|&x| x).collect::<Vec<u32>>(), vec![2u32, 3]);
+    /// }
+    /// ```
+    fn first_n_in_range(&self, from_elem: &T, to_elem: &T, n: usize)
+                        -> iter::Take<Self::RangeIter<'_>>;
 
-    fn range_iter(&self, from_elem: &T, to_elem: &T) -> BTreeSetRangeIter<T> {
-        BTreeSetRangeIter { iter: self.range(Included(from_elem), Excluded(to_elem)) }
-    }
+    /// Returns an iterator over immutable references to at most the last `n` elements of this
+    /// set in the range [from_elem, to_elem), in ascending order.
+    /// The range is walked backwards and at most `n` elements are buffered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert_eq!(set.last_n_in_range(&2, &5, 2).map(|&x| x).collect::<Vec<u32>>(), vec![3u32, 4]);
+    /// }
+    /// ```
+    fn last_n_in_range(&self, from_elem: &T, to_elem: &T, n: usize) -> iter::Rev<vec::IntoIter<&T>>;
 
-    fn range_remove_iter(&mut self, from_elem: &T, to_elem: &T) -> BTreeSetRangeRemoveIter<T> {
-        let ret: BTreeSet<T> = self.range_iter(from_elem, to_elem).cloned().collect();
-        for elem in ret.iter() {
-            assert!(self.remove(elem));
-        }
-        BTreeSetRangeRemoveIter { iter: ret.into_iter() }
-    }
-}
+    /// Returns a page of immutable references to at most `n` elements of this set strictly
+    /// greater than `cursor` (or the first `n` elements if `cursor` is `None`), in ascending
+    /// order. The page's `next_cursor` is the last element on the page if any elements remain
+    /// after it, and `None` otherwise. Since the cursor is excluded by value rather than by
+    /// position, removing it from this set between calls neither skips nor repeats any elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     let page = set.page_after(None, 3);
+    ///     assert_eq!(page.elems, vec![&1u32, &2, &3]);
+    ///     let page = set.page_after(page.next_cursor.as_ref(), 3);
+    ///     assert_eq!(page.elems, vec![&4u32, &5]);
+    ///     assert_eq!(page.next_cursor, None);
+    /// }
+    /// ```
+    fn page_after(&self, cursor: Option<&T>, n: usize) -> Page<'_, T>;
 
-pub struct BTreeSetRangeIter<'a, T: 'a> {
-    iter: btree_set::Range<'a, T>
-}
+    /// Returns a page of immutable references to at most `n` elements of this set strictly less
+    /// than `cursor` (or the last `n` elements if `cursor` is `None`), in descending order. The
+    /// page's `next_cursor` is the last element on the page if any elements remain after it, and
+    /// `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     let page = set.page_before(None, 3);
+    ///     assert_eq!(page.elems, vec![&5u32, &4, &3]);
+    ///     let page = set.page_before(page.next_cursor.as_ref(), 3);
+    ///     assert_eq!(page.elems, vec![&2u32, &1]);
+    ///     assert_eq!(page.next_cursor, None);
+    /// }
+    /// ```
+    fn page_before(&self, cursor: Option<&T>, n: usize) -> Page<'_, T>;
 
-impl<'a, T> Iterator for BTreeSetRangeIter<'a, T> {
-    type Item = &'a T;
+    /// Consumes this set and splits it in two: a set of the elements for which `pred` returns
+    /// `true` and a set of those for which it returns `false`. Elements are moved, not cloned,
+    /// and both sets are built in order in O(n).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     let (odd, even) = set.binary_partition(|&x| x % 2 == 1);
+    ///     assert_eq!(odd.into_iter().collect::<Vec<u32>>(), vec![1u32, 3, 5]);
+    ///     assert_eq!(even.into_iter().collect::<Vec<u32>>(), vec![2u32, 4]);
+    /// }
+    /// ```
+    fn binary_partition<F>(self, pred: F) -> (Self, Self)
+        where Self: Sized, F: FnMut(&T) -> bool;
 
-    fn next(&mut self) -> Option<&'a T> { self.iter.next() }
-    fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
-}
-impl<'a, T> DoubleEndedIterator for BTreeSetRangeIter<'a, T> {
-    fn next_back(&mut self) -> Option<&'a T> { self.iter.next_back() }
-}
+    /// Returns a map whose keys are the elements of this set and whose values are computed from
+    /// them by `f`, which is called once per element in ascending order. The map is built in
+    /// order in O(n).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     let map = set.elements_to_map(|&x| x * 10);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///                vec![(1u32, 10u32), (2, 20), (3, 30), (4, 40), (5, 50)]);
+    /// }
+    /// ```
+    fn elements_to_map<V, F>(&self, f: F) -> BTreeMap<T, V> where F: FnMut(&T) -> V;
 
-pub struct BTreeSetRangeRemoveIter<T> {
-    iter: btree_set::IntoIter<T>
+    /// Consumes this set and returns a map whose keys are its elements and whose values are
+    /// computed from them by `f`, which is called once per element in ascending order. The
+    /// elements are moved, not cloned, and the map is built in order in O(n).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     let map = set.into_map(|&x| x * 10);
+    ///     assert_eq!(map.into_iter().collect::<Vec<(u32, u32)>>(),
+    ///                vec![(1u32, 10u32), (2, 20), (3, 30), (4, 40), (5, 50)]);
+    /// }
+    /// ```
+    fn into_map<V, F>(self, f: F) -> BTreeMap<T, V> where F: FnMut(&T) -> V;
+
+    /// Returns clones of at most `limit` elements of this set in the range [from_elem, to_elem),
+    /// in ascending order, along with whether any more elements lie in the range beyond them. The
+    /// range is not walked any further than the element after the last one returned. Returns an
+    /// empty vector and `false` if `from_elem` is not less than `to_elem`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert_eq!(set.collect_range_truncated(&2, &5, 2), (vec![2u32, 3], true));
+    ///     assert_eq!(set.collect_range_truncated(&2, &5, 3), (vec![2u32, 3, 4], false));
+    /// }
+    /// ```
+    fn collect_range_truncated(&self, from_elem: &T, to_elem: &T, limit: usize) -> (Vec<T>, bool);
+
+    /// Returns, for each element of `probes`, whether it is present in this set. `probes` must be
+    /// sorted in ascending order, and may contain duplicates. The lookups are done in a single
+    /// ordered walk over this set unless there are few enough probes that looking each one up is
+    /// cheaper.
+    ///
+    /// In debug builds, panics if `probes` is not sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert_eq!(set.contains_each_sorted(&[0, 2, 2, 6]), vec![false, true, true, false]);
+    /// }
+    /// ```
+    fn contains_each_sorted(&self, probes: &[T]) -> Vec<bool>;
+
+    /// Writes, for each element of `probes`, whether it is present in this set into the
+    /// corresponding element of `out`. This behaves like `contains_each_sorted`, except that it
+    /// does not allocate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` is not the same length as `probes`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     let mut out = [false; 4];
+    ///     set.contains_each_sorted_into(&[0, 2, 2, 6], &mut out);
+    ///     assert_eq!(out, [false, true, true, false]);
+    /// }
+    /// ```
+    fn contains_each_sorted_into(&self, probes: &[T], out: &mut [bool]);
+
+    /// Returns immutable references to the `k` elements of this set closest to `probe` by the
+    /// distance `dist(probe, elem)`, in order of increasing distance, with ties going to the
+    /// lesser element. `dist` must not decrease moving away from `probe` in either direction.
+    /// Returns every element if there are no more than `k` of them. The set is walked outwards
+    /// from `probe` in both directions at once, so this takes O(log n + k) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     let nearest = set.k_nearest_by(&3, 3, |&a, &b| if a < b { b - a } else { a - b });
+    ///     assert_eq!(nearest, vec![&3u32, &2, &4]);
+    /// }
+    /// ```
+    fn k_nearest_by<D, F>(&self, probe: &T, k: usize, dist: F) -> Vec<&T>
+        where D: Ord, F: Fn(&T, &T) -> D;
+
+    /// Returns `true` if `pred` returns `true` for any element of this set in the range
+    /// [from_elem, to_elem), stopping at the first such element. Returns `false` if `from_elem` is
+    /// not less than `to_elem`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert!(set.range_any(&2, &5, |&x| x == 4));
+    ///     assert!(!set.range_any(&2, &5, |&x| x == 5));
+    /// }
+    /// ```
+    fn range_any<F>(&self, from_elem: &T, to_elem: &T, pred: F) -> bool where F: FnMut(&T) -> bool;
+
+    /// Returns `true` if `pred` returns `true` for every element of this set in the range
+    /// [from_elem, to_elem), stopping at the first element for which it does not. Returns `true`
+    /// if `from_elem` is not less than `to_elem`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     assert!(set.range_all(&2, &5, |&x| x < 5));
+    ///     assert!(!set.range_all(&2, &5, |&x| x < 4));
+    /// }
+    /// ```
+    fn range_all<F>(&self, from_elem: &T, to_elem: &T, pred: F) -> bool where F: FnMut(&T) -> bool;
+
+    /// Calls `f` on each element of this set in the range [from_elem, to_elem), in ascending
+    /// order, stopping at and returning the first error it returns. Returns `Ok(())` without
+    /// calling `f` if `from_elem` is not less than `to_elem`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use sorted_collections::SortedSetExt;
+    ///
+    /// fn main() {
+    ///     let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+    ///     let mut seen = Vec::new();
+    ///     let res = set.try_for_each_range(&2, &5, |&x| {
+    ///         if x == 4 { return Err(x); }
+    ///         seen.push(x);
+    ///         Ok(())
+    ///     });
+    ///     assert_eq!(res, Err(4));
+    ///     assert_eq!(seen, vec![2u32, 3]);
+    /// }
+    /// ```
+    fn try_for_each_range<E, F>(&self, from_elem: &T, to_elem: &T, f: F) -> Result<(), E>
+        where F: FnMut(&T) -> Result<(), E>;
 }
 
-impl<T> Iterator for BTreeSetRangeRemoveIter<T> {
-    type Item = T;
+// A generic reusable impl of SortedSetExt.
+macro_rules! sortedset_impl {
+    ($typ:ty) => (
+        fn first(&self) -> Option<&T> {
+            self.iter().next()
+        }
+
+        fn first_remove(&mut self) -> Option<T> {
+            if let Some(ret) = self.first().cloned() {
+                assert!(self.remove(&ret));
+                Some(ret)
+            } else {
+                None
+            }
+        }
+
+        fn last(&self) -> Option<&T> {
+            self.iter().next_back()
+        }
+
+        fn last_remove(&mut self) -> Option<T> {
+            if let Some(ret) = self.last().cloned() {
+                assert!(self.remove(&ret));
+                Some(ret)
+            } else {
+                None
+            }
+        }
+
+        fn ceiling_remove<Q>(&mut self, elem: &Q) -> Option<T>
+            where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+        {
+            if let Some(ceiling) = self.ceiling(elem).cloned() {
+                assert!(self.remove::<T>(&ceiling));
+                Some(ceiling)
+            } else {
+                None
+            }
+        }
+
+        fn floor_remove<Q>(&mut self, elem: &Q) -> Option<T>
+            where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+        {
+            if let Some(floor) = self.floor(elem).cloned() {
+                assert!(self.remove::<T>(&floor));
+                Some(floor)
+            } else {
+                None
+            }
+        }
+
+        fn higher_remove<Q>(&mut self, elem: &Q) -> Option<T>
+            where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+        {
+            if let Some(higher) = self.higher(elem).cloned() {
+                assert!(self.remove::<T>(&higher));
+                Some(higher)
+            } else {
+                None
+            }
+        }
+
+        fn lower_remove<Q>(&mut self, elem: &Q) -> Option<T>
+            where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey
+        {
+            if let Some(lower) = self.lower(elem).cloned() {
+                assert!(self.remove::<T>(&lower));
+                Some(lower)
+            } else {
+                None
+            }
+        }
+
+        fn histogram(&self, edges: &[T]) -> Vec<usize> {
+            crate::check_histogram_edges(edges);
+            let mut counts = vec![0; edges.len() - 1];
+            let mut bucket = 0;
+            for elem in self.range_iter(&edges[0], &edges[edges.len() - 1]) {
+                while *elem >= edges[bucket + 1] {
+                    bucket += 1;
+                }
+                counts[bucket] += 1;
+            }
+            counts
+        }
+
+        fn max_gap(&self) -> Option<(&T, &T)> where T: KeyDistance {
+            crate::max_adjacent_gap(self.iter())
+        }
+
+        fn max_gap_in_range(&self, from_elem: &T, to_elem: &T) -> Option<(&T, &T)>
+            where T: KeyDistance
+        {
+            crate::max_adjacent_gap(self.range_iter(from_elem, to_elem))
+        }
+
+        fn is_contiguous(&self) -> bool where T: KeyStep {
+            match (self.first(), self.last()) {
+                (Some(first), Some(last)) => first.steps_between(last) == Some(self.len() - 1),
+                _ => true,
+            }
+        }
+
+        fn is_contiguous_in_range(&self, from_elem: &T, to_elem: &T) -> bool where T: KeyStep {
+            crate::covers_range(self.range_iter(from_elem, to_elem), from_elem, to_elem)
+        }
+
+        fn partition_point_elem<F>(&self, mut pred: F) -> Option<&T> where F: FnMut(&T) -> bool {
+            self.iter().find(|&x| !pred(x))
+        }
+
+        fn first_n_in_range(&self, from_elem: &T, to_elem: &T, n: usize)
+                            -> iter::Take<Self::RangeIter<'_>> {
+            self.range_iter(from_elem, to_elem).take(n)
+        }
+
+        fn last_n_in_range(&self, from_elem: &T, to_elem: &T, n: usize) -> iter::Rev<vec::IntoIter<&T>> {
+            let last_n: Vec<&T> = self.range_iter(from_elem, to_elem).rev().take(n).collect();
+            last_n.into_iter().rev()
+        }
+
+        fn collect_range_truncated(&self, from_elem: &T, to_elem: &T, limit: usize)
+                                   -> (Vec<T>, bool) {
+            if from_elem >= to_elem {
+                return (Vec::new(), false);
+            }
+            let mut range = self.range_iter(from_elem, to_elem);
+            let elems = range.by_ref().take(limit).cloned().collect();
+            (elems, range.next().is_some())
+        }
+
+        fn contains_each_sorted(&self, probes: &[T]) -> Vec<bool> {
+            let mut out = vec![false; probes.len()];
+            self.contains_each_sorted_into(probes, &mut out);
+            out
+        }
+
+        fn contains_each_sorted_into(&self, probes: &[T], out: &mut [bool]) {
+            assert_eq!(probes.len(), out.len());
+            debug_assert!(probes.windows(2).all(|pair| pair[0] <= pair[1]),
+                          "contains_each_sorted requires sorted probes");
+            if crate::probing_is_cheaper(probes.len(), self.len()) {
+                for (probe, found) in probes.iter().zip(out.iter_mut()) {
+                    *found = self.contains(probe);
+                }
+            } else {
+                let mut elems = self.iter().peekable();
+                for (probe, found) in probes.iter().zip(out.iter_mut()) {
+                    while elems.peek().map_or(false, |&elem| elem < probe) {
+                        elems.next();
+                    }
+                    *found = elems.peek() == Some(&probe);
+                }
+            }
+        }
+
+        fn range_any<F>(&self, from_elem: &T, to_elem: &T, pred: F) -> bool
+            where F: FnMut(&T) -> bool
+        {
+            from_elem < to_elem && self.range_iter(from_elem, to_elem).any(pred)
+        }
 
-    fn next(&mut self) -> Option<T> { self.iter.next() }
-    fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
+        fn range_all<F>(&self, from_elem: &T, to_elem: &T, pred: F) -> bool
+            where F: FnMut(&T) -> bool
+        {
+            from_elem >= to_elem || self.range_iter(from_elem, to_elem).all(pred)
+        }
+
+        fn try_for_each_range<E, F>(&self, from_elem: &T, to_elem: &T, mut f: F) -> Result<(), E>
+            where F: FnMut(&T) -> Result<(), E>
+        {
+            if from_elem < to_elem {
+                for elem in self.range_iter(from_elem, to_elem) {
+                    f(elem)?;
+                }
+            }
+            Ok(())
+        }
+    );
 }
-impl<T> DoubleEndedIterator for BTreeSetRangeRemoveIter<T> {
-    fn next_back(&mut self) -> Option<T> { self.iter.next_back() }
+
+// An impl of SortedSetExt for the standard library BTreeSet
+impl<T> SortedSetExt<T> for BTreeSet<T>
+    where T: Clone + Ord + DebugKey
+{
+    type RangeIter<'a> = BTreeSetRangeIter<'a, T> where Self: 'a, T: 'a;
+    type RangeRemoveIter = BTreeSetRangeRemoveIter<T>;
+
+    sortedset_impl!(BTreeSet<T>);
+
+    fn ceiling<Q>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey {
+        let ceiling = self.range((Included(elem), Unbounded)).next();
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("ceiling", elem, ceiling,
+                              self.iter().filter(|&x| x.borrow() >= elem).min());
+        ceiling
+    }
+
+    fn floor<Q>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey {
+        let floor = self.range((Unbounded, Included(elem))).next_back();
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("floor", elem, floor,
+                              self.iter().filter(|&x| x.borrow() <= elem).max());
+        floor
+    }
+
+    fn higher<Q>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey {
+        let higher = self.range((Excluded(elem), Unbounded)).next();
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("higher", elem, higher,
+                              self.iter().filter(|&x| x.borrow() > elem).min());
+        higher
+    }
+
+    fn lower<Q>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, Q: ?Sized + Ord + DebugKey {
+        let lower = self.range((Unbounded, Excluded(elem))).next_back();
+        #[cfg(feature = "debug-validate")]
+        crate::validate_navigation("lower", elem, lower,
+                              self.iter().filter(|&x| x.borrow() < elem).max());
+        lower
+    }
+
+    fn range_iter(&self, from_elem: &T, to_elem: &T) -> BTreeSetRangeIter<'_, T> {
+        let to_elem = if from_elem > to_elem { from_elem } else { to_elem };
+        BTreeSetRangeIter { iter: self.range((Included(from_elem), Excluded(to_elem))) }
+    }
+
+    fn range_remove_iter(&mut self, from_elem: &T, to_elem: &T) -> BTreeSetRangeRemoveIter<T> {
+        if from_elem >= to_elem {
+            return BTreeSetRangeRemoveIter { iter: BTreeSet::new().into_iter() };
+        }
+        let ret: BTreeSet<T> = self.range_iter(from_elem, to_elem).cloned().collect();
+        for elem in ret.iter() {
+            assert!(self.remove(elem));
+        }
+        BTreeSetRangeRemoveIter { iter: ret.into_iter() }
+    }
+
+    fn first_remove_if<F>(&mut self, pred: F) -> Option<T> where F: FnOnce(&T) -> bool {
+        let matched = match self.iter().next() {
+            Some(elem) => pred(elem),
+            None => false,
+        };
+        if matched { self.pop_first() } else { None }
+    }
+
+    fn last_remove_if<F>(&mut self, pred: F) -> Option<T> where F: FnOnce(&T) -> bool {
+        let matched = match self.iter().next_back() {
+            Some(elem) => pred(elem),
+            None => false,
+        };
+        if matched { self.pop_last() } else { None }
+    }
+
+    fn append_assert_disjoint(&mut self, other: &mut BTreeSet<T>) -> Result<(), OverlapError<T>> {
+        if let (Some(self_first), Some(self_last)) = (self.iter().next(), self.iter().next_back()) {
+            if let (Some(other_first), Some(other_last)) =
+                    (other.iter().next(), other.iter().next_back()) {
+                if other_first <= self_last && self_first <= other_last {
+                    let elem = if self_first < other_first { other_first } else { self_first };
+                    return Err(OverlapError { key: elem.clone() });
+                }
+            }
+        }
+        self.append(other);
+        Ok(())
+    }
+
+    fn page_after(&self, cursor: Option<&T>, n: usize) -> Page<'_, T> {
+        match cursor {
+            Some(cursor) => Page::take(self.range((Excluded(cursor), Unbounded)), n),
+            None => Page::take(self.iter(), n),
+        }
+    }
+
+    fn page_before(&self, cursor: Option<&T>, n: usize) -> Page<'_, T> {
+        match cursor {
+            Some(cursor) => Page::take(self.range((Unbounded, Excluded(cursor))).rev(), n),
+            None => Page::take(self.iter().rev(), n),
+        }
+    }
+
+    fn binary_partition<F>(self, mut pred: F) -> (BTreeSet<T>, BTreeSet<T>)
+        where F: FnMut(&T) -> bool
+    {
+        let (matching, rest): (Vec<T>, Vec<T>) = self.into_iter().partition(|x| pred(x));
+        (matching.into_iter().collect(), rest.into_iter().collect())
+    }
+
+    fn elements_to_map<V, F>(&self, mut f: F) -> BTreeMap<T, V> where F: FnMut(&T) -> V {
+        self.iter().map(|elem| (elem.clone(), f(elem))).collect()
+    }
+
+    fn into_map<V, F>(self, mut f: F) -> BTreeMap<T, V> where F: FnMut(&T) -> V {
+        self.into_iter().map(|elem| {
+            let val = f(&elem);
+            (elem, val)
+        }).collect()
+    }
+
+    fn k_nearest_by<D, F>(&self, probe: &T, k: usize, dist: F) -> Vec<&T>
+        where D: Ord, F: Fn(&T, &T) -> D
+    {
+        let mut below = self.range((Unbounded, Excluded(probe))).rev().peekable();
+        let mut above = self.range((Included(probe), Unbounded)).peekable();
+        let mut nearest = Vec::new();
+        while nearest.len() < k {
+            let take_below = match (below.peek(), above.peek()) {
+                (Some(&lo), Some(&hi)) => dist(probe, lo) <= dist(probe, hi),
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            nearest.push(if take_below { below.next() } else { above.next() }.unwrap());
+        }
+        nearest
+    }
+}
+
+/// Returns the number of distinct elements in the union of `sets`, without building the union.
+/// The sets are merged through a heap in O(total · log sets).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use sorted_collections::sortedset::union_count;
+///
+/// fn main() {
+///     let a: BTreeSet<u32> = vec![1u32, 2, 3].into_iter().collect();
+///     let b: BTreeSet<u32> = vec![2u32, 3, 4].into_iter().collect();
+///     assert_eq!(union_count(&[&a, &b]), 4);
+/// }
+/// ```
+pub fn union_count<T: Ord>(sets: &[&BTreeSet<T>]) -> usize {
+    union_iter(sets).count()
+}
+
+/// Returns an iterator over immutable references to the distinct elements in the union of
+/// `sets`, in ascending order, without building the union. Where several sets hold equal
+/// elements, the one from the earliest set is yielded. The sets are merged through a heap in
+/// O(total · log sets).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use sorted_collections::sortedset::union_iter;
+///
+/// fn main() {
+///     let a: BTreeSet<u32> = vec![1u32, 3, 5].into_iter().collect();
+///     let b: BTreeSet<u32> = vec![2u32, 3, 4].into_iter().collect();
+///     assert_eq!(union_iter(&[&a, &b]).collect::<Vec<&u32>>(), vec![&1u32, &2, &3, &4, &5]);
+/// }
+/// ```
+pub fn union_iter<'a, T: Ord>(sets: &[&'a BTreeSet<T>]) -> UnionIter<'a, T> {
+    let mut iters: Vec<btree_set::Iter<T>> = sets.iter().map(|set| set.iter()).collect();
+    let mut heap = BinaryHeap::with_capacity(iters.len());
+    for (source, iter) in iters.iter_mut().enumerate() {
+        if let Some(elem) = iter.next() {
+            heap.push(UnionHead { elem, source });
+        }
+    }
+    UnionIter { iters, heap }
+}
+
+/// Returns the number of elements common to every one of `sets`, or zero if there are none.
+/// Only the smallest set is walked, looking each of its elements up in the others, so this
+/// returns immediately if any of the sets is empty.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use sorted_collections::sortedset::intersection_count;
+///
+/// fn main() {
+///     let a: BTreeSet<u32> = vec![1u32, 2, 3].into_iter().collect();
+///     let b: BTreeSet<u32> = vec![2u32, 3, 4].into_iter().collect();
+///     assert_eq!(intersection_count(&[&a, &b]), 2);
+/// }
+/// ```
+pub fn intersection_count<T: Ord>(sets: &[&BTreeSet<T>]) -> usize {
+    let mut sets: Vec<&BTreeSet<T>> = sets.to_vec();
+    sets.sort_by_key(|a| a.len());
+    match sets.split_first() {
+        Some((smallest, rest)) => {
+            smallest.iter().filter(|&elem| rest.iter().all(|set| set.contains(elem))).count()
+        }
+        None => 0,
+    }
+}
+
+#[derive(Debug)]
+pub struct BTreeSetRangeIter<'a, T: 'a> {
+    iter: btree_set::Range<'a, T>
+}
+
+wrapper_iterator!(impl<'a, T> BTreeSetRangeIter<'a, T> => &'a T);
+impl<'a, T> Clone for BTreeSetRangeIter<'a, T> {
+    fn clone(&self) -> BTreeSetRangeIter<'a, T> {
+        BTreeSetRangeIter { iter: self.iter.clone() }
+    }
 }
+
+#[derive(Debug)]
+pub struct BTreeSetRangeRemoveIter<T> {
+    iter: btree_set::IntoIter<T>
+}
+
+wrapper_iterator!(impl<T> BTreeSetRangeRemoveIter<T> => T);
 impl<T> ExactSizeIterator for BTreeSetRangeRemoveIter<T> {
     fn len(&self) -> usize { self.iter.len() }
 }
 
+/// An iterator over the distinct elements in the union of several sets. See `union_iter`.
+pub struct UnionIter<'a, T: 'a> {
+    iters: Vec<btree_set::Iter<'a, T>>,
+    heap: BinaryHeap<UnionHead<'a, T>>
+}
+
+impl<'a, T: Ord> UnionIter<'a, T> {
+    // Replaces the head of set `source`, which has just been popped off the heap, with its next
+    // element.
+    fn advance(&mut self, source: usize) {
+        if let Some(elem) = self.iters[source].next() {
+            self.heap.push(UnionHead { elem, source });
+        }
+    }
+}
+
+impl<'a, T: Ord> Iterator for UnionIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let UnionHead { elem, source } = self.heap.pop()?;
+        self.advance(source);
+        while self.heap.peek().is_some_and(|head| head.elem == elem) {
+            let source = self.heap.pop().unwrap().source;
+            self.advance(source);
+        }
+        Some(elem)
+    }
+}
+impl<'a, T: Ord> iter::FusedIterator for UnionIter<'a, T> {}
+
+// The head of one set in `union_iter`, ordered so that `BinaryHeap` pops the smallest element
+// first and, among equal elements, the one from the earliest set.
+struct UnionHead<'a, T: 'a> {
+    elem: &'a T,
+    source: usize
+}
+
+impl<'a, T: Ord> Ord for UnionHead<'a, T> {
+    fn cmp(&self, other: &UnionHead<'a, T>) -> Ordering {
+        match other.elem.cmp(self.elem) {
+            Ordering::Equal => other.source.cmp(&self.source),
+            ord => ord,
+        }
+    }
+}
+impl<'a, T: Ord> PartialOrd for UnionHead<'a, T> {
+    fn partial_cmp(&self, other: &UnionHead<'a, T>) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+impl<'a, T: Ord> PartialEq for UnionHead<'a, T> {
+    fn eq(&self, other: &UnionHead<'a, T>) -> bool { self.cmp(other) == Ordering::Equal }
+}
+impl<'a, T: Ord> Eq for UnionHead<'a, T> {}
+
+/// A page of set elements, as returned by `SortedSetExt::page_after` and
+/// `SortedSetExt::page_before`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<'a, T: 'a> {
+    /// The elements on this page, in the order they were paged through.
+    pub elems: Vec<&'a T>,
+    /// The cursor to pass to fetch the following page, or `None` if this is the final page.
+    /// A page of size zero is always final.
+    pub next_cursor: Option<T>
+}
+
+impl<'a, T: Clone> Page<'a, T> {
+    fn take<I>(mut iter: I, n: usize) -> Page<'a, T> where I: Iterator<Item = &'a T> {
+        let elems: Vec<&T> = iter.by_ref().take(n).collect();
+        let next_cursor = match (elems.last(), iter.next()) {
+            (Some(&last), Some(_)) => Some(last.clone()),
+            _ => None,
+        };
+        Page { elems, next_cursor }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeSet;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::iter::FusedIterator;
+    use std::rc::Rc;
 
-    use super::SortedSetExt;
+    use crate::OverlapError;
+    use super::{intersection_count, union_count, union_iter, SortedSetExt};
 
     #[test]
     fn test_first() {
@@ -506,13 +1300,391 @@ mod tests {
     #[test]
     fn test_range_iter() {
         let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
-        assert_eq!(set.range_iter(&2, &4).map(|&x| x).collect::<Vec<u32>>(), vec![2u32, 3]);
+        assert_eq!(set.range_iter(&2, &4).copied().collect::<Vec<u32>>(), vec![2u32, 3]);
+        assert_eq!(set.range_iter(&4, &2).count(), 0);
     }
 
     #[test]
     fn test_range_remove_iter() {
         let mut set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
         assert_eq!(set.range_remove_iter(&2, &4).collect::<Vec<u32>>(), vec![2u32, 3]);
+        assert_eq!(set.range_remove_iter(&5, &1).count(), 0);
         assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![1u32, 4, 5]);
     }
+
+    #[test]
+    fn test_histogram() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        assert_eq!(set.histogram(&[2, 4, 5]), vec![2, 1]);
+        assert_eq!(set.histogram(&[0, 1, 3, 10]), vec![0, 2, 3]);
+        assert_eq!(set.histogram(&[6, 7]), vec![0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_histogram_unsorted_edges() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        set.histogram(&[3, 2]);
+    }
+
+    #[test]
+    fn test_max_gap() {
+        let set: BTreeSet<u32> = vec![1u32, 100, 101, 102, 201].into_iter().collect();
+        assert_eq!(set.max_gap().unwrap(), (&1u32, &100u32));
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 200].into_iter().collect();
+        assert_eq!(set.max_gap().unwrap(), (&4u32, &200u32));
+        let set: BTreeSet<u32> = vec![1u32, 5, 9].into_iter().collect();
+        assert_eq!(set.max_gap().unwrap(), (&1u32, &5u32));
+        let set: BTreeSet<u32> = vec![1u32].into_iter().collect();
+        assert_eq!(set.max_gap(), None);
+    }
+
+    #[test]
+    fn test_max_gap_in_range() {
+        let set: BTreeSet<i32> = vec![-50i32, 1, 2, 3, 40].into_iter().collect();
+        assert_eq!(set.max_gap_in_range(&1, &40).unwrap(), (&1i32, &2i32));
+        assert_eq!(set.max_gap_in_range(&-50, &2).unwrap(), (&-50i32, &1i32));
+        assert_eq!(set.max_gap_in_range(&3, &40), None);
+        assert_eq!(set.max_gap_in_range(&40, &-50), None);
+    }
+
+    #[test]
+    fn test_is_contiguous() {
+        let set: BTreeSet<i32> = vec![-2i32, -1, 0, 1, 2].into_iter().collect();
+        assert!(set.is_contiguous());
+        let set: BTreeSet<i32> = vec![-2i32, -1, 1, 2].into_iter().collect();
+        assert!(!set.is_contiguous());
+        let set: BTreeSet<i32> = BTreeSet::new();
+        assert!(set.is_contiguous());
+    }
+
+    #[test]
+    fn test_is_contiguous_in_range() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 4, 5].into_iter().collect();
+        assert!(set.is_contiguous_in_range(&1, &3));
+        assert!(!set.is_contiguous_in_range(&1, &4));
+        assert!(!set.is_contiguous_in_range(&3, &6));
+        assert!(set.is_contiguous_in_range(&4, &6));
+        assert!(set.is_contiguous_in_range(&3, &3));
+        assert!(set.is_contiguous_in_range(&5, &1));
+    }
+
+    #[test]
+    fn test_partition_point_elem() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        let elems: Vec<u32> = set.iter().cloned().collect();
+        for bound in 0u32..7 {
+            let expected = elems.iter().position(|&x| x >= bound).map(|i| &elems[i]);
+            assert_eq!(set.partition_point_elem(|&x| x < bound), expected);
+        }
+    }
+
+    #[test]
+    fn test_first_remove_if() {
+        let mut set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        let mut calls = 0;
+        assert_eq!(set.first_remove_if(|_| { calls += 1; false }), None);
+        assert_eq!(calls, 1);
+        assert_eq!(set.first_remove_if(|&x| x == 1), Some(1u32));
+        assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![2u32, 3, 4, 5]);
+        let mut set: BTreeSet<u32> = BTreeSet::new();
+        assert_eq!(set.first_remove_if(|_| panic!("called on an empty set")), None);
+    }
+
+    #[test]
+    fn test_last_remove_if() {
+        let mut set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        let mut calls = 0;
+        assert_eq!(set.last_remove_if(|_| { calls += 1; false }), None);
+        assert_eq!(calls, 1);
+        assert_eq!(set.last_remove_if(|&x| x == 5), Some(5u32));
+        assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![1u32, 2, 3, 4]);
+        let mut set: BTreeSet<u32> = BTreeSet::new();
+        assert_eq!(set.last_remove_if(|_| panic!("called on an empty set")), None);
+    }
+
+    #[test]
+    fn test_append_assert_disjoint() {
+        let mut set: BTreeSet<u32> = vec![1u32, 2].into_iter().collect();
+        let mut other: BTreeSet<u32> = vec![3u32, 4].into_iter().collect();
+        assert_eq!(set.append_assert_disjoint(&mut other), Ok(()));
+        assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![1u32, 2, 3, 4]);
+        assert!(other.is_empty());
+
+        let mut set: BTreeSet<u32> = vec![3u32, 4].into_iter().collect();
+        let mut other: BTreeSet<u32> = vec![1u32, 2].into_iter().collect();
+        assert_eq!(set.append_assert_disjoint(&mut other), Ok(()));
+        assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![1u32, 2, 3, 4]);
+
+        let mut set: BTreeSet<u32> = vec![1u32, 2].into_iter().collect();
+        let mut other: BTreeSet<u32> = vec![2u32, 3].into_iter().collect();
+        assert_eq!(set.append_assert_disjoint(&mut other), Err(OverlapError { key: 2u32 }));
+        assert_eq!(set.into_iter().collect::<Vec<u32>>(), vec![1u32, 2]);
+        assert_eq!(other.into_iter().collect::<Vec<u32>>(), vec![2u32, 3]);
+
+        let mut set: BTreeSet<u32> = BTreeSet::new();
+        let mut other: BTreeSet<u32> = vec![1u32].into_iter().collect();
+        assert_eq!(set.append_assert_disjoint(&mut other), Ok(()));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_first_n_in_range() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        assert_eq!(set.first_n_in_range(&2, &5, 2).copied().collect::<Vec<u32>>(), vec![2u32, 3]);
+        assert_eq!(set.first_n_in_range(&2, &5, 10).copied().collect::<Vec<u32>>(), vec![2u32, 3, 4]);
+        assert_eq!(set.first_n_in_range(&2, &5, 0).count(), 0);
+        assert_eq!(set.first_n_in_range(&5, &2, 2).count(), 0);
+    }
+
+    #[test]
+    fn test_last_n_in_range() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        assert_eq!(set.last_n_in_range(&2, &5, 2).copied().collect::<Vec<u32>>(), vec![3u32, 4]);
+        assert_eq!(set.last_n_in_range(&2, &5, 10).copied().collect::<Vec<u32>>(), vec![2u32, 3, 4]);
+        assert_eq!(set.last_n_in_range(&2, &5, 3).copied().collect::<Vec<u32>>(), vec![2u32, 3, 4]);
+        assert_eq!(set.last_n_in_range(&2, &5, 0).count(), 0);
+        assert_eq!(set.last_n_in_range(&5, &2, 2).count(), 0);
+    }
+
+    #[test]
+    fn test_page_after() {
+        let mut set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        let page = set.page_after(None, 2);
+        assert_eq!(page.elems, vec![&1u32, &2]);
+        assert_eq!(page.next_cursor, Some(2u32));
+        let cursor = page.next_cursor.unwrap();
+        set.remove(&cursor);
+        let page = set.page_after(Some(&cursor), 2);
+        assert_eq!(page.elems, vec![&3u32, &4]);
+        assert_eq!(page.next_cursor, Some(4u32));
+        let page = set.page_after(Some(&4), 2);
+        assert_eq!(page.elems, vec![&5u32]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_page_before() {
+        let mut set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        let page = set.page_before(None, 2);
+        assert_eq!(page.elems, vec![&5u32, &4]);
+        assert_eq!(page.next_cursor, Some(4u32));
+        let cursor = page.next_cursor.unwrap();
+        set.remove(&cursor);
+        let page = set.page_before(Some(&cursor), 2);
+        assert_eq!(page.elems, vec![&3u32, &2]);
+        let page = set.page_before(Some(&2), 2);
+        assert_eq!(page.elems, vec![&1u32]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_binary_partition() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        let (all, none) = set.clone().binary_partition(|_| true);
+        assert_eq!(all, set);
+        assert!(none.is_empty());
+        let (none, all) = set.clone().binary_partition(|_| false);
+        assert!(none.is_empty());
+        assert_eq!(all, set);
+        let (odd, even) = set.binary_partition(|&x| x % 2 == 1);
+        assert_eq!(odd.into_iter().collect::<Vec<u32>>(), vec![1u32, 3, 5]);
+        assert_eq!(even.into_iter().collect::<Vec<u32>>(), vec![2u32, 4]);
+    }
+
+    #[test]
+    fn test_elements_to_map() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        let mut seen = Vec::new();
+        let map = set.elements_to_map(|&x| { seen.push(x); x * 10 });
+        assert_eq!(seen, vec![1u32, 2, 3, 4, 5]);
+        let naive: BTreeMap<u32, u32> = set.iter().cloned().map(|x| (x, x * 10)).collect();
+        assert_eq!(map, naive);
+        assert_eq!(set.len(), 5);
+        assert!(BTreeSet::<u32>::new().elements_to_map(|&x| x).is_empty());
+    }
+
+    #[test]
+    fn test_into_map() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        let naive: BTreeMap<u32, u32> = set.iter().cloned().map(|x| (x, x * 10)).collect();
+        assert_eq!(set.into_map(|&x| x * 10), naive);
+        let witnesses: Vec<Rc<u32>> = (1u32..6).map(Rc::new).collect();
+        let set: BTreeSet<Rc<u32>> = witnesses.iter().cloned().collect();
+        let map = set.into_map(|x| **x);
+        for (witness, (key, &val)) in witnesses.iter().zip(map.iter()) {
+            assert!(Rc::ptr_eq(witness, key));
+            assert_eq!(**witness, val);
+            assert_eq!(Rc::strong_count(witness), 2);
+        }
+    }
+
+    #[test]
+    fn test_collect_range_truncated() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        assert_eq!(set.collect_range_truncated(&2, &5, 3), (vec![2u32, 3, 4], false));
+        assert_eq!(set.collect_range_truncated(&2, &5, 2), (vec![2u32, 3], true));
+        assert_eq!(set.collect_range_truncated(&2, &5, 0), (vec![], true));
+        assert_eq!(set.collect_range_truncated(&6, &9, 0), (vec![], false));
+        assert_eq!(set.collect_range_truncated(&5, &2, 3), (vec![], false));
+    }
+
+    #[test]
+    fn test_contains_each_sorted() {
+        let set: BTreeSet<u32> = (0u32..50).map(|x| x * 2 + 10).collect();
+        assert_eq!(set.contains_each_sorted(&[0, 1, 2]), vec![false, false, false]);
+        assert_eq!(set.contains_each_sorted(&[200, 201]), vec![false, false]);
+        let probes: Vec<u32> = (0u32..120).collect();
+        let expected: Vec<bool> = probes.iter().map(|p| set.contains(p)).collect();
+        assert_eq!(set.contains_each_sorted(&probes), expected);
+        let mut out = [false; 4];
+        set.contains_each_sorted_into(&[12, 12, 13, 13], &mut out);
+        assert_eq!(out, [true, true, false, false]);
+    }
+
+    #[test]
+    fn test_union_iter() {
+        let a: BTreeSet<u32> = vec![1u32, 4, 7, 10].into_iter().collect();
+        let b: BTreeSet<u32> = vec![2u32, 4, 8, 10].into_iter().collect();
+        let c: BTreeSet<u32> = vec![3u32, 4, 9].into_iter().collect();
+        let empty: BTreeSet<u32> = BTreeSet::new();
+        assert_eq!(union_iter(&[&a, &empty, &b, &c]).cloned().collect::<Vec<u32>>(),
+                   vec![1u32, 2, 3, 4, 7, 8, 9, 10]);
+        assert_eq!(union_iter(&[&a, &a, &a]).cloned().collect::<Vec<u32>>(), vec![1u32, 4, 7, 10]);
+        assert_eq!(union_iter::<u32>(&[]).next(), None);
+        assert_eq!(union_iter(&[&empty]).next(), None);
+        let x: BTreeSet<Rc<u32>> = vec![Rc::new(1u32)].into_iter().collect();
+        let y: BTreeSet<Rc<u32>> = vec![Rc::new(1u32)].into_iter().collect();
+        let first = union_iter(&[&x, &y]).next().unwrap();
+        assert!(Rc::ptr_eq(first, x.iter().next().unwrap()));
+    }
+
+    #[test]
+    fn test_union_count() {
+        let a: BTreeSet<u32> = (0u32..100).collect();
+        let b: BTreeSet<u32> = (100u32..150).collect();
+        let c: BTreeSet<u32> = (150u32..160).collect();
+        let empty: BTreeSet<u32> = BTreeSet::new();
+        assert_eq!(union_count(&[&a, &b, &c]), 160);
+        assert_eq!(union_count(&[&a, &a, &a]), 100);
+        assert_eq!(union_count(&[&empty, &c, &empty]), 10);
+        assert_eq!(union_count::<u32>(&[]), 0);
+    }
+
+    #[test]
+    fn test_intersection_count() {
+        let a: BTreeSet<u32> = (0u32..100).collect();
+        let b: BTreeSet<u32> = (50u32..150).collect();
+        let c: BTreeSet<u32> = (0u32..200).filter(|x| x % 2 == 0).collect();
+        let empty: BTreeSet<u32> = BTreeSet::new();
+        assert_eq!(intersection_count(&[&a, &b, &c]), 25);
+        assert_eq!(intersection_count(&[&a, &a]), 100);
+        assert_eq!(intersection_count(&[&a, &empty, &b]), 0);
+        assert_eq!(intersection_count(&[&(0u32..10).collect(), &(10u32..20).collect()]), 0);
+        assert_eq!(intersection_count(&[&b]), 100);
+        assert_eq!(intersection_count::<u32>(&[]), 0);
+    }
+
+    #[test]
+    fn test_k_nearest_by() {
+        let set: BTreeSet<i32> = vec![1i32, 4, 6, 10, 20].into_iter().collect();
+        let dist = |a: &i32, b: &i32| (a - b).abs();
+        assert_eq!(set.k_nearest_by(&5, 3, dist), vec![&4i32, &6, &1]);
+        assert_eq!(set.k_nearest_by(&0, 2, dist), vec![&1i32, &4]);
+        assert_eq!(set.k_nearest_by(&25, 2, dist), vec![&20i32, &10]);
+        assert_eq!(set.k_nearest_by(&6, 2, dist), vec![&6i32, &4]);
+        assert_eq!(set.k_nearest_by(&5, 0, dist), Vec::<&i32>::new());
+        assert_eq!(set.k_nearest_by(&8, 10, dist), vec![&6i32, &10, &4, &1, &20]);
+        assert_eq!(BTreeSet::<i32>::new().k_nearest_by(&8, 10, dist), Vec::<&i32>::new());
+    }
+
+    #[test]
+    fn test_range_any() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        assert!(set.range_any(&2, &5, |&x| x == 2));
+        assert!(set.range_any(&2, &5, |&x| x == 4));
+        assert!(!set.range_any(&2, &5, |&x| x == 5));
+        assert!(!set.range_any(&4, &2, |_| true));
+    }
+
+    #[test]
+    fn test_range_all() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        assert!(!set.range_all(&2, &5, |&x| x != 2));
+        assert!(!set.range_all(&2, &5, |&x| x != 4));
+        assert!(set.range_all(&2, &5, |&x| x != 5));
+        assert!(set.range_all(&4, &2, |_| false));
+    }
+
+    #[test]
+    fn test_try_for_each_range() {
+        let set: BTreeSet<u32> = vec![1u32, 2, 3, 4, 5].into_iter().collect();
+        let mut seen = Vec::new();
+        assert_eq!(set.try_for_each_range(&2, &5, |&x| { seen.push(x); Err(x) }), Err(2u32));
+        assert_eq!(seen, vec![2u32]);
+        seen.clear();
+        let res = set.try_for_each_range(&2, &5, |&x| {
+            seen.push(x);
+            if x == 4 { Err(x) } else { Ok(()) }
+        });
+        assert_eq!(res, Err(4u32));
+        assert_eq!(seen, vec![2u32, 3, 4]);
+        assert_eq!(set.try_for_each_range(&5, &2, |&x| Err(x)), Ok(()));
+    }
+
+    #[test]
+    fn test_navigation_borrowed_str() {
+        let mut set: BTreeSet<String> =
+            vec!["b", "d", "f"].into_iter().map(|s| s.to_string()).collect();
+        assert_eq!(set.ceiling("c").map(|s| &s[..]), Some("d"));
+        assert_eq!(set.floor("c").map(|s| &s[..]), Some("b"));
+        assert_eq!(set.higher("f"), None);
+        assert_eq!(set.lower("b"), None);
+        assert_eq!(set.ceiling("f").map(|s| &s[..]), Some("f"));
+        assert_eq!(set.lower_remove("e"), Some("d".to_string()));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_navigation_borrowed_slice() {
+        let mut set: BTreeSet<Vec<u8>> =
+            vec![vec![1u8, 2], vec![1, 4], vec![3]].into_iter().collect();
+        let probe: &[u8] = &[1, 3];
+        assert_eq!(set.ceiling(probe), Some(&vec![1u8, 4]));
+        assert_eq!(set.lower(probe), Some(&vec![1u8, 2]));
+        assert_eq!(set.higher_remove(probe), Some(vec![1u8, 4]));
+        assert_eq!(set.floor_remove(probe), Some(vec![1u8, 2]));
+        assert_eq!(set.len(), 1);
+    }
+
+    // Drains `iter`, checks that it keeps returning `None` afterwards and returns how many items
+    // it yielded.
+    fn drain_fused<I: FusedIterator>(mut iter: I) -> usize {
+        let count = iter.by_ref().count();
+        for _ in 0..3 {
+            assert!(iter.next().is_none());
+        }
+        count
+    }
+
+    #[test]
+    fn test_iters_are_fused() {
+        let mut set: BTreeSet<u32> = (1u32..6).collect();
+        let other: BTreeSet<u32> = vec![4u32, 8].into_iter().collect();
+        assert_eq!(drain_fused(set.range_iter(&2, &4)), 2);
+        assert_eq!(drain_fused(union_iter(&[&set, &other])), 6);
+        assert_eq!(drain_fused(set.range_remove_iter(&2, &4)), 2);
+    }
+
+    #[test]
+    fn test_clone_range_iter() {
+        let set: BTreeSet<u32> = (1u32..8).collect();
+        let mut iter = set.range_iter(&2, &6);
+        assert_eq!(iter.next(), Some(&2u32));
+        let copy = iter.clone();
+        assert_eq!(iter.next_back(), Some(&5u32));
+        assert_eq!(copy.cloned().collect::<Vec<u32>>(), vec![3u32, 4, 5]);
+        assert_eq!(iter.cloned().collect::<Vec<u32>>(), vec![3u32, 4]);
+        assert!(format!("{:?}", set.range_iter(&2, &4)).starts_with("BTreeSetRangeIter"));
+    }
 }
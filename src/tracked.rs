@@ -0,0 +1,297 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::btree_map::{BTreeMap, self};
+use std::ops::Bound::{Included, Excluded};
+
+/// A map which keeps the size and extreme keys of any number of registered key windows up to
+/// date as it is modified, so that reading them is O(1). Each modification costs O(log n) per
+/// registered window it touches on top of the cost of the modification itself.
+#[derive(Clone, Debug)]
+pub struct TrackedSortedMap<K, V> {
+    map: BTreeMap<K, V>,
+    windows: Vec<TrackedWindow<K>>
+}
+
+/// A handle on a window registered with a `TrackedSortedMap`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WindowId(usize);
+
+/// The cached statistics of the key-value pairs of a `TrackedSortedMap` whose keys lie in a
+/// window [from, to).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackedWindow<K> {
+    from: K,
+    to: K,
+    count: usize,
+    first: Option<K>,
+    last: Option<K>
+}
+
+impl<K> TrackedWindow<K> {
+    /// Returns the inclusive lower bound of this window.
+    pub fn from(&self) -> &K {
+        &self.from
+    }
+
+    /// Returns the exclusive upper bound of this window.
+    pub fn to(&self) -> &K {
+        &self.to
+    }
+
+    /// Returns the number of key-value pairs in this window.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the first (least) key in this window, or `None` if it is empty.
+    pub fn first_key(&self) -> Option<&K> {
+        self.first.as_ref()
+    }
+
+    /// Returns the last (greatest) key in this window, or `None` if it is empty.
+    pub fn last_key(&self) -> Option<&K> {
+        self.last.as_ref()
+    }
+}
+
+impl<K: Clone + Ord, V> Default for TrackedSortedMap<K, V> {
+    fn default() -> TrackedSortedMap<K, V> {
+        TrackedSortedMap::new()
+    }
+}
+
+impl<K: Ord> TrackedWindow<K> {
+    fn contains(&self, key: &K) -> bool {
+        self.from <= *key && *key < self.to
+    }
+}
+
+impl<K: Clone + Ord, V> TrackedSortedMap<K, V> {
+    /// Makes a new, empty `TrackedSortedMap` with no registered windows.
+    pub fn new() -> TrackedSortedMap<K, V> {
+        TrackedSortedMap::from_map(BTreeMap::new())
+    }
+
+    /// Makes a new `TrackedSortedMap` holding the key-value pairs of `map`, with no registered
+    /// windows.
+    pub fn from_map(map: BTreeMap<K, V>) -> TrackedSortedMap<K, V> {
+        TrackedSortedMap { map, windows: Vec::new() }
+    }
+
+    /// Returns a reference to the underlying map.
+    pub fn map(&self) -> &BTreeMap<K, V> {
+        &self.map
+    }
+
+    /// Consumes this `TrackedSortedMap` and returns the underlying map.
+    pub fn into_map(self) -> BTreeMap<K, V> {
+        self.map
+    }
+
+    /// Registers the window [from_key, to_key) and returns a handle on it. This walks the
+    /// window once to count it; a window whose `from_key` is not less than its `to_key` is
+    /// always empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sorted_collections::tracked::TrackedSortedMap;
+    ///
+    /// fn main() {
+    ///     let mut map = TrackedSortedMap::from_map((1u32..6).map(|k| (k, k)).collect());
+    ///     let id = map.register_window(2, 4);
+    ///     assert_eq!(map.window(id).count(), 2);
+    ///     map.remove(&2);
+    ///     assert_eq!(map.window(id).first_key(), Some(&3u32));
+    /// }
+    /// ```
+    pub fn register_window(&mut self, from_key: K, to_key: K) -> WindowId {
+        let mut window = TrackedWindow { from: from_key, to: to_key, count: 0, first: None,
+                                         last: None };
+        if window.from < window.to {
+            let mut range = self.map.range((Included(&window.from), Excluded(&window.to)));
+            window.count = range.clone().count();
+            window.first = range.next().map(|(k, _)| k.clone());
+            window.last = range.next_back().map(|(k, _)| k.clone()).or(window.first.clone());
+        }
+        self.windows.push(window);
+        WindowId(self.windows.len() - 1)
+    }
+
+    /// Returns the cached statistics of the window `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was not registered with this `TrackedSortedMap`.
+    pub fn window(&self, id: WindowId) -> &TrackedWindow<K> {
+        &self.windows[id.0]
+    }
+
+    /// Inserts a key-value pair, returning the value previously associated with the key, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = self.map.insert(key.clone(), value);
+        if old.is_none() {
+            for window in self.windows.iter_mut().filter(|window| window.contains(&key)) {
+                window.count += 1;
+                if window.first.as_ref().is_none_or(|first| key < *first) {
+                    window.first = Some(key.clone());
+                }
+                if window.last.as_ref().is_none_or(|last| key > *last) {
+                    window.last = Some(key.clone());
+                }
+            }
+        }
+        old
+    }
+
+    /// Removes a key from this map, returning the value associated with it, if any.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let old = self.map.remove(key);
+        if old.is_some() {
+            let map = &self.map;
+            for window in self.windows.iter_mut().filter(|window| window.contains(key)) {
+                window.count -= 1;
+                reprobe(map, window);
+            }
+        }
+        old
+    }
+
+    /// Removes the key-value pairs whose keys lie in the range [from_key, to_key), and returns a
+    /// by-value iterator over them in ascending key order. Nothing is removed if `from_key` is
+    /// not less than `to_key`.
+    pub fn range_remove(&mut self, from_key: &K, to_key: &K) -> btree_map::IntoIter<K, V> {
+        if from_key >= to_key {
+            return BTreeMap::new().into_iter();
+        }
+        let mut removed = self.map.split_off(from_key);
+        let mut above = removed.split_off(to_key);
+        self.map.append(&mut above);
+        if !removed.is_empty() {
+            let map = &self.map;
+            for window in self.windows.iter_mut() {
+                if window.from < window.to && window.from < *to_key && *from_key < window.to {
+                    let lo = if window.from > *from_key { &window.from } else { from_key };
+                    let hi = if window.to < *to_key { &window.to } else { to_key };
+                    window.count -= removed.range((Included(lo), Excluded(hi))).count();
+                    reprobe(map, window);
+                }
+            }
+        }
+        removed.into_iter()
+    }
+}
+
+// Refreshes the extreme keys of `window` after keys have been removed from it, probing `map` only
+// for those extremes which are no longer present.
+fn reprobe<K: Clone + Ord, V>(map: &BTreeMap<K, V>, window: &mut TrackedWindow<K>) {
+    if window.count == 0 {
+        window.first = None;
+        window.last = None;
+        return;
+    }
+    if !window.first.as_ref().is_some_and(|first| map.contains_key(first)) {
+        window.first = map.range((Included(&window.from), Excluded(&window.to))).next()
+                          .map(|(k, _)| k.clone());
+    }
+    if !window.last.as_ref().is_some_and(|last| map.contains_key(last)) {
+        window.last = map.range((Included(&window.from), Excluded(&window.to))).next_back()
+                         .map(|(k, _)| k.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{TrackedSortedMap, WindowId};
+
+    fn stats(map: &TrackedSortedMap<u32, u32>, id: WindowId) -> (usize, Option<u32>, Option<u32>) {
+        let window = map.window(id);
+        (window.count(), window.first_key().cloned(), window.last_key().cloned())
+    }
+
+    fn tracked() -> TrackedSortedMap<u32, u32> {
+        TrackedSortedMap::from_map(vec![1u32, 3, 5, 7, 9].into_iter().map(|k| (k, k)).collect())
+    }
+
+    #[test]
+    fn test_register_window() {
+        let mut map = tracked();
+        let all = map.register_window(0, 10);
+        let one = map.register_window(3, 4);
+        let none = map.register_window(10, 20);
+        let inverted = map.register_window(7, 3);
+        assert_eq!(stats(&map, all), (5, Some(1), Some(9)));
+        assert_eq!(stats(&map, one), (1, Some(3), Some(3)));
+        assert_eq!(stats(&map, none), (0, None, None));
+        assert_eq!(stats(&map, inverted), (0, None, None));
+        assert_eq!(map.window(one).from(), &3u32);
+        assert_eq!(map.window(one).to(), &4u32);
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut map = tracked();
+        let id = map.register_window(2, 8);
+        assert_eq!(map.insert(4, 4), None);
+        assert_eq!(stats(&map, id), (4, Some(3), Some(7)));
+        assert_eq!(map.insert(2, 2), None);
+        assert_eq!(map.insert(4, 40), Some(4));
+        assert_eq!(map.insert(8, 8), None);
+        assert_eq!(stats(&map, id), (5, Some(2), Some(7)));
+        let empty = map.register_window(10, 12);
+        map.insert(11, 11);
+        assert_eq!(stats(&map, empty), (1, Some(11), Some(11)));
+    }
+
+    #[test]
+    fn test_remove_extremes() {
+        let mut map = tracked();
+        let id = map.register_window(2, 8);
+        assert_eq!(map.remove(&3), Some(3));
+        assert_eq!(stats(&map, id), (2, Some(5), Some(7)));
+        assert_eq!(map.remove(&7), Some(7));
+        assert_eq!(stats(&map, id), (1, Some(5), Some(5)));
+        assert_eq!(map.remove(&4), None);
+        assert_eq!(map.remove(&5), Some(5));
+        assert_eq!(stats(&map, id), (0, None, None));
+        map.insert(6, 6);
+        assert_eq!(stats(&map, id), (1, Some(6), Some(6)));
+    }
+
+    #[test]
+    fn test_overlapping_windows() {
+        let mut map = tracked();
+        let lo = map.register_window(0, 6);
+        let hi = map.register_window(4, 10);
+        assert_eq!(stats(&map, lo), (3, Some(1), Some(5)));
+        assert_eq!(stats(&map, hi), (3, Some(5), Some(9)));
+        map.remove(&5);
+        assert_eq!(stats(&map, lo), (2, Some(1), Some(3)));
+        assert_eq!(stats(&map, hi), (2, Some(7), Some(9)));
+        map.insert(4, 4);
+        assert_eq!(stats(&map, lo), (3, Some(1), Some(4)));
+        assert_eq!(stats(&map, hi), (3, Some(4), Some(9)));
+    }
+
+    #[test]
+    fn test_range_remove() {
+        let mut map = tracked();
+        let lo = map.register_window(0, 6);
+        let hi = map.register_window(4, 10);
+        let far = map.register_window(20, 30);
+        let removed: Vec<(u32, u32)> = map.range_remove(&3, &8).collect();
+        assert_eq!(removed, vec![(3u32, 3u32), (5, 5), (7, 7)]);
+        assert_eq!(stats(&map, lo), (1, Some(1), Some(1)));
+        assert_eq!(stats(&map, hi), (1, Some(9), Some(9)));
+        assert_eq!(stats(&map, far), (0, None, None));
+        assert_eq!(map.range_remove(&8, &3).count(), 0);
+        let expected: BTreeMap<u32, u32> = vec![(1u32, 1u32), (9, 9)].into_iter().collect();
+        assert_eq!(map.into_map(), expected);
+    }
+}
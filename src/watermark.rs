@@ -0,0 +1,222 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::btree_map::{BTreeMap, self};
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+/// A map paired with a watermark which only ever moves forward. Every key in the map is at least
+/// the watermark: keys below it are rejected on insertion, and advancing the watermark drains
+/// every key it passes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watermarked<K, V> {
+    map: BTreeMap<K, V>,
+    watermark: Option<K>
+}
+
+impl<K: Ord, V> Watermarked<K, V> {
+    /// Makes a new, empty `Watermarked` with no watermark, so that any key may be inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sorted_collections::Watermarked;
+    ///
+    /// fn main() {
+    ///     let mut map: Watermarked<u32, u32> = Watermarked::new();
+    ///     assert_eq!(map.watermark(), None);
+    ///     assert_eq!(map.insert(0, 0), Ok(None));
+    /// }
+    /// ```
+    pub fn new() -> Watermarked<K, V> {
+        Watermarked { map: BTreeMap::new(), watermark: None }
+    }
+
+    /// Makes a new, empty `Watermarked` whose watermark starts at `watermark`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sorted_collections::Watermarked;
+    ///
+    /// fn main() {
+    ///     let mut map: Watermarked<u32, u32> = Watermarked::with_watermark(10);
+    ///     assert_eq!(map.watermark(), Some(&10u32));
+    ///     assert!(map.insert(9, 9).is_err());
+    /// }
+    /// ```
+    pub fn with_watermark(watermark: K) -> Watermarked<K, V> {
+        Watermarked { map: BTreeMap::new(), watermark: Some(watermark) }
+    }
+
+    /// Returns a reference to the current watermark, or `None` if it has never been set.
+    pub fn watermark(&self) -> Option<&K> {
+        self.watermark.as_ref()
+    }
+
+    /// Returns a reference to the map of key-value pairs at or above the watermark.
+    pub fn map(&self) -> &BTreeMap<K, V> {
+        &self.map
+    }
+
+    /// Consumes this `Watermarked` and returns its map.
+    pub fn into_map(self) -> BTreeMap<K, V> {
+        self.map
+    }
+
+    /// Returns the number of key-value pairs at or above the watermark.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if there are no key-value pairs at or above the watermark.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Inserts a key-value pair, returning the value previously associated with the key, if any.
+    /// Returns an error holding the pair instead if the key is below the watermark.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sorted_collections::Watermarked;
+    /// use sorted_collections::watermark::LateKeyError;
+    ///
+    /// fn main() {
+    ///     let mut map = Watermarked::with_watermark(10u32);
+    ///     assert_eq!(map.insert(10, "a"), Ok(None));
+    ///     assert_eq!(map.insert(10, "b"), Ok(Some("a")));
+    ///     assert_eq!(map.insert(9, "c"), Err(LateKeyError { key: 9, value: "c" }));
+    /// }
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, LateKeyError<K, V>> {
+        if self.watermark.as_ref().is_some_and(|watermark| key < *watermark) {
+            return Err(LateKeyError { key, value });
+        }
+        Ok(self.map.insert(key, value))
+    }
+
+    /// Moves the watermark forward to `to`, and removes every key-value pair whose key is below
+    /// it, returning a by-value iterator over them in ascending key order. Returns an error
+    /// holding `to` instead, and leaves this `Watermarked` untouched, if `to` is below the
+    /// current watermark.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sorted_collections::Watermarked;
+    ///
+    /// fn main() {
+    ///     let mut map = Watermarked::new();
+    ///     for key in 1u32..6 {
+    ///         map.insert(key, key).unwrap();
+    ///     }
+    ///     let drained: Vec<(u32, u32)> = map.advance_watermark(3).unwrap().collect();
+    ///     assert_eq!(drained, vec![(1u32, 1u32), (2, 2)]);
+    ///     assert_eq!(map.watermark(), Some(&3u32));
+    ///     assert!(map.advance_watermark(2).is_err());
+    /// }
+    /// ```
+    pub fn advance_watermark(&mut self, to: K)
+                             -> Result<btree_map::IntoIter<K, V>, WatermarkRegressionError<K>> {
+        if self.watermark.as_ref().is_some_and(|watermark| to < *watermark) {
+            return Err(WatermarkRegressionError { to });
+        }
+        let above = self.map.split_off(&to);
+        self.watermark = Some(to);
+        Ok(mem::replace(&mut self.map, above).into_iter())
+    }
+}
+
+impl<K: Ord, V> Default for Watermarked<K, V> {
+    fn default() -> Watermarked<K, V> {
+        Watermarked::new()
+    }
+}
+
+/// The error returned by `Watermarked::insert` when the key is below the watermark.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LateKeyError<K, V> {
+    /// The rejected key.
+    pub key: K,
+    /// The rejected value.
+    pub value: V
+}
+
+impl<K: fmt::Debug, V> fmt::Display for LateKeyError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "key {:?} is below the watermark", self.key)
+    }
+}
+impl<K: fmt::Debug, V: fmt::Debug> Error for LateKeyError<K, V> {}
+
+/// The error returned by `Watermarked::advance_watermark` when the new watermark is below the
+/// current one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatermarkRegressionError<K> {
+    /// The rejected watermark.
+    pub to: K
+}
+
+impl<K: fmt::Debug> fmt::Display for WatermarkRegressionError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "watermark {:?} is below the current watermark", self.to)
+    }
+}
+impl<K: fmt::Debug> Error for WatermarkRegressionError<K> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{LateKeyError, Watermarked, WatermarkRegressionError};
+
+    #[test]
+    fn test_insert() {
+        let mut map = Watermarked::new();
+        assert_eq!(map.insert(5u32, 5u32), Ok(None));
+        assert_eq!(map.insert(1, 1), Ok(None));
+        assert_eq!(map.insert(5, 50), Ok(Some(5)));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_insert_late_key() {
+        let mut map = Watermarked::with_watermark(3u32);
+        assert_eq!(map.insert(2, 2u32), Err(LateKeyError { key: 2u32, value: 2u32 }));
+        assert_eq!(map.insert(3, 3), Ok(None));
+        map.advance_watermark(4).unwrap();
+        assert_eq!(map.insert(3, 30), Err(LateKeyError { key: 3u32, value: 30u32 }));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_advance_watermark() {
+        let mut map = Watermarked::new();
+        for key in [1u32, 2, 3, 7, 8] {
+            map.insert(key, key * 10).unwrap();
+        }
+        let drained: Vec<(u32, u32)> = map.advance_watermark(3).unwrap().collect();
+        assert_eq!(drained, vec![(1u32, 10u32), (2, 20)]);
+        let drained: Vec<(u32, u32)> = map.advance_watermark(3).unwrap().collect();
+        assert_eq!(drained, vec![]);
+        let drained: Vec<(u32, u32)> = map.advance_watermark(6).unwrap().collect();
+        assert_eq!(drained, vec![(3u32, 30u32)]);
+        assert_eq!(map.advance_watermark(7).unwrap().count(), 0);
+        assert_eq!(map.watermark(), Some(&7u32));
+        assert_eq!(map.into_map().into_iter().collect::<Vec<(u32, u32)>>(),
+                   vec![(7u32, 70u32), (8, 80)]);
+    }
+
+    #[test]
+    fn test_advance_watermark_regression() {
+        let mut map = Watermarked::with_watermark(5u32);
+        map.insert(6, 6u32).unwrap();
+        assert_eq!(map.advance_watermark(4).err(), Some(WatermarkRegressionError { to: 4u32 }));
+        assert_eq!(map.watermark(), Some(&5u32));
+        assert_eq!(map.len(), 1);
+    }
+}